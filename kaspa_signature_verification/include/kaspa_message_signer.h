@@ -0,0 +1,120 @@
+/*
+ * C ABI for kaspa_message_signer.
+ *
+ * Memory ownership:
+ *   - String arguments are borrowed: NUL-terminated UTF-8, only read during the call.
+ *     The library never keeps or frees them.
+ *   - Strings returned as `char *` belong to the caller and must be released with
+ *     kaspa_string_free(), exactly once. Never free them with free().
+ *   - kaspa_last_error() returns a string owned by the library. It stays valid until
+ *     the next kaspa_* call on the same thread and must not be freed.
+ *   - Output structs and integers (KaspaAddressInfo *, uint32_t *) are caller-allocated;
+ *     the library only writes to them on success.
+ *
+ * Errors: functions returning int32_t use negative KASPA_ERR_* codes; functions
+ * returning char * return NULL. Either way, kaspa_last_error() describes the failure.
+ * All functions are thread-safe.
+ *
+ * Panics: a library built with unwinding (`cargo build --lib --profile release-lib`)
+ * reports an internal panic as KASPA_ERR_INTERNAL or NULL. A library built with
+ * panic = "abort", like the plain release profile, aborts the process instead.
+ */
+
+
+#ifndef KASPA_MESSAGE_SIGNER_H
+#define KASPA_MESSAGE_SIGNER_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stdint.h>
+
+// The call succeeded.
+#define KASPA_OK 0
+
+// A required pointer argument was NULL.
+#define KASPA_ERR_NULL_POINTER -1
+
+// A string argument was not valid UTF-8.
+#define KASPA_ERR_INVALID_UTF8 -2
+
+// An argument could not be parsed (address, xpub, signature, key...).
+#define KASPA_ERR_INVALID_INPUT -3
+
+// The search window is 0 or larger than KASPA_MAX_SEARCH_WINDOW.
+#define KASPA_ERR_WINDOW -4
+
+// The library hit an internal error (a Rust panic) and the call was abandoned.
+// Only libraries built with unwinding (the release-lib profile) return it; with
+// panic = "abort", as in the plain release profile, a panic aborts the process.
+#define KASPA_ERR_INTERNAL -5
+
+// Largest window kaspa_xpub_find_address searches.
+#define KASPA_MAX_SEARCH_WINDOW 10000000
+
+// A parsed address.
+typedef struct KaspaAddressInfo {
+  // 0 mainnet, 1 testnet, 2 simnet, 3 devnet
+  uint8_t network;
+  // 0 Schnorr public key, 1 ECDSA public key, 8 script hash
+  uint8_t version;
+  // Number of bytes used in `payload` (32 or 33)
+  uint8_t payload_len;
+  uint8_t payload[33];
+} KaspaAddressInfo;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message of the last failed call on this thread, or NULL if the last call succeeded.
+//
+// The string is owned by the library and stays valid until the next kaspa_* call on
+// the same thread. Do not free it.
+const char *kaspa_last_error(void);
+
+// Releases a string returned by this library. Passing NULL does nothing.
+//
+// # Safety
+// `value` must be NULL or a pointer returned by a kaspa_* function that has not been freed yet.
+void kaspa_string_free(char *value);
+
+// Verifies a hex compact ECDSA signature of `message` (double SHA-256) by `address`.
+//
+// Returns 1 if the signature is valid, 0 if it is not, or a negative KASPA_ERR_* code.
+//
+// # Safety
+// All arguments must be NUL-terminated strings, readable for the duration of the call.
+int32_t kaspa_verify_signature(const char *message, const char *signature_hex, const char *address);
+
+// Parses and validates an address, filling `out`.
+//
+// Returns KASPA_OK, or a negative KASPA_ERR_* code (KASPA_ERR_INVALID_INPUT for a bad address).
+//
+// # Safety
+// `address` must be a NUL-terminated string and `out` must point to a writable KaspaAddressInfo.
+int32_t kaspa_address_parse(const char *address, struct KaspaAddressInfo *out);
+
+// Mainnet Schnorr address at m/<chain>/<index> below an account xpub, or NULL on error.
+// Free the result with kaspa_string_free.
+//
+// # Safety
+// `xpub` must be a NUL-terminated string.
+char *kaspa_xpub_derive_address(const char *xpub, uint32_t chain, uint32_t index);
+
+// Searches the first `window` receive addresses (m/0/i) of an account xpub for `address`.
+//
+// Returns 1 and writes the index to `out_index` if found, 0 if not, or a negative
+// KASPA_ERR_* code. `window` must be between 1 and KASPA_MAX_SEARCH_WINDOW.
+//
+// # Safety
+// `xpub` and `address` must be NUL-terminated strings; `out_index` must be NULL or writable.
+int32_t kaspa_xpub_find_address(const char *xpub,
+                                const char *address,
+                                uint32_t window,
+                                uint32_t *out_index);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* KASPA_MESSAGE_SIGNER_H */
@@ -0,0 +1,31 @@
+from typing import Optional
+
+class ExtendedPublicKey:
+    """An account extended public key (xpub)."""
+
+    def __init__(self, xpub: str) -> None: ...
+    def derive_address(self, index: int, chain: int = 0) -> str:
+        """Mainnet address at m/<chain>/<index>."""
+    def find_address(self, address: str, window: int = 1000, threads: Optional[int] = None) -> Optional[int]:
+        """Index of `address` among the first `window` receive addresses, or None. Releases the GIL.
+
+        Raises ValueError unless 1 <= window <= 10,000,000.
+        """
+
+def verify_signature(message: str, signature: str, address: str) -> bool:
+    """Checks a hex compact ECDSA signature of `message` by `address`.
+
+    Returns False for a wrong signature and raises ValueError for malformed input.
+    """
+
+def verify_schnorr_signature(message: str, signature: str, address: str) -> bool:
+    """Checks a hex BIP340 Schnorr signature of `message` by a Schnorr `address`.
+
+    Returns False for a wrong signature and raises ValueError for malformed input.
+    """
+
+def load_xpub(xpub: str) -> ExtendedPublicKey:
+    """Parses an xpub string."""
+
+def derive_address(xpub: str, index: int, chain: int = 0) -> str:
+    """Mainnet address at m/<chain>/<index> below an xpub string."""
@@ -0,0 +1,409 @@
+// Batch verification of (address, message, signature) rows from CSV or JSONL files.
+//
+// Rows are split across threads. ECDSA rows (this tool's signatures) are checked
+// one by one; Schnorr rows (the reference wallet's personal message signatures)
+// are checked together with the BIP340 batch equation, and only re-checked one by
+// one when the batch fails, to find the bad rows.
+use k256::elliptic_curve::bigint::U256;
+use k256::elliptic_curve::group::Group;
+use k256::elliptic_curve::ops::{LinearCombinationExt, Reduce};
+use k256::elliptic_curve::point::DecompactPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::{AffinePoint, FieldBytes, ProjectivePoint, Scalar};
+use kaspa_addresses::{Address, Version};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::thread;
+
+use crate::{personal_message_hash, verify_signature};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+}
+
+impl Format {
+    /// Picks the format from `--format`, or else from the file extension.
+    pub fn detect(explicit: Option<&str>, path: &Path) -> Result<Format, Box<dyn std::error::Error>> {
+        let name = explicit
+            .map(str::to_string)
+            .or_else(|| path.extension().map(|ext| ext.to_string_lossy().to_lowercase()))
+            .unwrap_or_default();
+        match name.as_str() {
+            "csv" => Ok(Format::Csv),
+            "jsonl" | "ndjson" | "json" => Ok(Format::Jsonl),
+            _ => Err("Cannot tell the input format: use --format csv or --format jsonl".into()),
+        }
+    }
+}
+
+/// One input row, with its line number in the file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchRow {
+    #[serde(skip)]
+    pub line: usize,
+    pub address: String,
+    pub message: String,
+    pub signature: String,
+    /// "ecdsa" (default) or "schnorr"
+    #[serde(default)]
+    pub algorithm: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RowResult {
+    pub line: usize,
+    pub address: String,
+    pub valid: bool,
+    pub reason: String,
+}
+
+impl RowResult {
+    fn new(row: &BatchRow, reason: Option<String>) -> RowResult {
+        RowResult {
+            line: row.line,
+            address: row.address.clone(),
+            valid: reason.is_none(),
+            reason: reason.unwrap_or_default(),
+        }
+    }
+}
+
+/// Reads every row of the file. Rows that can't be read become failed results.
+pub fn read_rows(path: &Path, format: Format) -> Result<(Vec<BatchRow>, Vec<RowResult>), Box<dyn std::error::Error>> {
+    let mut rows = Vec::new();
+    let mut unreadable = Vec::new();
+    match format {
+        Format::Csv => {
+            let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+            let headers = reader.headers()?.clone();
+            for record in reader.records() {
+                let record = match record {
+                    Ok(record) => record,
+                    // Reading the file failed: nothing after this can be read either
+                    Err(e) if matches!(e.kind(), csv::ErrorKind::Io(_)) => return Err(e.into()),
+                    Err(e) => {
+                        let line = e.position().map(|p| p.line() as usize).unwrap_or(0);
+                        unreadable.push(unreadable_row(line, e.to_string()));
+                        continue;
+                    }
+                };
+                let line = record.position().map(|p| p.line() as usize).unwrap_or(0);
+                match record.deserialize::<BatchRow>(Some(&headers)) {
+                    Ok(row) => rows.push(BatchRow { line, ..row }),
+                    Err(e) => unreadable.push(unreadable_row(line, e.to_string())),
+                }
+            }
+        }
+        Format::Jsonl => {
+            let contents = std::fs::read(path)?;
+            for (i, text) in contents.split(|byte| *byte == b'\n').enumerate() {
+                if text.trim_ascii().is_empty() {
+                    continue;
+                }
+                match serde_json::from_slice::<BatchRow>(text) {
+                    Ok(row) => rows.push(BatchRow { line: i + 1, ..row }),
+                    Err(e) => unreadable.push(unreadable_row(i + 1, e.to_string())),
+                }
+            }
+        }
+    }
+    Ok((rows, unreadable))
+}
+
+fn unreadable_row(line: usize, error: String) -> RowResult {
+    RowResult {
+        line,
+        address: String::new(),
+        valid: false,
+        reason: format!("unreadable row: {}", error),
+    }
+}
+
+/// Writes the per-row results in the same format as the input.
+pub fn write_results(path: &Path, format: Format, results: &[RowResult]) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_path(path)?;
+            for result in results {
+                writer.serialize(result)?;
+            }
+            writer.flush()?;
+        }
+        Format::Jsonl => {
+            let mut out = String::new();
+            for result in results {
+                out.push_str(&serde_json::to_string(result)?);
+                out.push('\n');
+            }
+            std::fs::write(path, out)?;
+        }
+    }
+    Ok(())
+}
+
+// A Schnorr row reduced to the values of the BIP340 equation s*G = R + e*P
+struct SchnorrItem {
+    index: usize,
+    r: ProjectivePoint,
+    s: Scalar,
+    e: Scalar,
+    public_key: ProjectivePoint,
+}
+
+fn tagged_hash(tag: &[u8]) -> Sha256 {
+    let tag_hash = Sha256::digest(tag);
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    hasher
+}
+
+fn schnorr_item(index: usize, row: &BatchRow) -> Result<SchnorrItem, String> {
+    let address = Address::try_from(row.address.as_str()).map_err(|e| format!("invalid address: {}", e))?;
+    if address.version != Version::PubKey {
+        return Err("Schnorr signatures need a Schnorr (PubKey) address".to_string());
+    }
+    let signature = hex::decode(row.signature.trim()).map_err(|_| "malformed signature".to_string())?;
+    if signature.len() != 64 {
+        return Err(format!("malformed signature: expected 64 bytes, got {}", signature.len()));
+    }
+
+    // lift_x of the public key and of r, both with even y
+    let lift_x = |x: &[u8]| Option::<AffinePoint>::from(AffinePoint::decompact(FieldBytes::from_slice(x)));
+    let public_key = lift_x(&address.payload).ok_or("invalid public key in address")?;
+    let r = lift_x(&signature[..32]).ok_or("signature does not match")?;
+    let s = Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(&signature[32..])))
+        .ok_or("malformed signature: s out of range")?;
+    let digest = personal_message_hash(&row.message);
+    let e = <Scalar as Reduce<U256>>::reduce_bytes(
+        &tagged_hash(b"BIP0340/challenge")
+            .chain_update(&signature[..32])
+            .chain_update(&address.payload)
+            .chain_update(digest)
+            .finalize(),
+    );
+
+    Ok(SchnorrItem {
+        index,
+        r: r.into(),
+        s,
+        e,
+        public_key: public_key.into(),
+    })
+}
+
+/// Checks sum(a_i * s_i) * G == sum(a_i * R_i) + sum(a_i * e_i * P_i) for random a_i (a_0 = 1),
+/// with a single multi-scalar multiplication.
+fn schnorr_batch_verify(items: &[SchnorrItem], rng: &mut impl Rng) -> bool {
+    let mut terms = Vec::with_capacity(items.len() * 2 + 1);
+    let mut s_sum = Scalar::ZERO;
+    for (i, item) in items.iter().enumerate() {
+        let a = if i == 0 {
+            Scalar::ONE
+        } else {
+            let bytes: [u8; 32] = rng.r#gen();
+            <Scalar as Reduce<U256>>::reduce_bytes(FieldBytes::from_slice(&bytes))
+        };
+        s_sum += a * item.s;
+        terms.push((item.r, a));
+        terms.push((item.public_key, a * item.e));
+    }
+    terms.push((ProjectivePoint::GENERATOR, -s_sum));
+    ProjectivePoint::lincomb_ext(terms.as_slice()).is_identity().into()
+}
+
+fn verify_ecdsa_row(row: &BatchRow) -> Option<String> {
+    match verify_signature(&row.message, row.signature.trim(), &row.address) {
+        Ok(true) => None,
+        Ok(false) => Some("signature does not match".to_string()),
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+fn verify_chunk(rows: &[BatchRow]) -> Vec<RowResult> {
+    let mut reasons: Vec<Option<String>> = vec![None; rows.len()];
+    let mut schnorr_items = Vec::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        match row.algorithm.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("") | Some("ecdsa") => reasons[i] = verify_ecdsa_row(row),
+            Some("schnorr") => match schnorr_item(i, row) {
+                Ok(item) => schnorr_items.push(item),
+                Err(reason) => reasons[i] = Some(reason),
+            },
+            Some(other) => reasons[i] = Some(format!("unsupported algorithm: {}", other)),
+        }
+    }
+
+    // One batch for all Schnorr rows; if it fails, every row is checked alone
+    let mut rng = rand::thread_rng();
+    if !schnorr_items.is_empty() && !schnorr_batch_verify(&schnorr_items, &mut rng) {
+        for item in &schnorr_items {
+            if !schnorr_batch_verify(std::slice::from_ref(item), &mut rng) {
+                reasons[item.index] = Some("signature does not match".to_string());
+            }
+        }
+    }
+
+    rows.iter()
+        .zip(reasons)
+        .map(|(row, reason)| RowResult::new(row, reason))
+        .collect()
+}
+
+/// Verifies a single row, exactly as it would be checked in a batch.
+pub fn verify_row(row: &BatchRow) -> RowResult {
+    verify_chunk(std::slice::from_ref(row)).remove(0)
+}
+
+/// Verifies all rows across `num_threads` threads, keeping the input order.
+pub fn verify_rows_parallel(rows: Vec<BatchRow>, num_threads: usize) -> Result<Vec<RowResult>, Box<dyn std::error::Error>> {
+    // Calculate chunk size for each thread
+    let chunk_size = std::cmp::max(1, rows.len().div_ceil(num_threads));
+    let mut handles = vec![];
+
+    for chunk in rows.chunks(chunk_size) {
+        let chunk = chunk.to_vec();
+        let handle = thread::spawn(move || verify_chunk(&chunk));
+        handles.push(handle);
+    }
+
+    // Wait for all threads to complete
+    let mut results = Vec::with_capacity(rows.len());
+    for handle in handles {
+        match handle.join() {
+            Ok(chunk_results) => results.extend(chunk_results),
+            Err(_) => return Err("Thread panicked".into()),
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn schnorr_rows() -> Vec<BatchRow> {
+        let vectors: Value = serde_json::from_str(include_str!("../tests/vectors/message_signing.json")).unwrap();
+        let mut rows: Vec<BatchRow> = serde_json::from_value(vectors["schnorr"].clone()).unwrap();
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.line = i + 1;
+            row.algorithm = Some("schnorr".to_string());
+        }
+        rows
+    }
+
+    fn items(rows: &[BatchRow]) -> Vec<SchnorrItem> {
+        rows.iter().enumerate().map(|(i, row)| schnorr_item(i, row).unwrap()).collect()
+    }
+
+    // Replaces s with s + delta (mod n)
+    fn shift_s(row: &mut BatchRow, delta: Scalar) {
+        let mut signature = hex::decode(&row.signature).unwrap();
+        let s = Scalar::from_repr(*FieldBytes::from_slice(&signature[32..])).unwrap() + delta;
+        signature[32..].copy_from_slice(&s.to_bytes());
+        row.signature = hex::encode(signature);
+    }
+
+    #[test]
+    fn batch_equation_accepts_valid_signatures() {
+        let rows = schnorr_rows();
+        assert!(rows.len() >= 4);
+        let mut rng = rand::thread_rng();
+        assert!(schnorr_batch_verify(&items(&rows), &mut rng));
+        for row in &rows {
+            assert!(schnorr_batch_verify(&items(std::slice::from_ref(row)), &mut rng), "{}", row.message);
+        }
+    }
+
+    #[test]
+    fn batch_equation_rejects_invalid_signatures() {
+        let mut rng = rand::thread_rng();
+
+        let mut rows = schnorr_rows();
+        shift_s(&mut rows[1], Scalar::ONE);
+        assert!(!schnorr_batch_verify(&items(&rows), &mut rng));
+
+        let mut rows = schnorr_rows();
+        rows[2].message.push('!');
+        assert!(!schnorr_batch_verify(&items(&rows), &mut rng));
+
+        let mut rows = schnorr_rows();
+        let signature = rows[0].signature.clone();
+        rows[0].signature = rows[1].signature.clone();
+        rows[1].signature = signature;
+        assert!(!schnorr_batch_verify(&items(&rows), &mut rng));
+
+        // Errors that cancel out in a plain sum of the equations are caught by the random weights
+        let mut rows = schnorr_rows();
+        shift_s(&mut rows[0], Scalar::ONE);
+        shift_s(&mut rows[3], -Scalar::ONE);
+        assert!(!schnorr_batch_verify(&items(&rows), &mut rng));
+    }
+
+    #[test]
+    fn failed_batch_pinpoints_bad_rows() {
+        let mut rows = schnorr_rows();
+        shift_s(&mut rows[1], Scalar::ONE);
+        rows[2].signature = "00".repeat(63);
+        rows[3].algorithm = Some("bls".to_string());
+
+        let results = verify_chunk(&rows);
+        let valid: Vec<bool> = results.iter().map(|result| result.valid).collect();
+        assert_eq!(valid[..4], [true, false, false, false]);
+        assert!(valid[4..].iter().all(|valid| *valid));
+        assert_eq!(results[1].reason, "signature does not match");
+        assert!(results[2].reason.starts_with("malformed signature"));
+        assert_eq!(results[3].reason, "unsupported algorithm: bls");
+        assert_eq!(results.iter().map(|result| result.line).collect::<Vec<_>>(), (1..=rows.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parallel_results_keep_input_order() {
+        let mut rows: Vec<BatchRow> = (0..5).flat_map(|_| schnorr_rows()).collect();
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.line = i + 1;
+        }
+        shift_s(&mut rows[7], Scalar::ONE);
+        for num_threads in [1, 3, 64] {
+            let results = verify_rows_parallel(rows.clone(), num_threads).unwrap();
+            assert_eq!(results.len(), rows.len());
+            for (i, result) in results.iter().enumerate() {
+                assert_eq!((result.line, result.valid), (i + 1, i != 7));
+            }
+        }
+    }
+
+    #[test]
+    fn unreadable_rows_become_failed_results() {
+        let row = schnorr_rows().remove(0);
+        let csv_row = format!("{},{},{},schnorr\n", row.address, row.message, row.signature);
+        let json_row = format!(
+            "{{\"address\":\"{}\",\"message\":\"{}\",\"signature\":\"{}\",\"algorithm\":\"schnorr\"}}\n",
+            row.address, row.message, row.signature
+        );
+        // The middle row is not UTF-8
+        let cases = [
+            (Format::Csv, [b"address,message,signature,algorithm\n".to_vec(), csv_row.clone().into(), b"kaspa:q,\xff,00,schnorr\n".to_vec(), csv_row.into()]),
+            (Format::Jsonl, [Vec::new(), json_row.clone().into(), b"{\"message\":\"\xff\"}\n".to_vec(), json_row.into()]),
+        ];
+        for (format, contents) in cases {
+            let path = std::env::temp_dir().join(format!("kaspa_batch_test_{}_{:?}", std::process::id(), format));
+            std::fs::write(&path, contents.concat()).unwrap();
+            let read = read_rows(&path, format);
+            let _ = std::fs::remove_file(&path);
+
+            let (rows, unreadable) = read.unwrap();
+            let first = if format == Format::Csv { 2 } else { 1 };
+            assert_eq!(rows.iter().map(|row| row.line).collect::<Vec<_>>(), vec![first, first + 2], "{:?}", format);
+            assert_eq!(unreadable.len(), 1, "{:?}", format);
+            assert_eq!((unreadable[0].line, unreadable[0].valid), (first + 1, false), "{:?}", format);
+            assert!(unreadable[0].reason.starts_with("unreadable row: "));
+        }
+    }
+}
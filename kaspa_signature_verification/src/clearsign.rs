@@ -0,0 +1,231 @@
+// ASCII-armored clearsigned messages, in the spirit of PGP and Bitcoin signed-message blocks.
+//
+// The signature covers the canonical form of the message only, so a clearsigned
+// message can also be checked with the plain `verify_signature` flow:
+// - CRLF and lone CR line endings become LF
+// - trailing spaces and tabs are removed from every line
+// - trailing empty lines are removed and there is no final newline
+//
+// Message lines starting with '-' are dash-escaped as "- -..." inside the armor.
+use kaspa_addresses::{Address, Prefix};
+
+use crate::payload::SIGNATURE_ALGORITHM;
+
+const BEGIN_MESSAGE: &str = "-----BEGIN KASPA SIGNED MESSAGE-----";
+const BEGIN_SIGNATURE: &str = "-----BEGIN KASPA SIGNATURE-----";
+const END_SIGNATURE: &str = "-----END KASPA SIGNATURE-----";
+
+/// A parsed clearsigned message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClearsignedMessage {
+    pub address: String,
+    pub network: String,
+    pub algorithm: String,
+    pub message: String,
+    pub signature: String,
+}
+
+/// Applies the canonicalization rules above to a message.
+pub fn canonicalize(message: &str) -> String {
+    let normalized = message.replace("\r\n", "\n").replace('\r', "\n");
+    let lines: Vec<&str> = normalized
+        .split('\n')
+        .map(|line| line.trim_end_matches([' ', '\t']))
+        .collect();
+    let end = lines.iter().rposition(|line| !line.is_empty()).map_or(0, |i| i + 1);
+    lines[..end].join("\n")
+}
+
+/// Name of the network an address belongs to, as written in the Network header.
+pub fn network_name(prefix: Prefix) -> &'static str {
+    match prefix {
+        Prefix::Mainnet => "mainnet",
+        Prefix::Testnet => "testnet",
+        Prefix::Simnet => "simnet",
+        Prefix::Devnet => "devnet",
+    }
+}
+
+impl ClearsignedMessage {
+    /// Wraps an already canonical message and its signature.
+    pub fn new(message: &str, address: &Address, signature: &str) -> ClearsignedMessage {
+        ClearsignedMessage {
+            address: address.to_string(),
+            network: network_name(address.prefix).to_string(),
+            algorithm: SIGNATURE_ALGORITHM.to_string(),
+            message: message.to_string(),
+            signature: signature.to_string(),
+        }
+    }
+
+    pub fn parse(armored: &str) -> Result<ClearsignedMessage, Box<dyn std::error::Error>> {
+        let normalized = armored.replace("\r\n", "\n").replace('\r', "\n");
+        let mut lines = normalized.split('\n').map(|line| line.trim_end_matches([' ', '\t']));
+
+        // Anything before the armor header is ignored, as with PGP
+        lines
+            .by_ref()
+            .find(|line| *line == BEGIN_MESSAGE)
+            .ok_or("Missing '-----BEGIN KASPA SIGNED MESSAGE-----' header")?;
+
+        let (mut address, mut network, mut algorithm) = (None, None, None);
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid armor header: {}", line))?;
+            let value = value.trim().to_string();
+            match key.trim() {
+                "Address" => address = Some(value),
+                "Network" => network = Some(value),
+                "Algorithm" => algorithm = Some(value),
+                other => return Err(format!("Unknown armor header: {}", other).into()),
+            }
+        }
+
+        let mut message_lines = Vec::new();
+        let mut found_signature = false;
+        for line in lines.by_ref() {
+            if line == BEGIN_SIGNATURE {
+                found_signature = true;
+                break;
+            }
+            if let Some(unescaped) = line.strip_prefix("- ") {
+                message_lines.push(unescaped);
+            } else if line.starts_with('-') {
+                return Err(format!("Message line is not dash-escaped: {}", line).into());
+            } else {
+                message_lines.push(line);
+            }
+        }
+        if !found_signature {
+            return Err("Missing '-----BEGIN KASPA SIGNATURE-----' line".into());
+        }
+
+        let mut signature = String::new();
+        let mut found_end = false;
+        for line in lines {
+            if line == END_SIGNATURE {
+                found_end = true;
+                break;
+            }
+            signature.push_str(line.trim());
+        }
+        if !found_end {
+            return Err("Missing '-----END KASPA SIGNATURE-----' line".into());
+        }
+
+        let message = ClearsignedMessage {
+            address: address.ok_or("Missing Address armor header")?,
+            network: network.ok_or("Missing Network armor header")?,
+            algorithm: algorithm.ok_or("Missing Algorithm armor header")?,
+            message: canonicalize(&message_lines.join("\n")),
+            signature,
+        };
+        message.check_headers()?;
+        Ok(message)
+    }
+
+    // The headers are not signed, so they must agree with what the signature is checked against
+    fn check_headers(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.algorithm != SIGNATURE_ALGORITHM {
+            return Err(format!("Unsupported signature algorithm: {}", self.algorithm).into());
+        }
+        let address = Address::try_from(self.address.as_str())?;
+        let expected_network = network_name(address.prefix);
+        if self.network != expected_network {
+            return Err(format!(
+                "Network header '{}' does not match the {} address",
+                self.network, expected_network
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ClearsignedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", BEGIN_MESSAGE)?;
+        writeln!(f, "Address: {}", self.address)?;
+        writeln!(f, "Network: {}", self.network)?;
+        writeln!(f, "Algorithm: {}", self.algorithm)?;
+        writeln!(f)?;
+        for line in self.message.split('\n') {
+            if line.starts_with('-') {
+                writeln!(f, "- {}", line)?;
+            } else {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        writeln!(f, "{}", BEGIN_SIGNATURE)?;
+        writeln!(f, "{}", self.signature)?;
+        writeln!(f, "{}", END_SIGNATURE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign_message, verify_signature};
+    use secp256k1::SecretKey;
+
+    const ADDRESS: &str = "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh";
+    const PRIVATE_KEY: &str = "24cd8d2875e8e17e4f88fbc554834a89b7b3e0c6f9d84fae36883604359ba440";
+
+    #[test]
+    fn canonicalization() {
+        assert_eq!(canonicalize("a\r\nb\rc\n"), "a\nb\nc");
+        assert_eq!(canonicalize("a \t\nb  \n\n\n"), "a\nb");
+        assert_eq!(canonicalize("  indented\n\nkept blank line"), "  indented\n\nkept blank line");
+        assert_eq!(canonicalize("\n \r\n\t"), "");
+        assert_eq!(canonicalize(&canonicalize("x \r\ny\t\r\n")), canonicalize("x \r\ny\t\r\n"));
+    }
+
+    #[test]
+    fn dash_escaping() {
+        let address = Address::try_from(ADDRESS).unwrap();
+        let message = "-----BEGIN KASPA SIGNATURE-----\n- item\nplain\n--";
+        let armored = ClearsignedMessage::new(message, &address, "00").to_string();
+        assert!(armored.contains("\n- -----BEGIN KASPA SIGNATURE-----\n- - item\nplain\n- --\n"));
+        assert_eq!(ClearsignedMessage::parse(&armored).unwrap().message, message);
+
+        let unescaped = armored.replace("\nplain\n", "\n-plain\n");
+        assert!(ClearsignedMessage::parse(&unescaped).is_err());
+    }
+
+    #[test]
+    fn signed_round_trip() {
+        let private_key = SecretKey::from_slice(&hex::decode(PRIVATE_KEY).unwrap()).unwrap();
+        let address = Address::try_from(ADDRESS).unwrap();
+        let message = canonicalize("Pay 10 KAS to Bob  \r\n-- Alice\r\n\r\n");
+        let signature = sign_message(&message, &private_key).unwrap();
+        let armored = ClearsignedMessage::new(&message, &address, &signature).to_string();
+
+        // Mail clients rewrite line endings and pad lines; neither changes what was signed
+        let mangled = format!("Some preamble\r\n{}", armored.replace('\n', "  \r\n"));
+        for text in [armored.as_str(), mangled.as_str()] {
+            let parsed = ClearsignedMessage::parse(text).unwrap();
+            assert_eq!(parsed, ClearsignedMessage::new(&message, &address, &signature));
+            assert!(verify_signature(&parsed.message, &parsed.signature, &parsed.address).unwrap());
+        }
+
+        let tampered = armored.replace("Pay 10 KAS", "Pay 90 KAS");
+        let parsed = ClearsignedMessage::parse(&tampered).unwrap();
+        assert!(!verify_signature(&parsed.message, &parsed.signature, &parsed.address).unwrap());
+    }
+
+    #[test]
+    fn rejects_inconsistent_headers() {
+        let address = Address::try_from(ADDRESS).unwrap();
+        let armored = ClearsignedMessage::new("hello", &address, "00").to_string();
+        assert!(ClearsignedMessage::parse(&armored.replace("Network: mainnet", "Network: testnet")).is_err());
+        assert!(ClearsignedMessage::parse(&armored.replace(SIGNATURE_ALGORITHM, "schnorr")).is_err());
+        assert!(ClearsignedMessage::parse(&armored.replace("Network: mainnet\n", "")).is_err());
+        assert!(ClearsignedMessage::parse(&armored.replace("Network:", "Comment: x\nNetwork:")).is_err());
+        assert!(ClearsignedMessage::parse(&armored.replace(END_SIGNATURE, "")).is_err());
+        assert!(ClearsignedMessage::parse(&armored.replace(BEGIN_MESSAGE, "")).is_err());
+    }
+}
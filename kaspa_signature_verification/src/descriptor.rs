@@ -0,0 +1,426 @@
+// Watch-only wallet descriptors, in the spirit of Bitcoin output descriptors (BIP380):
+//
+//     kaspa:pk([73c5da0a/44'/111111'/0']xpub6C.../<0;1>/*)#checksum
+//     kaspatest:sortedmulti(2,[73c5da0a/44'/111111'/0']xpub6C...,[f23a9c1d/44'/111111'/0']kpub2...)#checksum
+//
+// The address prefix before ':' is the network. The function is the address type:
+// pk (Schnorr), pkecdsa (ECDSA), or multi / sortedmulti and their _ecdsa forms for
+// P2SH multisig. Each key may carry its origin, the master fingerprint and path of
+// the account key. `/<0;1>/*` (receive and change chains) is implied and optional.
+// The checksum is the BIP380 descriptor checksum over everything before '#'.
+use kaspa_addresses::Prefix;
+use std::fmt;
+
+use crate::multisig::{MultisigWallet, WalletDefinition};
+use crate::{load_xpub_from_string, KaspaXPub};
+
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHAINS_SUFFIX: &str = "/<0;1>/*";
+
+fn polymod(c: u64, value: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7_ffff_ffff) << 5) ^ value;
+    for (bit, generator) in [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd].iter().enumerate() {
+        if c0 & (1 << bit) != 0 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// The 8-character BIP380 checksum of a descriptor without its '#' part.
+pub fn checksum(descriptor: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut c = 1u64;
+    let mut class = 0u64;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let position = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| format!("Invalid character '{}' in descriptor", ch))? as u64;
+        c = polymod(c, position & 31);
+        class = class * 3 + (position >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8).map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char).collect())
+}
+
+/// Where an account key comes from: the master key fingerprint and the path below it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOrigin {
+    pub fingerprint: [u8; 4],
+    /// Path steps below the master key, e.g. ["44'", "111111'", "0'"]
+    pub path: Vec<String>,
+}
+
+impl KeyOrigin {
+    /// Parses the inside of `[73c5da0a/44'/111111'/0']`. `h` is accepted as the hardened marker.
+    pub fn parse(input: &str) -> Result<KeyOrigin, Box<dyn std::error::Error>> {
+        let mut parts = input.split('/');
+        let fingerprint_hex = parts.next().unwrap_or_default();
+        let fingerprint: [u8; 4] = hex::decode(fingerprint_hex)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("Invalid key origin fingerprint '{}': expected 8 hex digits", fingerprint_hex))?;
+        let path = parts
+            .map(|step| {
+                let (number, hardened) = match step.strip_suffix(['\'', 'h']) {
+                    Some(number) => (number, true),
+                    None => (step, false),
+                };
+                match number.parse::<u32>() {
+                    Ok(n) if n < 1 << 31 => Ok(format!("{}{}", n, if hardened { "'" } else { "" })),
+                    _ => Err(format!("Invalid key origin path step '{}'", step)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(KeyOrigin { fingerprint, path })
+    }
+
+    /// Checks the origin against what the key records about itself: its depth is the number
+    /// of path steps and its child number is the last step. At depth 0 or 1 the master
+    /// fingerprint is also checked, against the key's own or its parent's fingerprint.
+    /// Returns whether the fingerprint could be checked.
+    pub fn verify(&self, xpub: &KaspaXPub) -> Result<bool, Box<dyn std::error::Error>> {
+        let attrs = xpub.attrs();
+        if attrs.depth as usize != self.path.len() {
+            return Err(format!(
+                "Key origin [{}] has {} path steps, but the key is at depth {}",
+                self, self.path.len(), attrs.depth
+            )
+            .into());
+        }
+        if let Some(last) = self.path.last().filter(|last| **last != attrs.child_number.to_string()) {
+            return Err(format!("Key origin [{}] ends in {}, but the key is child {}", self, last, attrs.child_number).into());
+        }
+        let master_fingerprint = match attrs.depth {
+            0 => xpub.fingerprint(),
+            1 => attrs.parent_fingerprint,
+            _ => return Ok(false),
+        };
+        if master_fingerprint != self.fingerprint {
+            return Err(format!(
+                "Key origin fingerprint {} does not match the key's master fingerprint {}",
+                hex::encode(self.fingerprint),
+                hex::encode(master_fingerprint)
+            )
+            .into());
+        }
+        Ok(true)
+    }
+}
+
+impl fmt::Display for KeyOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.fingerprint))?;
+        for step in &self.path {
+            write!(f, "/{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+/// A key expression: an optional origin and an extended key (xpub, kpub...) or a hex public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorKey {
+    pub origin: Option<KeyOrigin>,
+    pub key: String,
+}
+
+impl DescriptorKey {
+    pub fn parse(input: &str) -> Result<DescriptorKey, Box<dyn std::error::Error>> {
+        let input = input.trim();
+        let (origin, rest) = match input.strip_prefix('[') {
+            Some(rest) => {
+                let (origin, rest) = rest.split_once(']').ok_or("Unclosed '[' in key origin")?;
+                (Some(KeyOrigin::parse(origin)?), rest)
+            }
+            None => (None, input),
+        };
+        let key = rest.strip_suffix(CHAINS_SUFFIX).unwrap_or(rest);
+        if key.is_empty() || key.contains(['/', '[', ']', '(', ')', ',']) {
+            return Err(format!("Invalid key '{}': only account keys with /<0;1>/* are supported", rest).into());
+        }
+        Ok(DescriptorKey { origin, key: key.to_string() })
+    }
+
+    pub fn is_extended(&self) -> bool {
+        hex::decode(&self.key).is_err()
+    }
+
+    pub fn xpub(&self) -> Result<KaspaXPub, Box<dyn std::error::Error>> {
+        load_xpub_from_string(&self.key)
+    }
+}
+
+impl fmt::Display for DescriptorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "[{}]", origin)?;
+        }
+        write!(f, "{}", self.key)?;
+        if self.is_extended() {
+            write!(f, "{}", CHAINS_SUFFIX)?;
+        }
+        Ok(())
+    }
+}
+
+/// What the descriptor's addresses pay to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressType {
+    /// Single Schnorr key
+    PubKey,
+    /// Single ECDSA key
+    PubKeyEcdsa,
+    /// M-of-N pay-to-script-hash
+    Multisig { threshold: usize, sorted: bool, ecdsa: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descriptor {
+    pub network: Prefix,
+    pub address_type: AddressType,
+    pub keys: Vec<DescriptorKey>,
+}
+
+impl Descriptor {
+    /// Single-key descriptor for an account xpub.
+    pub fn single_key(network: Prefix, origin: Option<KeyOrigin>, xpub: &KaspaXPub, ecdsa: bool) -> Descriptor {
+        Descriptor {
+            network,
+            address_type: if ecdsa { AddressType::PubKeyEcdsa } else { AddressType::PubKey },
+            keys: vec![DescriptorKey { origin, key: xpub.to_string(None) }],
+        }
+    }
+
+    /// Parses a descriptor, checking the checksum if one is given.
+    pub fn parse(input: &str) -> Result<Descriptor, Box<dyn std::error::Error>> {
+        let input = input.trim();
+        let (body, expected) = match input.split_once('#') {
+            Some((body, expected)) => (body, Some(expected)),
+            None => (input, None),
+        };
+        let actual = checksum(body)?;
+        if let Some(expected) = expected.filter(|expected| *expected != actual) {
+            return Err(format!("Descriptor checksum mismatch: expected {}, computed {}", expected, actual).into());
+        }
+
+        let (network, expression) = body.split_once(':').ok_or("Descriptor must start with a network prefix, e.g. kaspa:")?;
+        let network = Prefix::try_from(network).map_err(|_| format!("Unknown network prefix '{}'", network))?;
+        let (function, arguments) = expression
+            .strip_suffix(')')
+            .and_then(|expression| expression.split_once('('))
+            .ok_or("Descriptor must look like <network>:<function>(<arguments>)")?;
+        let arguments: Vec<&str> = arguments.split(',').collect();
+
+        let (address_type, keys) = match function {
+            "pk" | "pkecdsa" => {
+                let [key] = arguments.as_slice() else {
+                    return Err(format!("{}() takes exactly one key", function).into());
+                };
+                let address_type = if function == "pk" { AddressType::PubKey } else { AddressType::PubKeyEcdsa };
+                (address_type, vec![DescriptorKey::parse(key)?])
+            }
+            "multi" | "sortedmulti" | "multi_ecdsa" | "sortedmulti_ecdsa" => {
+                let (threshold, keys) = arguments.split_first().ok_or("multi() needs a threshold and keys")?;
+                let address_type = AddressType::Multisig {
+                    threshold: threshold.trim().parse().map_err(|_| format!("Invalid multisig threshold '{}'", threshold))?,
+                    sorted: function.starts_with("sorted"),
+                    ecdsa: function.ends_with("_ecdsa"),
+                };
+                (address_type, keys.iter().map(|key| DescriptorKey::parse(key)).collect::<Result<Vec<_>, _>>()?)
+            }
+            other => return Err(format!("Unknown descriptor function '{}'", other).into()),
+        };
+
+        let descriptor = Descriptor { network, address_type, keys };
+        // Catch bad keys and thresholds now rather than at first use
+        match descriptor.address_type {
+            AddressType::Multisig { .. } => {
+                descriptor.multisig_wallet()?;
+            }
+            _ => {
+                descriptor.xpub()?;
+            }
+        }
+        Ok(descriptor)
+    }
+
+    /// The account xpub of a single-key descriptor.
+    pub fn xpub(&self) -> Result<KaspaXPub, Box<dyn std::error::Error>> {
+        match (self.address_type, self.keys.as_slice()) {
+            (AddressType::PubKey | AddressType::PubKeyEcdsa, [key]) if key.is_extended() => key.xpub(),
+            (AddressType::Multisig { .. }, _) => Err("This is a multisig descriptor, not a single account key".into()),
+            _ => Err("Single-key descriptors need an extended public key".into()),
+        }
+    }
+
+    pub fn multisig_wallet(&self) -> Result<MultisigWallet, Box<dyn std::error::Error>> {
+        let AddressType::Multisig { threshold, sorted, ecdsa } = self.address_type else {
+            return Err("This is a single-key descriptor, not a multisig wallet".into());
+        };
+        MultisigWallet::from_definition(&WalletDefinition {
+            threshold,
+            cosigners: self.keys.iter().map(|key| key.key.clone()).collect(),
+            sorted,
+            ecdsa,
+        })
+    }
+
+    fn body(&self) -> String {
+        let keys: Vec<String> = self.keys.iter().map(DescriptorKey::to_string).collect();
+        let expression = match self.address_type {
+            AddressType::PubKey => format!("pk({})", keys.join(",")),
+            AddressType::PubKeyEcdsa => format!("pkecdsa({})", keys.join(",")),
+            AddressType::Multisig { threshold, sorted, ecdsa } => format!(
+                "{}multi{}({},{})",
+                if sorted { "sorted" } else { "" },
+                if ecdsa { "_ecdsa" } else { "" },
+                threshold,
+                keys.join(",")
+            ),
+        };
+        format!("{}:{}", self.network, expression)
+    }
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = self.body();
+        // Key strings and prefixes only use characters from the checksum charset
+        let checksum = checksum(&body).map_err(|_| fmt::Error)?;
+        write!(f, "{}#{}", body, checksum)
+    }
+}
+
+/// Loads an account xpub given as a bare xpub/kpub, a key expression with origin
+/// (`[fingerprint/path]xpub`), or a single-key descriptor.
+pub fn load_xpub(input: &str) -> Result<KaspaXPub, Box<dyn std::error::Error>> {
+    load_account_descriptor(input)?.xpub()
+}
+
+/// Like `load_xpub`, keeping the network, address type and key origin as a single-key
+/// descriptor. Bare keys and key expressions are mainnet Schnorr accounts.
+pub fn load_account_descriptor(input: &str) -> Result<Descriptor, Box<dyn std::error::Error>> {
+    let input = input.trim();
+    if input.contains('(') {
+        let descriptor = Descriptor::parse(input)?;
+        descriptor.xpub()?;
+        Ok(descriptor)
+    } else if input.starts_with('[') || input.ends_with(CHAINS_SUFFIX) {
+        let key = DescriptorKey::parse(input)?;
+        Ok(Descriptor::single_key(Prefix::Mainnet, key.origin.clone(), &key.xpub()?, false))
+    } else {
+        Ok(Descriptor::single_key(Prefix::Mainnet, None, &load_xpub_from_string(input)?, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_address_belongs_to_xpub_with, create_master_key, generate_seed_from_mnemonic};
+    use bip39::{Language, Mnemonic};
+    use kaspa_bip32::DerivationPath;
+    use std::str::FromStr;
+
+    const XPUB: &str = "xpub6CWzHoo4nAhEpu2Pwyo5j6Em2Rz9y77gFPGPhZAHWyTGTPBRDCU95ABmQceLqsKyboi3BKwhnjyVgGx3FHAqPNCfSVsse8QKz1bSc69Gsxf";
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn abandon_key(path: &str) -> KaspaXPub {
+        let seed = generate_seed_from_mnemonic(&Mnemonic::parse_in(Language::English, PHRASE).unwrap());
+        create_master_key(&seed).unwrap().derive_path(&DerivationPath::from_str(path).unwrap()).unwrap().public_key()
+    }
+
+    #[test]
+    fn bip380_checksums() {
+        assert_eq!(checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert_eq!(checksum("addr(mkmZxiEcEd8ZqjQWVZuC6so5dFMKEFpN2j)").unwrap(), "02wpgw69");
+        assert_eq!(
+            checksum("pkh([d34db33f/44'/0'/0']xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/1/*)")
+                .unwrap(),
+            "ml40v0wf"
+        );
+        assert!(checksum("raw(Ü)").is_err());
+    }
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        let input = format!("kaspatest:pk([73c5da0a/44'/111111'/0']{}/<0;1>/*)#wnezluyd", XPUB);
+        let descriptor = Descriptor::parse(&input).unwrap();
+        assert_eq!(descriptor.network, Prefix::Testnet);
+        assert_eq!(descriptor.address_type, AddressType::PubKey);
+        assert_eq!(descriptor.to_string(), input);
+        assert_eq!(Descriptor::parse(&descriptor.to_string()).unwrap(), descriptor);
+
+        // The checksum is optional, `h` is read as `'` and the chains suffix is implied
+        let loose = format!("kaspatest:pk([73c5da0a/44h/111111h/0h]{})", XPUB);
+        assert_eq!(Descriptor::parse(&loose).unwrap().to_string(), input);
+
+        let ecdsa = format!("kaspa:pkecdsa({}/<0;1>/*)#mm884pjm", XPUB);
+        assert_eq!(Descriptor::parse(&ecdsa).unwrap().to_string(), ecdsa);
+
+        assert!(Descriptor::parse(&input.replace("#wnezluyd", "#wnezluyq")).is_err());
+        assert!(Descriptor::parse(&input.replace("kaspatest:", "bitcoin:")).is_err());
+        assert!(Descriptor::parse(&format!("kaspa:pk({},{})", XPUB, XPUB)).is_err());
+        assert!(Descriptor::parse(&format!("kaspa:multi(3,{},{})", XPUB, XPUB)).is_err());
+    }
+
+    #[test]
+    fn key_origin_verification() {
+        let account = abandon_key("m/44'/111111'/0'");
+        assert_eq!(account.to_string(None), XPUB);
+        assert!(!KeyOrigin::parse("73c5da0a/44'/111111'/0'").unwrap().verify(&account).unwrap());
+        // Wrong depth, and wrong last step
+        assert!(KeyOrigin::parse("73c5da0a/44'/111111'").unwrap().verify(&account).is_err());
+        assert!(KeyOrigin::parse("73c5da0a/44'/111111'/1'").unwrap().verify(&account).is_err());
+        assert!(KeyOrigin::parse("73c5da0a/44'/111111'/0").unwrap().verify(&account).is_err());
+
+        // Near the master key the fingerprint is checked too
+        let purpose = abandon_key("m/44'");
+        assert!(KeyOrigin::parse("73c5da0a/44'").unwrap().verify(&purpose).unwrap());
+        assert!(KeyOrigin::parse("00000000/44'").unwrap().verify(&purpose).is_err());
+        assert!(KeyOrigin::parse("73c5da0a").unwrap().verify(&abandon_key("m")).unwrap());
+        assert!(KeyOrigin::parse("73c5da0b").unwrap().verify(&abandon_key("m")).is_err());
+
+        assert!(KeyOrigin::parse("73c5da0/44'").is_err());
+        assert!(KeyOrigin::parse("73c5da0a/2147483648").is_err());
+        assert!(KeyOrigin::parse("73c5da0a/x'").is_err());
+    }
+
+    #[test]
+    fn account_descriptors_keep_network_and_address_type() {
+        let search = |input: &str, address: &str| {
+            let account = load_account_descriptor(input).unwrap();
+            let ecdsa = account.address_type == AddressType::PubKeyEcdsa;
+            check_address_belongs_to_xpub_with(&account.xpub().unwrap(), address, 10, 2, account.network, ecdsa).unwrap()
+        };
+        let testnet = "kaspatest:qq729xvyeasfr9yujucpylnxapkjhwzvxrc8rgj36dl9nl78emtex2v8088re";
+        let ecdsa = "kaspa:qyp8gda5uuj2485t7f7nemzx32kjf9zql6vk2020ka9pf2y5hf48jesf9x3m2j7";
+        assert_eq!(search(&format!("kaspatest:pk({})", XPUB), testnet), Some(7));
+        assert_eq!(search(&format!("kaspa:pkecdsa({})", XPUB), ecdsa), Some(1));
+        // Bare keys and key expressions are mainnet Schnorr
+        assert_eq!(search(XPUB, testnet), None);
+        assert_eq!(search(&format!("[73c5da0a/44'/111111'/0']{}", XPUB), ecdsa), None);
+        assert_eq!(
+            search(&format!("[73c5da0a/44'/111111'/0']{}", XPUB), "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh"),
+            Some(0)
+        );
+
+        let origin = load_account_descriptor(&format!("[73c5da0a/44'/111111'/0']{}/<0;1>/*", XPUB)).unwrap().keys[0].origin.clone();
+        assert_eq!(origin.unwrap().to_string(), "73c5da0a/44'/111111'/0'");
+        assert!(load_account_descriptor(&format!("kaspa:sortedmulti(1,{})", XPUB)).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+// C ABI over the verification core, for programs that link the library instead of
+// running the binary. The header in include/ is generated from this file:
+//
+//     cbindgen --config cbindgen.toml --output include/kaspa_message_signer.h
+//
+// The ownership rules are part of the header preamble in cbindgen.toml. Build the
+// library for release with unwinding, so panics become KASPA_ERR_INTERNAL:
+//
+//     cargo build --lib --profile release-lib
+use kaspa_addresses::{Address, Prefix};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{check_address_belongs_to_xpub_parallel, derive_xpub_address, load_xpub_from_string, verify_signature};
+
+/// The call succeeded.
+pub const KASPA_OK: i32 = 0;
+/// A required pointer argument was NULL.
+pub const KASPA_ERR_NULL_POINTER: i32 = -1;
+/// A string argument was not valid UTF-8.
+pub const KASPA_ERR_INVALID_UTF8: i32 = -2;
+/// An argument could not be parsed (address, xpub, signature, key...).
+pub const KASPA_ERR_INVALID_INPUT: i32 = -3;
+/// The search window is 0 or larger than KASPA_MAX_SEARCH_WINDOW.
+pub const KASPA_ERR_WINDOW: i32 = -4;
+/// The library hit an internal error (a Rust panic) and the call was abandoned.
+/// Only libraries built with unwinding (the release-lib profile) return it; with
+/// panic = "abort", as in the plain release profile, a panic aborts the process.
+pub const KASPA_ERR_INTERNAL: i32 = -5;
+
+/// Largest window kaspa_xpub_find_address searches.
+pub const KASPA_MAX_SEARCH_WINDOW: u32 = 10_000_000;
+
+/// A parsed address.
+#[repr(C)]
+pub struct KaspaAddressInfo {
+    /// 0 mainnet, 1 testnet, 2 simnet, 3 devnet
+    pub network: u8,
+    /// 0 Schnorr public key, 1 ECDSA public key, 8 script hash
+    pub version: u8,
+    /// Number of bytes used in `payload` (32 or 33)
+    pub payload_len: u8,
+    pub payload: [u8; 33],
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: Option<String>) {
+    let message = message.map(|message| CString::new(message.replace('\0', " ")).unwrap_or_default());
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+fn fail(code: i32, message: impl ToString) -> i32 {
+    set_last_error(Some(message.to_string()));
+    code
+}
+
+/// Borrows a C string argument for the duration of the call.
+unsafe fn read_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, i32> {
+    if ptr.is_null() {
+        return Err(fail(KASPA_ERR_NULL_POINTER, format!("{} is NULL", name)));
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|_| fail(KASPA_ERR_INVALID_UTF8, format!("{} is not valid UTF-8", name)))
+}
+
+// Runs an exported function's body, turning a panic into `on_panic` instead of
+// unwinding into the caller, which is undefined behavior across the C ABI
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|panic| {
+        let reason = panic
+            .downcast_ref::<&str>()
+            .map(|reason| reason.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        set_last_error(Some(format!("internal error: {}", reason)));
+        on_panic
+    })
+}
+
+fn into_c_string(value: String) -> *mut c_char {
+    CString::new(value).map(CString::into_raw).unwrap_or(std::ptr::null_mut())
+}
+
+/// Message of the last failed call on this thread, or NULL if the last call succeeded.
+///
+/// The string is owned by the library and stays valid until the next kaspa_* call on
+/// the same thread. Do not free it.
+#[unsafe(no_mangle)]
+pub extern "C" fn kaspa_last_error() -> *const c_char {
+    guard(std::ptr::null(), || {
+        LAST_ERROR.with(|last| last.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+    })
+}
+
+/// Releases a string returned by this library. Passing NULL does nothing.
+///
+/// # Safety
+/// `value` must be NULL or a pointer returned by a kaspa_* function that has not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kaspa_string_free(value: *mut c_char) {
+    guard((), || {
+        if !value.is_null() {
+            drop(unsafe { CString::from_raw(value) });
+        }
+    })
+}
+
+/// Verifies a hex compact ECDSA signature of `message` (double SHA-256) by `address`.
+///
+/// Returns 1 if the signature is valid, 0 if it is not, or a negative KASPA_ERR_* code.
+///
+/// # Safety
+/// All arguments must be NUL-terminated strings, readable for the duration of the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kaspa_verify_signature(
+    message: *const c_char,
+    signature_hex: *const c_char,
+    address: *const c_char,
+) -> i32 {
+    guard(KASPA_ERR_INTERNAL, || {
+        set_last_error(None);
+        let (message, signature_hex, address) = match unsafe {
+            (read_str(message, "message"), read_str(signature_hex, "signature_hex"), read_str(address, "address"))
+        } {
+            (Ok(message), Ok(signature_hex), Ok(address)) => (message, signature_hex, address),
+            (Err(code), _, _) | (_, Err(code), _) | (_, _, Err(code)) => return code,
+        };
+        match verify_signature(message, signature_hex.trim(), address.trim()) {
+            Ok(valid) => valid as i32,
+            Err(e) => fail(KASPA_ERR_INVALID_INPUT, e),
+        }
+    })
+}
+
+/// Parses and validates an address, filling `out`.
+///
+/// Returns KASPA_OK, or a negative KASPA_ERR_* code (KASPA_ERR_INVALID_INPUT for a bad address).
+///
+/// # Safety
+/// `address` must be a NUL-terminated string and `out` must point to a writable KaspaAddressInfo.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kaspa_address_parse(address: *const c_char, out: *mut KaspaAddressInfo) -> i32 {
+    guard(KASPA_ERR_INTERNAL, || {
+        set_last_error(None);
+        let address = match unsafe { read_str(address, "address") } {
+            Ok(address) => address,
+            Err(code) => return code,
+        };
+        if out.is_null() {
+            return fail(KASPA_ERR_NULL_POINTER, "out is NULL");
+        }
+        let address = match Address::try_from(address.trim()) {
+            Ok(address) => address,
+            Err(e) => return fail(KASPA_ERR_INVALID_INPUT, e),
+        };
+
+        let mut info = KaspaAddressInfo {
+            network: match address.prefix {
+                Prefix::Mainnet => 0,
+                Prefix::Testnet => 1,
+                Prefix::Simnet => 2,
+                Prefix::Devnet => 3,
+            },
+            version: address.version as u8,
+            payload_len: address.payload.len() as u8,
+            payload: [0u8; 33],
+        };
+        info.payload[..address.payload.len()].copy_from_slice(&address.payload);
+        unsafe { out.write(info) };
+        KASPA_OK
+    })
+}
+
+/// Mainnet Schnorr address at m/<chain>/<index> below an account xpub, or NULL on error.
+/// Free the result with kaspa_string_free.
+///
+/// # Safety
+/// `xpub` must be a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kaspa_xpub_derive_address(xpub: *const c_char, chain: u32, index: u32) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        set_last_error(None);
+        let Ok(xpub) = (unsafe { read_str(xpub, "xpub") }) else {
+            return std::ptr::null_mut();
+        };
+        match load_xpub_from_string(xpub.trim()).and_then(|xpub| derive_xpub_address(&xpub, chain, index)) {
+            Ok(address) => into_c_string(address.to_string()),
+            Err(e) => {
+                fail(KASPA_ERR_INVALID_INPUT, e);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Searches the first `window` receive addresses (m/0/i) of an account xpub for `address`.
+///
+/// Returns 1 and writes the index to `out_index` if found, 0 if not, or a negative
+/// KASPA_ERR_* code. `window` must be between 1 and KASPA_MAX_SEARCH_WINDOW.
+///
+/// # Safety
+/// `xpub` and `address` must be NUL-terminated strings; `out_index` must be NULL or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kaspa_xpub_find_address(
+    xpub: *const c_char,
+    address: *const c_char,
+    window: u32,
+    out_index: *mut u32,
+) -> i32 {
+    guard(KASPA_ERR_INTERNAL, || {
+        set_last_error(None);
+        let (xpub, address) = match unsafe { (read_str(xpub, "xpub"), read_str(address, "address")) } {
+            (Ok(xpub), Ok(address)) => (xpub, address),
+            (Err(code), _) | (_, Err(code)) => return code,
+        };
+        if window == 0 || window > KASPA_MAX_SEARCH_WINDOW {
+            return fail(KASPA_ERR_WINDOW, format!("window must be between 1 and {}", KASPA_MAX_SEARCH_WINDOW));
+        }
+        let found = load_xpub_from_string(xpub.trim())
+            .and_then(|xpub| check_address_belongs_to_xpub_parallel(&xpub, address.trim(), window, num_cpus::get()));
+        match found {
+            Ok(Some(index)) => {
+                if !out_index.is_null() {
+                    unsafe { out_index.write(index) };
+                }
+                1
+            }
+            Ok(None) => 0,
+            Err(e) => fail(KASPA_ERR_INVALID_INPUT, e),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_error_codes() {
+        assert_eq!(guard(KASPA_ERR_INTERNAL, || -> i32 { panic!("bad state") }), KASPA_ERR_INTERNAL);
+        let error = unsafe { CStr::from_ptr(kaspa_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "internal error: bad state");
+
+        assert!(guard(std::ptr::null_mut(), || -> *mut c_char { panic!("{} keys", 2) }).is_null());
+        assert_eq!(guard(KASPA_ERR_INTERNAL, || KASPA_OK), KASPA_OK);
+    }
+}
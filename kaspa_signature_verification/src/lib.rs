@@ -0,0 +1,418 @@
+// Signing and verification core shared by the command line tool and the bindings:
+// the C ABI in `ffi`, Python in `python` and WebAssembly in `wasm`. The wasm32 build
+// leaves out everything that needs threads.
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_bip32::{ExtendedPublicKey, DerivationPath};
+// Important: Import the kaspa_bip32's version of secp256k1 for the PublicKey type
+use kaspa_bip32::secp256k1::PublicKey as KaspaSecp256k1PublicKey;
+use secp256k1::{Message, Secp256k1, SecretKey};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::error::Error as StdError;
+use byteorder::{BigEndian, ByteOrder};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub type KaspaXPub = ExtendedPublicKey<KaspaSecp256k1PublicKey>;
+
+pub fn load_xpub_from_string(xpub_str: &str) -> Result<KaspaXPub, Box<dyn std::error::Error>> {
+    // First, decode the Base58 encoded string (without checksum, as bs58 crate doesn't have built-in check)
+    let decoded = bs58::decode(xpub_str).into_vec()?;
+    
+    // Check if we have 82 bytes (78 + 4 byte checksum)
+    if decoded.len() == 82 {
+        // Take only the first 78 bytes, after checking the checksum against them
+        let data = &decoded[0..78];
+        if double_sha256(data)[..4] != decoded[78..] {
+            return Err("Invalid extended public key: checksum mismatch".into());
+        }
+        
+        // Extract the components of the extended key
+        let depth = data[4];
+        
+        let mut fingerprint = [0u8; 4];
+        fingerprint.copy_from_slice(&data[5..9]);
+        
+        let child_number = kaspa_bip32::ChildNumber(u32::from_be_bytes([data[9], data[10], data[11], data[12]]));
+        
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&data[13..45]);
+        
+        let mut pubkey_bytes = [0u8; 33];
+        pubkey_bytes.copy_from_slice(&data[45..78]);
+        
+        // Create the public key
+        let public_key = KaspaSecp256k1PublicKey::from_slice(&pubkey_bytes)?;
+        
+        // Create extended key attributes
+        let attrs = kaspa_bip32::ExtendedKeyAttrs {
+            depth,
+            parent_fingerprint: fingerprint,
+            child_number,
+            chain_code,
+        };
+        
+        // Create the extended public key
+        let xpub = ExtendedPublicKey::from_public_key(public_key, &attrs);
+        
+        Ok(xpub)
+    } else if decoded.len() == 78 {
+        // Extract the components of the extended key
+        // Format: [4-byte version] [1-byte depth] [4-byte fingerprint] [4-byte child number] [32-byte chain code] [33-byte public key]
+    
+        // Extract depth (1 byte)
+        let depth = decoded[4];
+        
+        // Extract parent fingerprint (4 bytes)
+        let mut fingerprint = [0u8; 4];
+        fingerprint.copy_from_slice(&decoded[5..9]);
+        
+        // Extract child number (4 bytes)
+        let child_number = kaspa_bip32::ChildNumber(BigEndian::read_u32(&decoded[9..13]));
+        
+        // Extract chain code (32 bytes)
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&decoded[13..45]);
+        
+        // Extract public key (33 bytes)
+        let mut pubkey_bytes = [0u8; 33];
+        pubkey_bytes.copy_from_slice(&decoded[45..78]);
+        
+        // Parse the secp256k1 public key using Kaspa's type
+        let public_key = KaspaSecp256k1PublicKey::from_slice(&pubkey_bytes)?;
+        
+        // Use the ExtendedKeyAttrs struct directly instead of going through a namespace
+        let attrs = kaspa_bip32::ExtendedKeyAttrs {
+            depth,
+            parent_fingerprint: fingerprint,
+            child_number,
+            chain_code,
+        };
+
+        // Create the extended public key using from_public_key
+        let xpub = ExtendedPublicKey::from_public_key(public_key, &attrs);
+
+        Ok(xpub)
+    } else {
+        Err(format!("Invalid extended public key length: got {} bytes, expected 78 or 82", decoded.len()).into())
+    }
+    
+    
+}
+
+// Wrapper for thread errors
+#[derive(Debug)]
+pub struct ThreadError(pub String);
+
+impl std::fmt::Display for ThreadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Thread error: {}", self.0)
+    }
+}
+
+impl StdError for ThreadError {}
+
+impl From<kaspa_bip32::Error> for ThreadError {
+    fn from(err: kaspa_bip32::Error) -> Self {
+        ThreadError(err.to_string())
+    }
+}
+
+impl From<&str> for ThreadError {
+    fn from(s: &str) -> Self {
+        ThreadError(s.to_string())
+    }
+}
+
+/// Mainnet Schnorr address at m/<chain>/<index> below an account xpub.
+pub fn derive_xpub_address(xpub: &KaspaXPub, chain: u32, index: u32) -> Result<Address, Box<dyn std::error::Error>> {
+    let path = DerivationPath::from_str(&format!("m/{}/{}", chain, index))?;
+    let public_key = xpub.clone().derive_path(&path)?.public_key().serialize();
+    Ok(Address::new(Prefix::Mainnet, Version::PubKey, &public_key[1..33]))
+}
+
+// Updated to use the correct PublicKey type
+#[cfg(not(target_arch = "wasm32"))]
+pub fn check_address_belongs_to_xpub_parallel(
+    xpub: &ExtendedPublicKey<KaspaSecp256k1PublicKey>,
+    address_to_check: &str,
+    search_limit: u32,
+    num_threads: usize
+) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+    check_address_belongs_to_xpub_with(xpub, address_to_check, search_limit, num_threads, Prefix::Mainnet, false)
+}
+
+/// Like `check_address_belongs_to_xpub_parallel`, deriving `prefix` addresses, ECDSA ones
+/// if `ecdsa` is set, as a descriptor's network and address type ask for.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn check_address_belongs_to_xpub_with(
+    xpub: &ExtendedPublicKey<KaspaSecp256k1PublicKey>,
+    address_to_check: &str,
+    search_limit: u32,
+    num_threads: usize,
+    prefix: Prefix,
+    ecdsa: bool,
+) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+    // Parse the address to check
+    let address_to_check = Address::try_from(address_to_check)?;
+    let address_to_check_str = address_to_check.to_string();
+    
+    // Derive the change path first (external chain is 0)
+    let change_path = DerivationPath::from_str("m/0")?;
+    let change_xpub = xpub.clone().derive_path(&change_path)?;
+    
+    // Share the xpub and result across threads
+    let change_xpub = Arc::new(change_xpub);
+    let found_index = Arc::new(Mutex::new(None));
+    let should_exit = Arc::new(Mutex::new(false));
+    
+    // Create thread handles
+    let mut handles = vec![];
+    
+    // Calculate chunk size for each thread
+    let chunk_size = (search_limit as usize).div_ceil(num_threads);
+    
+    // Spawn threads
+    for thread_id in 0..num_threads {
+        // Calculate range for this thread
+        let start = thread_id as u32 * chunk_size as u32;
+        let end = std::cmp::min(start + chunk_size as u32, search_limit);
+        
+        // Skip if we're already past the search limit
+        if start >= search_limit {
+            continue;
+        }
+        
+        // Clone Arc references for this thread
+        let change_xpub = Arc::clone(&change_xpub);
+        let found_index = Arc::clone(&found_index);
+        let should_exit = Arc::clone(&should_exit);
+        let address_to_check_str = address_to_check_str.clone();
+        
+        // Spawn the thread
+        let handle = thread::spawn(move || -> Result<(), ThreadError> {
+            // Check if we should exit early
+            if *should_exit.lock().unwrap() {
+                return Ok(());
+            }
+            
+            // Process this thread's range
+            for i in start..end {
+                // Check if another thread found the address
+                if *should_exit.lock().unwrap() {
+                    break;
+                }
+                
+                // Derive the i-th child
+                let index_path = DerivationPath::from_str(&format!("m/{}", i))?;
+                let child_xpub = <ExtendedPublicKey<kaspa_bip32::secp256k1::PublicKey> as Clone>::clone(&change_xpub).derive_path(&index_path)?;
+                
+                // Get the public key
+                let public_key = child_xpub.public_key();
+                
+                // Extract the X coordinate from the compressed public key
+                let pubkey_bytes = public_key.serialize();
+                let x_only_pubkey = match pubkey_bytes[0] {
+                    0x02 | 0x03 => &pubkey_bytes[1..33], // Take 32 bytes after the prefix byte
+                    _ => return Err("Unexpected public key format".into()),
+                };
+                
+                // Create the Kaspa address (ECDSA addresses keep the whole compressed key)
+                let derived_address = if ecdsa {
+                    Address::new(prefix, Version::PubKeyECDSA, &pubkey_bytes)
+                } else {
+                    Address::new(prefix, Version::PubKey, x_only_pubkey)
+                };
+                 
+                //println!("Derived Address: {}, Index Path: {}",derived_address,index_path);
+                
+                // Check if this address matches the one we're looking for
+                if derived_address.to_string() == address_to_check_str {
+                    // Address found, update the shared result
+                    let mut found = found_index.lock().unwrap();
+                    *found = Some(i);
+                    
+                    // Signal other threads to exit
+                    let mut exit = should_exit.lock().unwrap();
+                    *exit = true;
+                    
+                    break;
+                }
+            }
+            
+            Ok(())
+        });
+        
+        handles.push(handle);
+    }
+    
+    // Wait for all threads to complete
+    for handle in handles {
+        match handle.join() {
+            Ok(result) => {
+                if let Err(e) = result {
+                    return Err(Box::new(ThreadError(format!("Thread error: {}", e))));
+                }
+            }
+            Err(_) => {
+                return Err("Thread panicked".into());
+            }
+        }
+    }
+    
+    // Return the result
+    Ok(*found_index.lock().unwrap())
+}
+
+pub fn sign_message(
+    message: &str,
+    private_key: &SecretKey,
+) -> Result<String, Box<dyn std::error::Error>> {
+    // Hash the message (Kaspa typically uses double SHA256)
+    let message_hash = double_sha256(message.as_bytes());
+
+    sign_digest(&message_hash, private_key)
+}
+
+pub fn sign_digest(
+    message_hash: &[u8; 32],
+    private_key: &SecretKey,
+) -> Result<String, Box<dyn std::error::Error>> {
+    // Create a context for Secp256k1 operations
+    let secp = Secp256k1::new();
+
+    // Create a secp256k1 message object from the hash
+    let secp_message = Message::from_slice(message_hash)?;
+
+    // Sign the message
+    let signature = secp.sign_ecdsa(&secp_message, private_key);
+
+    // Return the signature as a hex string
+    Ok(hex::encode(signature.serialize_compact()))
+}
+
+pub fn verify_signature(
+    message: &str,
+    signature_hex: &str,
+    address: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // Hash the message
+    let message_hash = double_sha256(message.as_bytes());
+
+    verify_digest_signature(&message_hash, signature_hex, address)
+}
+
+pub fn verify_digest_signature(
+    message_hash: &[u8; 32],
+    signature_hex: &str,
+    address: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // Step 1: Parse the Kaspa address
+    let kaspa_address = Address::try_from(address)?;
+
+    // Step 2: Extract the public key data from the address
+    // Kaspa address contains the X coordinate of the public key
+    let pubkey_data = &kaspa_address.payload;
+
+    // Step 3: Create a context for Secp256k1 operations
+    let secp = Secp256k1::new();
+
+    // Create a secp256k1 message object from the hash
+    let secp_message = Message::from_slice(message_hash)?;
+
+    // Parse the signature from hex
+    let signature_bytes = hex::decode(signature_hex)?;
+    let signature = secp256k1::ecdsa::Signature::from_compact(&signature_bytes)?;
+
+    // We need to reconstruct the full public key from just the X coordinate
+    // This requires trying both possible Y coordinates (even and odd)
+    for prefix in [0x02u8, 0x03] {
+        let mut pubkey_bytes = vec![prefix];
+        pubkey_bytes.extend_from_slice(pubkey_data);
+
+        if secp256k1::PublicKey::from_slice(&pubkey_bytes)
+            .is_ok_and(|pubkey| secp.verify_ecdsa(&secp_message, &signature, &pubkey).is_ok())
+        {
+            return Ok(true);
+        }
+    }
+
+    // If we reach here, neither key verified the signature
+    Ok(false)
+}
+
+/// BLAKE2b-256 keyed with "PersonalMessageSigningHash", the digest the reference
+/// wallet signs with Schnorr when signing a message.
+pub fn personal_message_hash(message: &str) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    let digest = blake2b_simd::Params::new()
+        .hash_length(32)
+        .key(b"PersonalMessageSigningHash")
+        .hash(message.as_bytes());
+    hash.copy_from_slice(digest.as_bytes());
+    hash
+}
+
+/// Checks a hex BIP340 Schnorr signature of `message` by a Schnorr address, signed the
+/// way the reference wallet signs messages. Returns false for a wrong signature and an
+/// error for malformed input.
+pub fn verify_schnorr_signature(
+    message: &str,
+    signature_hex: &str,
+    address: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let kaspa_address = Address::try_from(address)?;
+    if kaspa_address.version != Version::PubKey {
+        return Err("Schnorr signatures need a Schnorr (PubKey) address".into());
+    }
+    let public_key = secp256k1::XOnlyPublicKey::from_slice(&kaspa_address.payload)?;
+    let signature = secp256k1::schnorr::Signature::from_slice(&hex::decode(signature_hex)?)?;
+    let secp_message = Message::from_slice(&personal_message_hash(message))?;
+    Ok(Secp256k1::verification_only().verify_schnorr(&signature, &secp_message, &public_key).is_ok())
+}
+
+// Addresses of the keys a compact ECDSA signature could have been made with. The
+// signature carries no recovery id, so every id is tried and the signer is one of them.
+pub fn recover_signature_addresses(
+    message_hash: &[u8; 32],
+    signature_hex: &str,
+) -> Result<Vec<Address>, Box<dyn std::error::Error>> {
+    let secp = Secp256k1::verification_only();
+    let secp_message = Message::from_slice(message_hash)?;
+    let signature_bytes = hex::decode(signature_hex)?;
+    secp256k1::ecdsa::Signature::from_compact(&signature_bytes)?;
+
+    let mut addresses: Vec<Address> = Vec::new();
+    for id in 0..4 {
+        let recovery_id = secp256k1::ecdsa::RecoveryId::from_i32(id)?;
+        let signature = secp256k1::ecdsa::RecoverableSignature::from_compact(&signature_bytes, recovery_id)?;
+        if let Ok(public_key) = secp.recover_ecdsa(&secp_message, &signature) {
+            let address = Address::new(Prefix::Mainnet, Version::PubKey, &public_key.serialize()[1..33]);
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+    }
+    Ok(addresses)
+}
+
+pub fn double_sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher1 = Sha256::new();
+    hasher1.update(data);
+    let first_hash = hasher1.finalize();
+
+    let mut hasher2 = Sha256::new();
+    hasher2.update(first_hash);
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&hasher2.finalize());
+    output
+}
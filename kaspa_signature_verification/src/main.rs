@@ -5,23 +5,60 @@ use kaspa_bip32::{ExtendedPrivateKey, ExtendedPublicKey, DerivationPath, SecretK
 // Important: Import the kaspa_bip32's version of secp256k1 for the PublicKey type
 use kaspa_bip32::secp256k1::PublicKey as KaspaSecp256k1PublicKey;
 use rand::Rng;
-use secp256k1::{Message, Secp256k1, SecretKey};
+use secp256k1::{Secp256k1, SecretKey};
 use sha2::{Digest, Sha256};
-use std::io::{self};
+use std::io::{self, Read};
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::error::Error as StdError;
+use std::sync::Arc;
 use bs58;
-use byteorder::{BigEndian, ByteOrder};
 use rpassword::read_password;
+use kaspa_message_signer::{
+    check_address_belongs_to_xpub_with, double_sha256, load_xpub_from_string, personal_message_hash,
+    recover_signature_addresses, sign_digest, sign_message, verify_digest_signature, verify_signature, KaspaXPub,
+    ThreadError,
+};
+
+mod batch;
+mod clearsign;
+mod descriptor;
+mod keyfile;
+mod mnemonic_repair;
+mod multisig;
+mod payload;
+mod pskt;
+mod reserves;
+mod review;
+mod script;
+mod serve;
+mod sighash;
+mod slip39;
+mod transaction;
+mod typed_data;
+mod vanity;
+mod wallet_schemes;
+mod wif;
+
+// Fixture helpers shared with tests/vectors.rs
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod common;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Commands given on the command line run without the interactive menu
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_command(&args);
+    }
+
     // Step 0: Determine if we need to sign or verify a signature
     println!("Choose an option:");
     println!("1. Sign a message");
     println!("2. Verify a signature");
     println!("3. Check address against extended public key");
+    println!("4. Split seed into SLIP-39 shares");
+    println!("5. Recover seed from SLIP-39 shares and sign");
+    println!("6. Repair a mnemonic with a missing or mistyped word");
+    println!("7. Sign a message with a private key (xprv/kprv, hex or WIF)");
     let mut option = String::new();
     io::stdin().read_line(&mut option)?;
     let option = option.trim();
@@ -30,6 +67,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "1" => sign_message_flow(),
         "2" => verify_signature_flow(),
         "3" => check_address_against_xpub_flow(),
+        "4" => split_seed_flow(),
+        "5" => recover_seed_flow(),
+        "6" => repair_mnemonic_flow(),
+        "7" => sign_message_with_private_key_flow(),
         &_ => {
             println!("Invalid option.");
             Ok(())
@@ -42,6 +83,1245 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+fn run_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    match args[0].as_str() {
+        "sign" => sign_payload_command(&args[1..]),
+        "verify" => verify_payload_command(&args[1..]),
+        "sign-tx" => sign_transaction_command(&args[1..]),
+        "pskt" => pskt_command(&args[1..]),
+        "multisig" => multisig_command(&args[1..]),
+        "reserves" => reserves_command(&args[1..]),
+        "verify-batch" => verify_batch_command(&args[1..]),
+        "serve" => serve_command(&args[1..]),
+        "derive-addresses" => derive_addresses_command(&args[1..]),
+        "export-xprv" => export_xprv_command(&args[1..]),
+        "export-key" => export_key_command(&args[1..]),
+        "detect-wallet" => detect_wallet_command(&args[1..]),
+        "vanity" => vanity_command(&args[1..]),
+        "help" | "--help" | "-h" => {
+            print_usage();
+            Ok(())
+        }
+        other => {
+            print_usage();
+            Err(format!("Unknown command: {}", other).into())
+        }
+    }
+}
+
+fn print_usage() {
+    println!("Usage:");
+    println!("  kaspa_message_signer                 Start the interactive menu");
+    println!("  kaspa_message_signer sign <payload> [--out <signature file>] [--yes] [<key source>]");
+    println!("  kaspa_message_signer sign --clearsign <payload> [--out <armored file>] [--yes] [<key source>]");
+    println!("  kaspa_message_signer verify <payload> [--signature-file <path> | --address <address> --signature <hex>]");
+    println!("  kaspa_message_signer verify <armored file | ->");
+    println!("  kaspa_message_signer sign --typed <payload> [--out <signed file>] [--yes] [<key source>]");
+    println!("  kaspa_message_signer verify --typed <payload> --domain <name> [--version <version>] [--network <name>]");
+    println!("  kaspa_message_signer sign-tx --file <unsigned tx> [--out <signed tx>] [--scan <addresses per chain>] [--yes]");
+    println!("  kaspa_message_signer pskt inspect --file <pskt> [--xpub <xpub|descriptor>] [--scan <addresses per chain>]");
+    println!("  kaspa_message_signer pskt sign --file <pskt> [--out <pskt>] [--scan <addresses per chain>] [--yes]");
+    println!("  kaspa_message_signer multisig address (--wallet <definition> | --descriptor <descriptor> | --threshold <m> --keys <key,...> [--sorted] [--ecdsa]) [--index <i>] [--count <n>] [--change]");
+    println!("  kaspa_message_signer multisig check (--wallet <definition> | --descriptor <descriptor> | --threshold <m> --keys <key,...> [--sorted] [--ecdsa]) --address <p2sh address> [--window <n>]");
+    println!("  kaspa_message_signer multisig sign-message (--bundle <file> | --message <text> (--redeem-script <hex> | <wallet options> [--index <i>] [--change])) [--out <file>] [--scan <addresses per chain>] [--yes]");
+    println!("  kaspa_message_signer multisig verify-message --bundle <file>");
+    println!("  kaspa_message_signer reserves prove --challenge <text> [--ranges <chain>:<start>-<end>,...] [--out <proof>] [--yes]");
+    println!("  kaspa_message_signer reserves verify --proof <proof> --challenge <expected text> [--utxos <snapshot>]");
+    println!("  kaspa_message_signer verify-batch --file <rows.csv|rows.jsonl> [--format csv|jsonl] [--out <results>]");
+    println!("  kaspa_message_signer serve [--bind <host:port>] [--max-body <bytes>] [--max-concurrent <n>] [--max-window <n>] [--enable-signing [<key source>]]");
+    println!("  kaspa_message_signer derive-addresses --xpub <xpub|kpub|descriptor> [--chain <0|1>] [--start <i>] [--count <n>] [--network <name>] [--ecdsa] [--format text|csv|json] [--out <file>]");
+    println!("  kaspa_message_signer export-xprv [--kprv] (--out <encrypted file> | --force)");
+    println!("  kaspa_message_signer export-key [--index <i>] [--change] [--testnet] (--out <encrypted file> | --force)");
+    println!("  kaspa_message_signer detect-wallet (--address <known address> [--window <n>] | --list)");
+    println!("  kaspa_message_signer vanity (--prefix <chars after kaspa:q> | --suffix <chars>)... [--ecdsa] [--network <name>] [--threads <n>] (--out <encrypted file> | --force)");
+    println!("  kaspa_message_signer vanity (--prefix <chars> | --suffix <chars>)... --xpub <xpub|descriptor> [--chain <0|1>] [--start <i>] [--count <n>] [--ecdsa] [--network <name>]");
+    println!("  kaspa_message_signer pskt combine <pskt> <pskt>... [--out <pskt>]");
+    println!("  kaspa_message_signer pskt finalize --file <pskt> [--out <signed tx>]");
+    println!();
+    println!("Payloads: --file <path>, --stdin, --hex <bytes> or --base64 <bytes>");
+    println!("Key sources: the mnemonic is asked for unless --xprv (asks for an xprv/kprv), --private-key (asks for a hex or WIF key)");
+    println!("  or --key-file <file from export-xprv or export-key> is given");
+    println!("Signing shows what will be signed and asks for yes; --yes skips the question (and is needed with --stdin)");
+    println!("Networks: --network mainnet (the default), testnet, simnet or devnet; verify --typed rejects payloads for any other network");
+    println!("Descriptors: <network>:pk(KEY), pkecdsa(KEY), [sorted]multi[_ecdsa](<m>,KEY,...) with an optional #checksum,");
+    println!("  where KEY is [fingerprint/44'/111111'/0']xpub.../<0;1>/* (the origin and suffix are optional)");
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn has_flag(args: &[String], name: &str) -> bool {
+    args.iter().any(|arg| arg == name)
+}
+
+// A payload on stdin leaves nothing to answer the confirmation with
+fn check_stdin_confirmation(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if has_flag(args, "--stdin") && !has_flag(args, "--yes") {
+        return Err("Signing from --stdin can't ask for confirmation: review the payload and add --yes".into());
+    }
+    Ok(())
+}
+
+fn sign_payload_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    check_stdin_confirmation(args)?;
+    if has_flag(args, "--clearsign") {
+        return clearsign_command(args);
+    }
+    if has_flag(args, "--typed") {
+        return sign_typed_data_command(args);
+    }
+
+    // Step 1: Hash the payload first so a bad path fails before the mnemonic is entered
+    let source = payload::PayloadSource::from_args(args)?;
+    let mut head = Vec::new();
+    let (digest, size) = source.digest_with_head(review::PREVIEW_BYTES, &mut head)?;
+
+    // Step 2: Derive the signing key
+    let (private_key, address, key_path) = load_message_signing_key(args)?;
+
+    // Step 3: Show what will be signed and wait for the user to agree
+    confirm_signing(args, &review::render_payload(&head, size, &digest, &address.to_string(), &key_path))?;
+
+    // Step 4: Sign the digest and write the detached signature
+    let signature = sign_digest(&digest, &private_key)?;
+    let detached = payload::DetachedSignature::new(&address.to_string(), digest, size, signature);
+    let out_path = option_value(args, "--out")
+        .map(std::path::PathBuf::from)
+        .or_else(|| source.default_signature_path());
+    match out_path {
+        Some(path) => {
+            std::fs::write(&path, detached.to_string())?;
+            println!("Signed {} bytes with {}", size, address);
+            println!("Signature written to {}", path.display());
+        }
+        None => print!("{}", detached),
+    }
+    Ok(())
+}
+
+fn verify_payload_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // A bare path (or "-" for stdin) is a clearsigned message
+    if let Some(path) = args.first().filter(|arg| !arg.starts_with("--") || *arg == "-") {
+        return verify_clearsigned_command(path);
+    }
+    if has_flag(args, "--typed") {
+        return verify_typed_data_command(args);
+    }
+
+    // Step 1: Hash the payload
+    let source = payload::PayloadSource::from_args(args)?;
+    let (digest, size) = source.digest()?;
+
+    // Step 2: Get the signature, either inline or from a detached signature file
+    let (address, signature) = match (option_value(args, "--address"), option_value(args, "--signature")) {
+        (Some(address), Some(signature)) => (address.to_string(), signature.to_string()),
+        _ => {
+            let path = option_value(args, "--signature-file")
+                .map(std::path::PathBuf::from)
+                .or_else(|| source.default_signature_path())
+                .ok_or("No signature given: use --signature-file <path> or --address and --signature")?;
+            let detached = payload::DetachedSignature::parse(&std::fs::read_to_string(&path)?)?;
+            if detached.digest != digest || detached.size != size {
+                println!("Digest mismatch: the payload is not the data that was signed.");
+                println!("Signature verification: Invalid");
+                std::process::exit(1);
+            }
+            (detached.address, detached.signature)
+        }
+    };
+
+    // Step 3: Verify the signature over the digest
+    let is_valid = verify_digest_signature(&digest, &signature, &address)?;
+    println!("Address: {}", address);
+    println!("Digest: {}", hex::encode(digest));
+    println!(
+        "Signature verification: {}",
+        if is_valid { "Valid" } else { "Invalid" }
+    );
+    if !is_valid {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn clearsign_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Read and canonicalize the message text
+    let source = payload::PayloadSource::from_args(args)?;
+    let text = String::from_utf8(source.read_to_end()?)
+        .map_err(|_| "Clearsigned messages must be UTF-8 text; sign binary data with a detached signature instead")?;
+    let message = clearsign::canonicalize(&text);
+
+    // Step 2: Derive the signing key
+    let (private_key, address, key_path) = load_message_signing_key(args)?;
+
+    // Step 3: Show the canonical message, which is what gets signed, and wait for a yes
+    confirm_signing(args, &review::render(&message, &double_sha256(message.as_bytes()), &address.to_string(), &key_path))?;
+
+    // Step 4: Sign the canonical message and armor it
+    let signature = sign_message(&message, &private_key)?;
+    let armored = clearsign::ClearsignedMessage::new(&message, &address, &signature).to_string();
+    match option_value(args, "--out") {
+        Some(path) => {
+            std::fs::write(path, &armored)?;
+            println!("Clearsigned message written to {}", path);
+        }
+        None => print!("{}", armored),
+    }
+    Ok(())
+}
+
+fn verify_clearsigned_command(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let armored = if path == "-" {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        buffer
+    } else {
+        std::fs::read_to_string(path)?
+    };
+    let clearsigned = clearsign::ClearsignedMessage::parse(&armored)?;
+
+    let is_valid = verify_signature(&clearsigned.message, &clearsigned.signature, &clearsigned.address)?;
+    println!("Address: {}", clearsigned.address);
+    println!("Network: {}", clearsigned.network);
+    println!(
+        "Signature verification: {}",
+        if is_valid { "Valid" } else { "Invalid" }
+    );
+    if !is_valid {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn sign_typed_data_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Parse the typed data and compute its digest
+    let source = payload::PayloadSource::from_args(args)?;
+    let data = typed_data::TypedData::parse(&String::from_utf8(source.read_to_end()?)?)?;
+    let digest = data.digest()?;
+
+    // Step 2: Derive the signing key, with its address on the domain network
+    let (private_key, address, key_path) = load_message_signing_key(args)?;
+    let address = Address::new(data.domain.prefix()?, address.version, &address.payload);
+
+    // Step 3: Refuse payloads this key could never produce a valid signature for, before signing
+    let mut signed = typed_data::SignedTypedData {
+        domain: data.domain,
+        message: data.message,
+        address: address.to_string(),
+        signature: String::new(),
+    };
+    let expected = typed_data::ExpectedDomain {
+        name: &signed.domain.name,
+        version: None,
+        network: None,
+    };
+    signed.check_domain(&expected, typed_data::unix_now())?;
+
+    // Step 4: Show the domain and message and wait for a yes
+    let shown = serde_json::to_string_pretty(&signed.typed_data())?;
+    confirm_signing(args, &review::render(&shown, &digest, &signed.address, &key_path))?;
+
+    // Step 5: Sign
+    signed.signature = sign_digest(&digest, &private_key)?;
+
+    let json = serde_json::to_string_pretty(&signed)?;
+    match option_value(args, "--out") {
+        Some(path) => {
+            std::fs::write(path, json + "\n")?;
+            println!("Signed typed data written to {}", path);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn verify_typed_data_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let source = payload::PayloadSource::from_args(args)?;
+    let signed: typed_data::SignedTypedData = serde_json::from_slice(&source.read_to_end()?)?;
+    let expected = typed_data::ExpectedDomain {
+        name: option_value(args, "--domain").ok_or("The expected application must be given with --domain <name>")?,
+        version: option_value(args, "--version"),
+        network: Some(parse_network(option_value(args, "--network").unwrap_or("mainnet"))?),
+    };
+
+    // Domain, network and expiry are checked before the signature
+    let is_valid = match signed.check_domain(&expected, typed_data::unix_now()) {
+        Ok(()) => verify_digest_signature(&signed.typed_data().digest()?, &signed.signature, &signed.address)?,
+        Err(e) => {
+            println!("Rejected: {}", e);
+            false
+        }
+    };
+    println!("Address: {}", signed.address);
+    println!(
+        "Signature verification: {}",
+        if is_valid { "Valid" } else { "Invalid" }
+    );
+    if !is_valid {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn sign_transaction_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    check_stdin_confirmation(args)?;
+
+    // Step 1: Import the unsigned transaction
+    let source = payload::PayloadSource::from_args(args)?;
+    let mut tx = transaction::Transaction::parse(&String::from_utf8(source.read_to_end()?)?)?;
+    let scan: u32 = option_value(args, "--scan").map(str::parse).transpose()?.unwrap_or(100);
+
+    // Step 2: Derive the wallet's receive and change keys, indexed by their Schnorr and ECDSA P2PK scripts
+    let mnemonic = get_or_generate_mnemonic()?;
+    let seed = generate_seed_from_mnemonic(&mnemonic);
+    let secp = Secp256k1::new();
+    let mut keys = std::collections::HashMap::new();
+    let mut paths = std::collections::HashMap::new();
+    for chain in 0..2 {
+        for index in 0..scan {
+            let path = format!("m/44'/111111'/0'/{}/{}", chain, index);
+            let (private_key, address) = derive_kaspa_key_and_address_at(&seed, &path)?;
+            for spk in [script::p2pk_script(&address.payload), script::p2pk_ecdsa_script(&private_key.public_key(&secp).serialize())] {
+                keys.insert(spk.clone(), private_key);
+                paths.insert(spk, path.clone());
+            }
+        }
+    }
+
+    // Step 3: Show the outputs, amounts, fee and the inputs we will sign, and wait for the user to agree
+    confirm_signing(args, &render_transaction_review(&tx, &paths))?;
+
+    // Step 4: Sign the inputs we own
+    let signed = transaction::sign_transaction(&mut tx, &keys)?;
+    let unsigned = tx.inputs.iter().filter(|input| input.signature_script.is_empty()).count();
+    println!("Signed {} of {} inputs.", signed.len(), tx.inputs.len());
+    if unsigned > 0 {
+        println!("WARNING: {} inputs are still unsigned (not owned by this wallet within the first {} addresses).", unsigned, scan);
+    }
+
+    // Step 5: Export for broadcast elsewhere
+    let json = serde_json::to_string_pretty(&tx)?;
+    match option_value(args, "--out") {
+        Some(path) => {
+            std::fs::write(path, json + "\n")?;
+            println!("Signed transaction written to {}", path);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+// What sign-tx is about to sign: every input and output with its amount and address,
+// the fee, and the inputs this wallet signs with the key at `paths[script]`
+fn render_transaction_review(tx: &transaction::Transaction, paths: &std::collections::HashMap<Vec<u8>, String>) -> String {
+    let describe = |spk: &[u8]| {
+        script::address_from_script(spk, Prefix::Mainnet)
+            .map(|address| address.to_string())
+            .unwrap_or_else(|| format!("script {}", hex::encode(spk)))
+    };
+    let owner = |spk: &transaction::ScriptPublicKey| match paths.get(&spk.script) {
+        Some(path) if spk.version == 0 => format!(" [ours {}]", path),
+        _ => String::new(),
+    };
+
+    let mut lines = vec!["Review before signing".to_string(), "Inputs:".to_string()];
+    let mut to_sign = Vec::new();
+    for (i, input) in tx.inputs.iter().enumerate() {
+        let spk = &input.utxo_entry.script_public_key;
+        let status = if !input.signature_script.is_empty() {
+            " (already signed)"
+        } else if spk.version == 0 && paths.contains_key(&spk.script) {
+            to_sign.push(format!("  #{} with the key at {}", i, paths[&spk.script]));
+            " (signing)"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "  #{} {}:{} {} {}{}{}",
+            i,
+            hex::encode(&input.previous_outpoint.transaction_id),
+            input.previous_outpoint.index,
+            format_sompi(input.utxo_entry.amount),
+            describe(&spk.script),
+            owner(spk),
+            status
+        ));
+    }
+    lines.push("Outputs:".to_string());
+    for (i, output) in tx.outputs.iter().enumerate() {
+        lines.push(format!(
+            "  #{} {} {}{}",
+            i,
+            format_sompi(output.value),
+            describe(&output.script_public_key.script),
+            owner(&output.script_public_key)
+        ));
+    }
+    let inputs: u64 = tx.inputs.iter().map(|input| input.utxo_entry.amount).sum();
+    let outputs: u64 = tx.outputs.iter().map(|output| output.value).sum();
+    match inputs.checked_sub(outputs) {
+        Some(fee) => lines.push(format!("Fee: {}", format_sompi(fee))),
+        None => lines.push(format!("WARNING: Outputs exceed inputs by {}", format_sompi(outputs - inputs))),
+    }
+    if to_sign.is_empty() {
+        lines.push("Signing: no inputs pay to this wallet".to_string());
+    } else {
+        lines.push("Signing:".to_string());
+        lines.extend(to_sign);
+    }
+    lines.join("\n")
+}
+
+// Public keys of the first `scan` receive and change addresses under an account xpub,
+// paired with their path relative to the account
+fn derive_wallet_public_keys(
+    xpub: &KaspaXPub,
+    scan: u32,
+) -> Result<Vec<(String, KaspaSecp256k1PublicKey)>, Box<dyn std::error::Error>> {
+    let mut keys = Vec::new();
+    for chain in 0..2 {
+        for (index, public_key) in derive_chain_public_keys(xpub, chain, 0, scan)? {
+            keys.push((format!("{}/{}", chain, index), public_key));
+        }
+    }
+    Ok(keys)
+}
+
+// Most keys one call derives; larger ranges are searched in batches
+const MAX_DERIVE_COUNT: u32 = 1_000_000;
+
+// Keys at m/<chain>/<start> .. m/<chain>/<start + count - 1>, deriving the chain key once
+fn derive_chain_public_keys(
+    xpub: &KaspaXPub,
+    chain: u32,
+    start: u32,
+    count: u32,
+) -> Result<Vec<(u32, KaspaSecp256k1PublicKey)>, Box<dyn std::error::Error>> {
+    // Non-hardened indices stop at 2^31 - 1
+    let end = start
+        .checked_add(count)
+        .filter(|end| *end <= 1 << 31)
+        .ok_or("Index range goes past the last non-hardened index (2147483647)")?;
+    if count > MAX_DERIVE_COUNT {
+        return Err(format!("Cannot derive more than {} keys at once (asked for {})", MAX_DERIVE_COUNT, count).into());
+    }
+    let chain_path = DerivationPath::from_str(&format!("m/{}", chain))?;
+    let chain_xpub = xpub.clone().derive_path(&chain_path)?;
+    let mut keys = Vec::new();
+    for index in start..end {
+        let index_path = DerivationPath::from_str(&format!("m/{}", index))?;
+        let child_xpub = chain_xpub.clone().derive_path(&index_path)?;
+        keys.push((index, *child_xpub.public_key()));
+    }
+    Ok(keys)
+}
+
+fn parse_network(name: &str) -> Result<Prefix, Box<dyn std::error::Error>> {
+    match name.to_lowercase().as_str() {
+        "mainnet" | "kaspa" => Ok(Prefix::Mainnet),
+        "testnet" | "kaspatest" => Ok(Prefix::Testnet),
+        "simnet" | "kaspasim" => Ok(Prefix::Simnet),
+        "devnet" | "kaspadev" => Ok(Prefix::Devnet),
+        other => Err(format!("Unknown network '{}': use mainnet, testnet, simnet or devnet", other).into()),
+    }
+}
+
+fn format_sompi(sompi: u64) -> String {
+    format!("{}.{:08} KAS", sompi / 100_000_000, sompi % 100_000_000)
+}
+
+fn write_output(args: &[String], contents: String, what: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match option_value(args, "--out") {
+        Some(path) => {
+            std::fs::write(path, contents + "\n")?;
+            println!("{} written to {}", what, path);
+        }
+        None => println!("{}", contents),
+    }
+    Ok(())
+}
+
+fn pskt_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let subcommand = args.first().map(String::as_str).unwrap_or("");
+    let args = args.get(1..).unwrap_or(&[]);
+    let scan: u32 = option_value(args, "--scan").map(str::parse).transpose()?.unwrap_or(100);
+
+    match subcommand {
+        "inspect" => {
+            let bundle = pskt::Bundle::parse(&String::from_utf8(payload::PayloadSource::from_args(args)?.read_to_end()?)?)?;
+            // Change detection needs the account xpub, either given or derived from the mnemonic
+            let account = match option_value(args, "--xpub") {
+                Some(xpub) => descriptor::load_account_descriptor(xpub)?,
+                None => {
+                    let seed = generate_seed_from_mnemonic(&get_or_generate_mnemonic()?);
+                    let xpub = create_extended_public_key(&create_master_key(&seed)?)?;
+                    descriptor::Descriptor::single_key(Prefix::Mainnet, None, &xpub, false)
+                }
+            };
+            let wallet_keys = derive_wallet_public_keys(&account.xpub()?, scan)?;
+            println!("{}", render_pskt_bundle(&bundle, &wallet_keys, &account));
+            Ok(())
+        }
+        "sign" => {
+            check_stdin_confirmation(args)?;
+            let mut bundle = pskt::Bundle::parse(&String::from_utf8(payload::PayloadSource::from_args(args)?.read_to_end()?)?)?;
+            let seed = generate_seed_from_mnemonic(&get_or_generate_mnemonic()?);
+            let xpub = create_extended_public_key(&create_master_key(&seed)?)?;
+            let wallet_keys = derive_wallet_public_keys(&xpub, scan)?;
+
+            // Step 1: Find the inputs that pay to one of our keys and still need our signature
+            let mut to_sign = Vec::new();
+            for (n, pskt) in bundle.pskts.iter().enumerate() {
+                for (input_index, input) in pskt.inputs.iter().enumerate() {
+                    for (relative_path, public_key) in &wallet_keys {
+                        let compressed = public_key.serialize();
+                        let pubkey_hex = hex::encode(compressed);
+                        if input.final_script_sig.is_some() || input.partial_sigs.contains_key(&pubkey_hex) {
+                            continue;
+                        }
+                        // Ours if the input pays to our key directly or through a redeem script listing it
+                        let pays_to_us = match &input.redeem_script {
+                            Some(redeem_script) => script::MultisigScript::parse(redeem_script)
+                                .is_some_and(|multisig| !multisig.ecdsa && multisig.pubkeys.iter().any(|key| key[..] == compressed[1..])),
+                            None => input.utxo_entry.as_ref().is_some_and(|entry| {
+                                entry.script_public_key.script == script::p2pk_script(&compressed[1..])
+                            }),
+                        };
+                        if pays_to_us {
+                            to_sign.push((n, input_index, format!("m/44'/111111'/0'/{}", relative_path), pubkey_hex));
+                        }
+                    }
+                }
+            }
+
+            // Step 2: Show the outputs, amounts, fee and the inputs we will sign, and wait for the user to agree
+            if !to_sign.is_empty() {
+                let account = descriptor::Descriptor::single_key(Prefix::Mainnet, None, &xpub, false);
+                let mut review = vec!["Review before signing".to_string(), render_pskt_bundle(&bundle, &wallet_keys, &account), "Signing:".to_string()];
+                for (n, input_index, path, _) in &to_sign {
+                    review.push(format!("  PSKT {} input #{} with the key at {}", n + 1, input_index, path));
+                }
+                confirm_signing(args, &review.join("\n"))?;
+            }
+
+            // Step 3: Sign
+            let secp = Secp256k1::new();
+            let mut rng = rand::thread_rng();
+            for (n, input_index, path, pubkey_hex) in &to_sign {
+                let pskt = &mut bundle.pskts[*n];
+                let (private_key, _) = derive_kaspa_key_and_address_at(&seed, path)?;
+                let sighash = pskt.signature_hash(*input_index)?;
+                let signature = transaction::schnorr_sign(&secp, &mut rng, &sighash, &private_key)?;
+                pskt.inputs[*input_index]
+                    .partial_sigs
+                    .insert(pubkey_hex.clone(), pskt::PartialSignature::Schnorr(hex::encode(signature)));
+                println!("Signed input {} with the key at {}", input_index, path);
+            }
+            println!("Added {} signatures.", to_sign.len());
+            write_output(args, bundle.serialize()?, "PSKT")
+        }
+        "combine" => {
+            let paths: Vec<&String> = args
+                .iter()
+                .enumerate()
+                .filter(|(i, arg)| !arg.starts_with("--") && (*i == 0 || args[i - 1] != "--out"))
+                .map(|(_, arg)| arg)
+                .collect();
+            if paths.len() < 2 {
+                return Err("At least two PSKT files are needed to combine".into());
+            }
+            let mut combined = pskt::Bundle::parse(&std::fs::read_to_string(paths[0])?)?;
+            for path in &paths[1..] {
+                let other = pskt::Bundle::parse(&std::fs::read_to_string(path)?)?;
+                if other.pskts.len() != combined.pskts.len() {
+                    return Err(format!("{} holds a different number of PSKTs", path).into());
+                }
+                for (pskt, other_pskt) in combined.pskts.iter_mut().zip(&other.pskts) {
+                    pskt.combine(other_pskt)?;
+                }
+            }
+            println!("Combined {} PSKT files.", paths.len());
+            write_output(args, combined.serialize()?, "PSKT")
+        }
+        "finalize" => {
+            let mut bundle = pskt::Bundle::parse(&String::from_utf8(payload::PayloadSource::from_args(args)?.read_to_end()?)?)?;
+            let mut transactions = Vec::new();
+            for pskt in bundle.pskts.iter_mut() {
+                pskt.finalize()?;
+                transactions.push(pskt.unsigned_transaction()?);
+            }
+            println!("Finalized {} transactions.", transactions.len());
+            let json = if transactions.len() == 1 {
+                serde_json::to_string_pretty(&transactions[0])?
+            } else {
+                serde_json::to_string_pretty(&transactions)?
+            };
+            write_output(args, json, "Signed transaction")
+        }
+        other => {
+            print_usage();
+            Err(format!("Unknown pskt command: {}", other).into())
+        }
+    }
+}
+
+fn render_pskt_bundle(
+    bundle: &pskt::Bundle,
+    wallet_keys: &[(String, KaspaSecp256k1PublicKey)],
+    account: &descriptor::Descriptor,
+) -> String {
+    // Label a script as ours if it pays to one of the wallet's keys, as the account's address type
+    let ecdsa = account.address_type == descriptor::AddressType::PubKeyEcdsa;
+    let owner = |spk: &[u8]| {
+        wallet_keys
+            .iter()
+            .find(|(_, key)| {
+                let key = key.serialize();
+                let script = if ecdsa { script::p2pk_ecdsa_script(&key) } else { script::p2pk_script(&key[1..]) };
+                script == spk
+            })
+            .map(|(path, _)| {
+                let label = if path.starts_with("1/") { "change" } else { "ours" };
+                format!(" [{} m/44'/111111'/0'/{}]", label, path)
+            })
+            .unwrap_or_default()
+    };
+    let describe = |spk: &[u8]| {
+        script::address_from_script(spk, account.network)
+            .map(|address| address.to_string())
+            .unwrap_or_else(|| format!("script {}", hex::encode(spk)))
+    };
+
+    let mut lines = Vec::new();
+    for (n, pskt) in bundle.pskts.iter().enumerate() {
+        lines.push(format!("PSKT {} of {}", n + 1, bundle.pskts.len()));
+        lines.push("Inputs:".to_string());
+        for (i, input) in pskt.inputs.iter().enumerate() {
+            let outpoint = format!(
+                "{}:{}",
+                hex::encode(&input.previous_outpoint.transaction_id),
+                input.previous_outpoint.index
+            );
+            let status = if input.final_script_sig.is_some() {
+                "finalized".to_string()
+            } else {
+                format!("{} signatures", input.partial_sigs.len())
+            };
+            match &input.utxo_entry {
+                Some(entry) => lines.push(format!(
+                    "  #{} {} {} {}{} ({})",
+                    i,
+                    outpoint,
+                    format_sompi(entry.amount),
+                    describe(&entry.script_public_key.script),
+                    owner(&entry.script_public_key.script),
+                    status
+                )),
+                None => lines.push(format!("  #{} {} (UTXO entry missing) ({})", i, outpoint, status)),
+            }
+        }
+        lines.push("Outputs:".to_string());
+        for (i, output) in pskt.outputs.iter().enumerate() {
+            lines.push(format!(
+                "  #{} {} {}{}",
+                i,
+                format_sompi(output.amount),
+                describe(&output.script_public_key.script),
+                owner(&output.script_public_key.script)
+            ));
+        }
+        match pskt.fee() {
+            Some(fee) => lines.push(format!("Fee: {}", format_sompi(fee))),
+            None => lines.push("Fee: unknown (missing UTXO entries or outputs exceed inputs)".to_string()),
+        }
+    }
+    lines.join("\n")
+}
+
+// A multisig wallet from a definition file, a descriptor, or --threshold and --keys on the
+// command line, with the network its addresses are on
+fn load_multisig_wallet(args: &[String]) -> Result<(multisig::MultisigWallet, Prefix), Box<dyn std::error::Error>> {
+    if let Some(path) = option_value(args, "--wallet") {
+        return Ok((multisig::MultisigWallet::parse(&std::fs::read_to_string(path)?)?, Prefix::Mainnet));
+    }
+    if let Some(descriptor) = option_value(args, "--descriptor") {
+        let descriptor = descriptor::Descriptor::parse(descriptor)?;
+        return Ok((descriptor.multisig_wallet()?, descriptor.network));
+    }
+    let threshold = option_value(args, "--threshold").ok_or("Use --wallet <definition>, --descriptor <descriptor> or --threshold <m> --keys <key,...>")?;
+    let keys = option_value(args, "--keys").ok_or("Missing --keys <key,...>")?;
+    let wallet = multisig::MultisigWallet::from_definition(&multisig::WalletDefinition {
+        threshold: threshold.parse()?,
+        cosigners: keys.split(',').map(|key| key.trim().to_string()).collect(),
+        sorted: has_flag(args, "--sorted"),
+        ecdsa: has_flag(args, "--ecdsa"),
+    })?;
+    Ok((wallet, Prefix::Mainnet))
+}
+
+fn multisig_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let subcommand = args.first().map(String::as_str).unwrap_or("");
+    let args = args.get(1..).unwrap_or(&[]);
+
+    match subcommand {
+        "address" => {
+            let (wallet, prefix) = load_multisig_wallet(args)?;
+            let first: u32 = option_value(args, "--index").map(str::parse).transpose()?.unwrap_or(0);
+            let count: u32 = option_value(args, "--count").map(str::parse).transpose()?.unwrap_or(1);
+            let chain = if has_flag(args, "--change") { 1 } else { 0 };
+            for index in first..first.saturating_add(count) {
+                let script = wallet.script_at(chain, index)?;
+                println!("m/{}/{}: {}", chain, index, wallet.address_at(chain, index, prefix)?);
+                println!("  redeem script: {}", hex::encode(script.to_script()));
+            }
+            Ok(())
+        }
+        "check" => {
+            let (wallet, _) = load_multisig_wallet(args)?;
+            let address = option_value(args, "--address").ok_or("Missing --address <p2sh address>")?;
+            let window: u32 = option_value(args, "--window").map(str::parse).transpose()?.unwrap_or(10_000);
+            let num_threads = num_cpus::get();
+            println!("This will use {} threads.", num_threads);
+
+            let start_time = std::time::Instant::now();
+            match multisig::find_address_parallel(Arc::new(wallet), address, window, num_threads)? {
+                Some((chain, index)) => {
+                    let kind = if chain == 0 { "receive" } else { "change" };
+                    println!("Address found! It's the {} address at m/{}/{} of this multisig wallet.", kind, chain, index);
+                }
+                None => {
+                    println!("Address does not belong to this multisig wallet (within first {} addresses of each chain).", window);
+                    std::process::exit(1);
+                }
+            }
+            println!("Search completed in {:.2?}", start_time.elapsed());
+            Ok(())
+        }
+        "sign-message" => multisig_sign_message_command(args),
+        "verify-message" => {
+            let path = option_value(args, "--bundle").ok_or("Missing --bundle <file>")?;
+            let bundle = multisig::MultisigMessage::parse(&std::fs::read_to_string(path)?)?;
+            let results = bundle.verify()?;
+            for (public_key, valid) in &results {
+                println!("{} {}", if *valid { "VALID  " } else { "INVALID" }, public_key);
+            }
+            let valid = results.iter().filter(|(_, valid)| *valid).count();
+            if valid >= bundle.threshold {
+                println!("Multisig signature is VALID: {} of {} required signatures for {}", valid, bundle.threshold, bundle.address);
+                Ok(())
+            } else {
+                println!("Multisig signature is INVALID: {} of {} required signatures for {}", valid, bundle.threshold, bundle.address);
+                std::process::exit(1);
+            }
+        }
+        other => {
+            print_usage();
+            Err(format!("Unknown multisig command: {}", other).into())
+        }
+    }
+}
+
+fn multisig_sign_message_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Load the partial bundle, or start one from the message and redeem script
+    let bundle_path = option_value(args, "--bundle");
+    let mut bundle = match bundle_path {
+        Some(path) => multisig::MultisigMessage::parse(&std::fs::read_to_string(path)?)?,
+        None => {
+            let message = option_value(args, "--message").ok_or("Use --bundle <file> or --message <text>")?;
+            let (redeem_script, prefix) = match option_value(args, "--redeem-script") {
+                Some(script) => (hex::decode(script)?, Prefix::Mainnet),
+                None => {
+                    let index: u32 = option_value(args, "--index").map(str::parse).transpose()?.unwrap_or(0);
+                    let chain = if has_flag(args, "--change") { 1 } else { 0 };
+                    let (wallet, prefix) = load_multisig_wallet(args)?;
+                    (wallet.script_at(chain, index)?.to_script(), prefix)
+                }
+            };
+            multisig::MultisigMessage::new(message, &redeem_script, prefix)?
+        }
+    };
+    let multisig_script = bundle.multisig_script()?;
+
+    // Step 2: Find which of our keys is a cosigner of the redeem script
+    let scan: u32 = option_value(args, "--scan").map(str::parse).transpose()?.unwrap_or(100);
+    let seed = generate_seed_from_mnemonic(&get_or_generate_mnemonic()?);
+    let xpub = create_extended_public_key(&create_master_key(&seed)?)?;
+    let (relative_path, public_key) = derive_wallet_public_keys(&xpub, scan)?
+        .into_iter()
+        .find(|(_, key)| {
+            let compressed = key.serialize();
+            multisig_script.pubkeys.iter().any(|script_key| {
+                if multisig_script.ecdsa { script_key[..] == compressed[..] } else { script_key[..] == compressed[1..] }
+            })
+        })
+        .ok_or_else(|| format!("None of this wallet's first {} keys per chain is a cosigner of {}", scan, bundle.address))?;
+
+    // Step 3: Show the message and wait for a yes
+    let path = format!("m/44'/111111'/0'/{}", relative_path);
+    let digest = double_sha256(bundle.message.as_bytes());
+    confirm_signing(args, &review::render(&bundle.message, &digest, &bundle.address, &path))?;
+
+    // Step 4: Sign the message with that key and add the signature
+    let (private_key, _) = derive_kaspa_key_and_address_at(&seed, &path)?;
+    let signature = sign_message(&bundle.message, &private_key)?;
+    bundle.add_signature(&public_key.serialize(), signature);
+    let valid = bundle.verify()?.iter().filter(|(_, valid)| *valid).count();
+    println!("Signed with the key at {}. The bundle has {} of {} required signatures.", path, valid, bundle.threshold);
+
+    // Step 5: Write the bundle back, to --out or over the partial bundle
+    let json = serde_json::to_string_pretty(&bundle)?;
+    match option_value(args, "--out").or(bundle_path) {
+        Some(path) => {
+            std::fs::write(path, json + "\n")?;
+            println!("Bundle written to {}", path);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn reserves_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let subcommand = args.first().map(String::as_str).unwrap_or("");
+    let args = args.get(1..).unwrap_or(&[]);
+
+    match subcommand {
+        "prove" => {
+            // Step 1: The challenge and which addresses to prove
+            let challenge = option_value(args, "--challenge").ok_or("Missing --challenge <text>")?;
+            let ranges = reserves::AddressRange::parse_list(option_value(args, "--ranges").unwrap_or("0:0-99"))?;
+
+            // Step 2: Show the challenge and wait for a yes, as one signature per address is made
+            let seed = generate_seed_from_mnemonic(&get_or_generate_mnemonic()?);
+            let count: u64 = ranges.iter().map(|range| (range.end - range.start) as u64 + 1).sum();
+            let paths: Vec<String> = ranges
+                .iter()
+                .map(|range| format!("m/44'/111111'/0'/{}/{}-{}", range.chain, range.start, range.end))
+                .collect();
+            let signers = format!("each of {} wallet addresses", count);
+            confirm_signing(args, &review::render(challenge, &double_sha256(challenge.as_bytes()), &signers, &paths.join(", ")))?;
+
+            // Step 3: Sign the challenge with the key of every address in the ranges
+            let mut entries = Vec::new();
+            for range in &ranges {
+                for index in range.start..=range.end {
+                    let path = format!("m/44'/111111'/0'/{}/{}", range.chain, index);
+                    let (private_key, address) = derive_kaspa_key_and_address_at(&seed, &path)?;
+                    entries.push(reserves::ProofEntry {
+                        address: address.to_string(),
+                        path,
+                        signature: sign_message(challenge, &private_key)?,
+                    });
+                }
+            }
+            println!("Signed the challenge with {} addresses.", entries.len());
+
+            // Step 4: Emit the proof document
+            let proof = reserves::ReservesProof {
+                challenge: challenge.to_string(),
+                entries,
+            };
+            write_output(args, serde_json::to_string_pretty(&proof)?, "Proof")
+        }
+        "verify" => {
+            let path = option_value(args, "--proof").ok_or("Missing --proof <file>")?;
+            // The expected challenge is required, so an old proof can't be passed off as a fresh one
+            let expected = option_value(args, "--challenge").ok_or("Missing --challenge <expected text>")?;
+            let proof = reserves::ReservesProof::parse(&std::fs::read_to_string(path)?)?;
+            if proof.challenge != expected {
+                println!("Proof is INVALID: it signs the challenge '{}', expected '{}'", proof.challenge, expected);
+                std::process::exit(1);
+            }
+            let balances = match option_value(args, "--utxos") {
+                Some(path) => reserves::parse_snapshot(&std::fs::read_to_string(path)?)?,
+                None => Default::default(),
+            };
+
+            let report = proof.verify(&balances);
+            for (address, reason) in &report.invalid {
+                println!("INVALID {}: {}", address, reason);
+            }
+            println!("Challenge: {}", proof.challenge);
+            println!("Valid signatures: {} of {} entries", report.valid, proof.entries.len());
+            if !balances.is_empty() {
+                println!("Proven balance: {}", format_sompi(report.proven_balance));
+                println!("Snapshot addresses not covered by the proof: {}", report.unproven_addresses);
+            }
+            if !report.invalid.is_empty() {
+                println!("Proof of reserves is INVALID.");
+                std::process::exit(1);
+            }
+            println!("Proof of reserves is VALID.");
+            Ok(())
+        }
+        other => {
+            print_usage();
+            Err(format!("Unknown reserves command: {}", other).into())
+        }
+    }
+}
+
+fn verify_batch_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Read the rows
+    let input = std::path::PathBuf::from(option_value(args, "--file").ok_or("Missing --file <rows>")?);
+    let format = batch::Format::detect(option_value(args, "--format"), &input)?;
+    let (rows, mut results) = batch::read_rows(&input, format)?;
+
+    // Step 2: Verify them across all cores
+    let num_threads = num_cpus::get();
+    println!("Verifying {} rows with {} threads.", rows.len() + results.len(), num_threads);
+    let start_time = std::time::Instant::now();
+    results.extend(batch::verify_rows_parallel(rows, num_threads)?);
+    results.sort_by_key(|result| result.line);
+    let duration = start_time.elapsed();
+
+    // Step 3: Write the per-row results next to the input unless told otherwise
+    let out = match option_value(args, "--out") {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let extension = if format == batch::Format::Csv { "results.csv" } else { "results.jsonl" };
+            input.with_extension(extension)
+        }
+    };
+    batch::write_results(&out, format, &results)?;
+    println!("Results written to {}", out.display());
+
+    // Step 4: Summary
+    let valid = results.iter().filter(|result| result.valid).count();
+    let mut reasons: std::collections::BTreeMap<&str, usize> = std::collections::BTreeMap::new();
+    for result in results.iter().filter(|result| !result.valid) {
+        *reasons.entry(result.reason.as_str()).or_insert(0) += 1;
+    }
+    println!("Total:   {}", results.len());
+    println!("Valid:   {}", valid);
+    println!("Invalid: {}", results.len() - valid);
+    for (reason, count) in &reasons {
+        println!("  {:>6}  {}", count, reason);
+    }
+    println!("Verified in {:.2?} ({:.0} rows/s)", duration, results.len() as f64 / duration.as_secs_f64().max(1e-9));
+    Ok(())
+}
+
+fn serve_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Limits, defaulting to a local-only listener
+    let bind = option_value(args, "--bind").unwrap_or("127.0.0.1:8787");
+    let max_body = option_value(args, "--max-body").map(str::parse).transpose()?.unwrap_or(64 * 1024);
+    let max_concurrent = option_value(args, "--max-concurrent").map(str::parse).transpose()?.unwrap_or(num_cpus::get() * 4);
+    let max_window = option_value(args, "--max-window").map(str::parse).transpose()?.unwrap_or(100_000);
+    if max_concurrent == 0 {
+        return Err("--max-concurrent must be at least 1".into());
+    }
+
+    // Step 2: Signing stays off unless asked for, and then needs the key up front
+    let signer = if has_flag(args, "--enable-signing") {
+        let (private_key, address, _) = load_message_signing_key(args)?;
+        println!("Signing enabled for {}", address);
+        Some((private_key, address))
+    } else {
+        None
+    };
+
+    // Step 3: Serve until killed
+    let (server, local) = serve::bind(bind)?;
+    println!("Listening on http://{}", local);
+    serve::run(
+        server,
+        serve::ServeConfig {
+            max_body,
+            max_concurrent,
+            max_window,
+            search_threads: num_cpus::get(),
+            signer,
+        },
+    )
+}
+
+#[derive(serde::Serialize)]
+struct DerivedAddress {
+    index: u32,
+    path: String,
+    public_key: String,
+    address: String,
+}
+
+fn derive_addresses_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Parse the xpub (or kpub, or single-key descriptor) and the range
+    let xpub_input = option_value(args, "--xpub").ok_or("Missing --xpub <xpub>")?.trim();
+    let xpub = descriptor::load_xpub(xpub_input)?;
+    let chain: u32 = option_value(args, "--chain").map(str::parse).transpose()?.unwrap_or(0);
+    let start: u32 = option_value(args, "--start").map(str::parse).transpose()?.unwrap_or(0);
+    let count: u32 = option_value(args, "--count").map(str::parse).transpose()?.unwrap_or(20);
+    // A descriptor already names the network and address type; the flags override it
+    let described = if xpub_input.contains('(') { Some(descriptor::Descriptor::parse(xpub_input)?) } else { None };
+    let prefix = match (option_value(args, "--network"), &described) {
+        (Some(network), _) => parse_network(network)?,
+        (None, Some(described)) => described.network,
+        (None, None) => Prefix::Mainnet,
+    };
+    let ecdsa = has_flag(args, "--ecdsa")
+        || described.is_some_and(|described| described.address_type == descriptor::AddressType::PubKeyEcdsa);
+
+    // Step 2: Derive each key and its address
+    let rows: Vec<DerivedAddress> = derive_chain_public_keys(&xpub, chain, start, count)?
+        .into_iter()
+        .map(|(index, public_key)| {
+            let public_key = public_key.serialize();
+            let address = if ecdsa {
+                Address::new(prefix, Version::PubKeyECDSA, &public_key)
+            } else {
+                Address::new(prefix, Version::PubKey, &public_key[1..33])
+            };
+            DerivedAddress {
+                index,
+                path: format!("m/{}/{}", chain, index),
+                public_key: hex::encode(public_key),
+                address: address.to_string(),
+            }
+        })
+        .collect();
+
+    // Step 3: Format them
+    let contents = match option_value(args, "--format").unwrap_or("text") {
+        "text" => rows
+            .iter()
+            .map(|row| format!("{:<14} {}  {}", row.path, row.address, row.public_key))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "csv" => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for row in &rows {
+                writer.serialize(row)?;
+            }
+            String::from_utf8(writer.into_inner()?)?.trim_end().to_string()
+        }
+        "json" => serde_json::to_string_pretty(&rows)?,
+        other => return Err(format!("Unknown format '{}': use text, csv or json", other).into()),
+    };
+    write_output(args, contents, "Addresses")
+}
+
+// Private keys only leave this program encrypted to --out, or printed with --force.
+// Checked before anything secret is entered.
+fn check_export_destination(args: &[String], what: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if option_value(args, "--out").is_none() && !has_flag(args, "--force") {
+        return Err(format!("Refusing to print the {} to the terminal: use --out <file> to write it encrypted, or --force", what).into());
+    }
+    Ok(())
+}
+
+// Shows what is about to be signed and waits for a yes; --yes skips the question for scripts
+fn confirm_signing(args: &[String], review: &str) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", review);
+    if has_flag(args, "--yes") {
+        return Ok(());
+    }
+    println!("Sign this? Type yes to sign:");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !answer.trim().eq_ignore_ascii_case("yes") {
+        return Err("Signing cancelled. Nothing was signed.".into());
+    }
+    Ok(())
+}
+
+// Makes the user confirm they want a key out of this program
+fn confirm_export(warning: &str) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", warning);
+    println!("Anyone who gets it can spend the funds. Type EXPORT to continue:");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if answer.trim() != "EXPORT" {
+        return Err("Export cancelled".into());
+    }
+    Ok(())
+}
+
+// Encrypts an exported key to --out, or prints it (check_export_destination has seen --force)
+fn write_exported_key(args: &[String], kind: &str, what: &str, secret: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match option_value(args, "--out") {
+        Some(path) => {
+            let password = read_new_password()?;
+            std::fs::write(path, keyfile::EncryptedKey::encrypt(kind, secret, &password)?.to_string() + "\n")?;
+            println!("Encrypted {} written to {}", what, path);
+        }
+        None => println!("{}", secret),
+    }
+    Ok(())
+}
+
+fn export_xprv_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Refuse an unencrypted export before anything secret is entered
+    check_export_destination(args, "xprv")?;
+
+    // Step 2: Derive the account key (m/44'/111111'/0') from the mnemonic
+    let seed = generate_seed_from_mnemonic(&get_or_generate_mnemonic()?);
+    let account_key = create_master_key(&seed)?.derive_path(&DerivationPath::from_str("m/44'/111111'/0'")?)?;
+    let prefix = if has_flag(args, "--kprv") { kaspa_bip32::Prefix::KPRV } else { kaspa_bip32::Prefix::XPRV };
+
+    // Step 3: Make the user confirm they want the key out of this program
+    confirm_export("The account extended private key can sign for every address of this wallet.")?;
+
+    // Step 4: Encrypt it to --out, or print it with --force
+    write_exported_key(args, "xprv", "extended private key", &account_key.to_string(prefix))
+}
+
+fn export_key_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Refuse an unencrypted export before anything secret is entered
+    check_export_destination(args, "private key")?;
+    let index: u32 = option_value(args, "--index").map(str::parse).transpose()?.unwrap_or(0);
+    let chain = if has_flag(args, "--change") { 1 } else { 0 };
+    let prefix = if has_flag(args, "--testnet") { Prefix::Testnet } else { Prefix::Mainnet };
+
+    // Step 2: Derive the child key from the mnemonic
+    let seed = generate_seed_from_mnemonic(&get_or_generate_mnemonic()?);
+    let path = format!("m/44'/111111'/0'/{}/{}", chain, index);
+    let (private_key, _) = derive_kaspa_key_and_address_at(&seed, &path)?;
+    println!("Address: {}", address_for_private_key(&private_key, prefix));
+
+    // Step 3: Make the user confirm they want the key out of this program
+    confirm_export(&format!("The private key at {} can sign for this address.", path))?;
+
+    // Step 4: Encrypt it to --out, or print it with --force
+    write_exported_key(args, "wif", "private key", &wif::encode(&private_key, prefix)?)
+}
+
+fn detect_wallet_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if has_flag(args, "--list") {
+        for scheme in wallet_schemes::WALLET_SCHEMES {
+            let accounts = match scheme.accounts.len() {
+                1 => String::new(),
+                _ => format!(" (accounts {}-{})", scheme.accounts.start, scheme.accounts.end - 1),
+            };
+            let address_type = if scheme.ecdsa { "ECDSA" } else { "Schnorr" };
+            let path = format!("{}/{}", scheme.account_path, scheme.child_path("<chain>", "<i>"));
+            println!("{:<20} {}{} {}: {}", scheme.name, path, accounts, address_type, scheme.wallets);
+        }
+        return Ok(());
+    }
+
+    // Step 1: Get the known address and the phrase
+    let address = option_value(args, "--address").ok_or("Missing --address <known address>")?;
+    let window: u32 = option_value(args, "--window").map(str::parse).transpose()?.unwrap_or(100);
+    let mnemonic = get_or_generate_mnemonic()?;
+    let word_count = mnemonic.word_count();
+    let seed = generate_seed_from_mnemonic(&mnemonic);
+
+    // Step 2: Derive from every scheme in parallel
+    let num_threads = num_cpus::get();
+    println!(
+        "Trying {} wallet schemes on the first {} receive and change addresses using {} threads.",
+        wallet_schemes::WALLET_SCHEMES.len(),
+        window,
+        num_threads
+    );
+    let start_time = std::time::Instant::now();
+    let matches = wallet_schemes::detect_wallet_parallel(&seed, address, window, num_threads)?;
+
+    // Step 3: Report the schemes that reproduce the address
+    if matches.is_empty() {
+        println!("No known wallet scheme derives {} from this phrase (within the first {} addresses of each chain).", address, window);
+        std::process::exit(1);
+    }
+    for found in &matches {
+        println!("Match: {} at {} ({})", found.scheme.name, found.path, found.scheme.wallets);
+        if !found.scheme.word_counts.contains(&word_count) {
+            let expected: Vec<String> = found.scheme.word_counts.iter().map(usize::to_string).collect();
+            println!("  Note: these wallets create {}-word phrases, this one has {} words.", expected.join(" or "), word_count);
+        }
+    }
+    println!("Search completed in {:.2?}", start_time.elapsed());
+    Ok(())
+}
+
+// --threads, or one per core; each thread is an OS thread, so at most a few per core
+fn thread_count(args: &[String], cores: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    let max_threads = cores.max(1) * 4;
+    let num_threads: usize = option_value(args, "--threads").map(str::parse).transpose()?.unwrap_or(cores.max(1));
+    if !(1..=max_threads).contains(&num_threads) {
+        return Err(format!("--threads must be between 1 and {} (4 per CPU core), got {}", max_threads, num_threads).into());
+    }
+    Ok(num_threads)
+}
+
+fn vanity_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Check the pattern, and where a random key will go, before searching
+    let ecdsa = has_flag(args, "--ecdsa");
+    let pattern = Arc::new(vanity::Pattern::new(option_value(args, "--prefix"), option_value(args, "--suffix"), ecdsa)?);
+    let prefix = parse_network(option_value(args, "--network").unwrap_or("mainnet"))?;
+    let num_threads = thread_count(args, num_cpus::get())?;
+    let xpub = option_value(args, "--xpub").map(descriptor::load_xpub).transpose()?;
+    let password = match xpub {
+        Some(_) => None,
+        None => {
+            // A random key is shown or saved as WIF, so check its network has a version byte
+            wif::version(prefix)?;
+            check_export_destination(args, "private key")?;
+            option_value(args, "--out").map(|_| read_new_password()).transpose()?
+        }
+    };
+
+    // Step 2: Estimate the work. Each key matches with probability 1/difficulty, so n keys
+    // find a match with probability 1 - (1 - 1/difficulty)^n.
+    let difficulty = pattern.difficulty();
+    let chance = move |tried: f64| -(tried * (-1.0 / difficulty).ln_1p()).exp_m1();
+    println!("Searching for an address matching {} using {} threads: 1 in {:.0} keys matches.", pattern, num_threads, difficulty);
+    let progress = move |tried: u64, elapsed: std::time::Duration| {
+        let rate = tried as f64 / elapsed.as_secs_f64();
+        println!(
+            "Tried {} keys ({:.0}/s), {:.1}% chance of a match by now. Expected time {}, 50% chance within {}.",
+            tried,
+            rate,
+            100.0 * chance(tried as f64),
+            vanity::format_estimate(difficulty / rate),
+            vanity::format_estimate(std::f64::consts::LN_2 * difficulty / rate)
+        );
+    };
+
+    // Step 3: Search
+    let start_time = std::time::Instant::now();
+    match xpub {
+        Some(xpub) => {
+            let chain: u32 = option_value(args, "--chain").map(str::parse).transpose()?.unwrap_or(0);
+            let start: u32 = option_value(args, "--start").map(str::parse).transpose()?.unwrap_or(0);
+            let count: u32 = option_value(args, "--count").map(str::parse).transpose()?.unwrap_or(1_000_000);
+            let end = start
+                .checked_add(count)
+                .filter(|end| *end <= 1 << 31)
+                .ok_or("Index range goes past the last non-hardened index (2147483647)")?;
+            println!("Searching {} indices gives a {:.1}% chance of a match.", count, 100.0 * chance(count as f64));
+            match vanity::search_xpub_parallel(Arc::clone(&pattern), &xpub, chain, start..end, prefix, num_threads, &progress)? {
+                Some((index, address)) => {
+                    println!("Found {} at m/{}/{} below the xpub.", address, chain, index);
+                    println!("Search completed in {:.2?}", start_time.elapsed());
+                }
+                None => {
+                    println!("No address matches {} in the {} indices searched.", pattern, count);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => {
+            let (private_key, address) = vanity::search_random_parallel(pattern, prefix, num_threads, &progress)?;
+            println!("Found {}", address);
+            println!("Search completed in {:.2?}", start_time.elapsed());
+
+            // Step 4: The key only leaves encrypted, unless --force prints it
+            let key = wif::encode(&private_key, prefix)?;
+            match (option_value(args, "--out"), password) {
+                (Some(path), Some(password)) => {
+                    std::fs::write(path, keyfile::EncryptedKey::encrypt("wif", &key, &password)?.to_string() + "\n")?;
+                    println!("Encrypted private key written to {}", path);
+                }
+                _ => println!("Private key (WIF): {}", key),
+            }
+        }
+    }
+    Ok(())
+}
+
 fn sign_message_flow() -> Result<(), Box<dyn std::error::Error>> {
     // Step 1: Get or generate mnemonic
     let mnemonic = get_or_generate_mnemonic()?;
@@ -50,28 +1330,63 @@ fn sign_message_flow() -> Result<(), Box<dyn std::error::Error>> {
     // Step 2: Generate seed from mnemonic
     let seed = generate_seed_from_mnemonic(&mnemonic);
 
+    sign_message_with_seed(&seed)
+}
+
+fn sign_message_with_seed(seed: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
     // Step 3: Derive Kaspa private key and address
-    let (private_key, address) = derive_kaspa_key_and_address(&seed)?;
+    let (private_key, address) = derive_kaspa_key_and_address(seed)?;
     let address_str = address.to_string();
     
     // Step 4: Generate the extended public key (xpub) for address derivation
-    let master_key = create_master_key(&seed)?;
+    let master_key = create_master_key(seed)?;
     let xpub = create_extended_public_key(&master_key)?;
+    let origin = account_key_origin(&master_key);
     println!("Derived Kaspa address: {}", address);
+    println!("Master fingerprint: {}", hex::encode(origin.fingerprint));
+    println!("Key origin: [{}]", origin);
     println!("Derived Extended Public Key: {}", xpub);
+    println!("Wallet descriptor: {}", descriptor::Descriptor::single_key(Prefix::Mainnet, Some(origin), &xpub, false));
+
+    sign_message_with_key(&private_key, &address_str, "m/44'/111111'/0'/0/0")
+}
+
+fn sign_message_with_private_key_flow() -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Get the key, without echoing it
+    println!("Enter the extended private key (xprv or kprv), or a single private key (hex or WIF):");
+    let key = read_password()?;
+    let key = key.trim();
 
+    // Step 2: Derive the receive key below an extended key, or use a single key as it is
+    let (private_key, address, key_path) = if ["xprv", "kprv", "tprv", "ktrv"].iter().any(|prefix| key.starts_with(prefix)) {
+        derive_kaspa_key_and_address_from_xprv(key)?
+    } else {
+        let (private_key, prefix) = wif::parse_private_key(key)?;
+        (private_key, address_for_private_key(&private_key, prefix), SINGLE_KEY_PATH.to_string())
+    };
+    println!("Derived Kaspa address: {}", address);
+
+    sign_message_with_key(&private_key, &address.to_string(), &key_path)
+}
+
+const SINGLE_KEY_PATH: &str = "single private key, not derived";
+
+fn sign_message_with_key(private_key: &SecretKey, address_str: &str, key_path: &str) -> Result<(), Box<dyn std::error::Error>> {
     // Step 5: Get message to sign
     println!("Enter message to sign:");
     let mut message = String::new();
     io::stdin().read_line(&mut message)?;
     message = message.trim().to_string();
 
-    // Step 6: Sign the message
-    let signature = sign_message(&message, &private_key)?;
+    // Step 6: Show exactly what will be signed, and sign nothing until the user agrees
+    confirm_signing(&[], &review::render(&message, &double_sha256(message.as_bytes()), address_str, key_path))?;
+
+    // Step 7: Sign the message
+    let signature = sign_message(&message, private_key)?;
     println!("Message: {}", message);
     println!("Signature: {}", signature);
 
-    let is_valid = verify_signature(&message, &signature, &address_str)?;
+    let is_valid = verify_signature(&message, &signature, address_str)?;
     println!(
         "Signature verification: {}",
         if is_valid { "Valid" } else { "Invalid" }
@@ -107,14 +1422,148 @@ fn verify_signature_flow() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+fn split_seed_flow() -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Get the seed to split
+    let mnemonic = get_or_generate_mnemonic()?;
+    let seed = generate_seed_from_mnemonic(&mnemonic);
+
+    // Step 2: Get the group layout
+    println!("Enter groups as member_threshold/member_count separated by commas (e.g. 2/3 or 2/3,1/1):");
+    let mut groups_input = String::new();
+    io::stdin().read_line(&mut groups_input)?;
+    let groups = parse_slip39_groups(groups_input.trim())?;
+
+    let group_threshold = if groups.len() > 1 {
+        println!("Enter how many groups are required to recover the seed:");
+        let mut threshold_input = String::new();
+        io::stdin().read_line(&mut threshold_input)?;
+        threshold_input.trim().parse::<u8>()?
+    } else {
+        1
+    };
+
+    // Step 3: Optional passphrase protecting the shares
+    println!("Enter a passphrase for the shares (or leave empty for none):");
+    let passphrase = read_password()?;
+
+    // Step 4: Split and print the shares
+    let shares = slip39::split_master_secret(&seed, &passphrase, group_threshold, &groups, 1, true)?;
+    for (group_index, (group, group_shares)) in groups.iter().zip(&shares).enumerate() {
+        println!(
+            "Group {} ({} of {} shares required):",
+            group_index + 1,
+            group.member_threshold,
+            group.member_count
+        );
+        for (member_index, share) in group_shares.iter().enumerate() {
+            println!("  Share {}: {}", member_index + 1, share);
+        }
+    }
+    println!("{} of {} groups are required to recover the seed.", group_threshold, groups.len());
+    Ok(())
+}
+
+fn parse_slip39_groups(input: &str) -> Result<Vec<slip39::GroupSpec>, Box<dyn std::error::Error>> {
+    input
+        .split(',')
+        .map(|group| {
+            let (threshold, count) = group
+                .trim()
+                .split_once('/')
+                .ok_or_else(|| format!("Invalid group '{}', expected member_threshold/member_count", group.trim()))?;
+            Ok(slip39::GroupSpec {
+                member_threshold: threshold.trim().parse()?,
+                member_count: count.trim().parse()?,
+            })
+        })
+        .collect()
+}
+
+fn recover_seed_flow() -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Collect shares until an empty line
+    println!("Enter SLIP-39 shares, one per line, followed by an empty line:");
+    let mut shares = Vec::new();
+    loop {
+        let mut share = String::new();
+        if io::stdin().read_line(&mut share)? == 0 || share.trim().is_empty() {
+            break;
+        }
+        shares.push(share.trim().to_string());
+    }
+
+    // Step 2: Passphrase the shares were created with
+    println!("Enter the passphrase for the shares (or leave empty for none):");
+    let passphrase = read_password()?;
+
+    // Step 3: Recombine the seed and continue with the regular signing flow
+    let seed = slip39::combine_mnemonics(&shares, &passphrase)?;
+    println!("Seed recovered from {} shares.", shares.len());
+    sign_message_with_seed(&seed)
+}
+
+fn repair_mnemonic_flow() -> Result<(), Box<dyn std::error::Error>> {
+    // Step 1: Get the damaged phrase
+    println!("Enter the mnemonic phrase to repair (leave out a word you don't know):");
+    let input = read_password()?;
+    let words: Vec<String> = input.split_whitespace().map(|w| w.to_lowercase()).collect();
+
+    // Step 2: Get something the correct phrase must reproduce
+    println!("Enter a known Kaspa address, the account extended public key or its descriptor:");
+    let mut known_input = String::new();
+    io::stdin().read_line(&mut known_input)?;
+    let known_input = known_input.trim();
+    let target = if known_input.starts_with("kaspa:") && !known_input.contains('(') {
+        mnemonic_repair::RepairTarget::Address {
+            address: Address::try_from(known_input)?.to_string(),
+            window: 20,
+        }
+    } else {
+        mnemonic_repair::RepairTarget::Xpub(descriptor::load_xpub(known_input)?)
+    };
+
+    // Step 3: Enumerate candidates and search them in parallel
+    let candidates = mnemonic_repair::repair_candidates(&words, 2)?;
+    let num_threads = num_cpus::get();
+    println!("Checking {} candidate phrases using {} threads.", candidates.len(), num_threads);
+    let start_time = std::time::Instant::now();
+    match mnemonic_repair::find_matching_mnemonic_parallel(candidates, target, num_threads)? {
+        Some(mnemonic) => println!("Corrected phrase: {}", mnemonic),
+        None => println!("No single-word repair matches the given address or extended public key."),
+    }
+    let duration = start_time.elapsed();
+    println!("Search completed in {:.2?}", duration);
+    Ok(())
+}
+
 fn check_address_against_xpub_flow() -> Result<(), Box<dyn std::error::Error>> {
     // Step 1: get xpub
-    println!("Enter extended public key:");
+    println!("Enter extended public key or wallet descriptor:");
     let mut xpub_input = String::new();
     io::stdin().read_line(&mut xpub_input)?;
-    let xpub_input = load_xpub_from_string(xpub_input.trim())?;
+    let account = descriptor::load_account_descriptor(xpub_input.trim())?;
+    let (xpub_input, origin) = (account.xpub()?, account.keys[0].origin.clone());
     //load_xpub_from_string
 
+    // Step 1b: check a supplied key origin against the key's own depth and parent
+    let attrs = xpub_input.attrs();
+    match origin {
+        Some(origin) => {
+            if origin.verify(&xpub_input)? {
+                println!("Key origin [{}] matches the key's depth, child number and master fingerprint.", origin);
+            } else {
+                println!(
+                    "Key origin [{}] matches the key's depth and child number (the master fingerprint can't be checked from a key at depth {}).",
+                    origin, attrs.depth
+                );
+            }
+        }
+        None => println!(
+            "No key origin given. Key depth {}, parent fingerprint {}.",
+            attrs.depth,
+            hex::encode(attrs.parent_fingerprint)
+        ),
+    }
+
     // Step 2: get address to check
     println!("Enter wallet address:");
     let mut address_input = String::new();
@@ -127,7 +1576,8 @@ fn check_address_against_xpub_flow() -> Result<(), Box<dyn std::error::Error>> {
     let num_derivations = 2_000_000_000;
     // Step 4: // Check if the address belongs to this xpub (searching first 1000000 addresses)
     let start_time = std::time::Instant::now();
-    match check_address_belongs_to_xpub_parallel(&xpub_input,address_input,num_derivations,num_threads)? {
+    let ecdsa = account.address_type == descriptor::AddressType::PubKeyEcdsa;
+    match check_address_belongs_to_xpub_with(&xpub_input,address_input,num_derivations,num_threads,account.network,ecdsa)? {
         Some(index) => println!("Address found! It's the {}th derived address from this xpub.", index),
         None => println!("Address does not belong to this xpub (within first {} addresses).",num_derivations)
     }
@@ -136,90 +1586,6 @@ fn check_address_against_xpub_flow() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-type KaspaXPub = ExtendedPublicKey<KaspaSecp256k1PublicKey>;
-
-fn load_xpub_from_string(xpub_str: &str) -> Result<KaspaXPub, Box<dyn std::error::Error>> {
-    // First, decode the Base58 encoded string (without checksum, as bs58 crate doesn't have built-in check)
-    let decoded = bs58::decode(xpub_str).into_vec()?;
-    
-    // Check if we have 82 bytes (78 + 4 byte checksum)
-    if decoded.len() == 82 {
-        // Take only the first 78 bytes, discarding the checksum
-        let data = &decoded[0..78];
-        
-        // Extract the components of the extended key
-        let depth = data[4];
-        
-        let mut fingerprint = [0u8; 4];
-        fingerprint.copy_from_slice(&data[5..9]);
-        
-        let child_number = kaspa_bip32::ChildNumber(u32::from_be_bytes([data[9], data[10], data[11], data[12]]));
-        
-        let mut chain_code = [0u8; 32];
-        chain_code.copy_from_slice(&data[13..45]);
-        
-        let mut pubkey_bytes = [0u8; 33];
-        pubkey_bytes.copy_from_slice(&data[45..78]);
-        
-        // Create the public key
-        let public_key = KaspaSecp256k1PublicKey::from_slice(&pubkey_bytes)?;
-        
-        // Create extended key attributes
-        let attrs = kaspa_bip32::ExtendedKeyAttrs {
-            depth,
-            parent_fingerprint: fingerprint,
-            child_number,
-            chain_code,
-        };
-        
-        // Create the extended public key
-        let xpub = ExtendedPublicKey::from_public_key(public_key, &attrs);
-        
-        return Ok(xpub);
-    } else if decoded.len() == 78 {
-        // Extract the components of the extended key
-        // Format: [4-byte version] [1-byte depth] [4-byte fingerprint] [4-byte child number] [32-byte chain code] [33-byte public key]
-    
-        // Extract depth (1 byte)
-        let depth = decoded[4];
-        
-        // Extract parent fingerprint (4 bytes)
-        let mut fingerprint = [0u8; 4];
-        fingerprint.copy_from_slice(&decoded[5..9]);
-        
-        // Extract child number (4 bytes)
-        let child_number = kaspa_bip32::ChildNumber(BigEndian::read_u32(&decoded[9..13]));
-        
-        // Extract chain code (32 bytes)
-        let mut chain_code = [0u8; 32];
-        chain_code.copy_from_slice(&decoded[13..45]);
-        
-        // Extract public key (33 bytes)
-        let mut pubkey_bytes = [0u8; 33];
-        pubkey_bytes.copy_from_slice(&decoded[45..78]);
-        
-        // Parse the secp256k1 public key using Kaspa's type
-        let public_key = KaspaSecp256k1PublicKey::from_slice(&pubkey_bytes)?;
-        
-        // Use the ExtendedKeyAttrs struct directly instead of going through a namespace
-        let attrs = kaspa_bip32::ExtendedKeyAttrs {
-            depth,
-            parent_fingerprint: fingerprint,
-            child_number,
-            chain_code,
-        };
-
-        // Create the extended public key using from_public_key
-        let xpub = ExtendedPublicKey::from_public_key(public_key, &attrs);
-
-        Ok(xpub)
-    } else {
-        return Err(format!("Invalid extended public key length: got {} bytes, expected 78 or 82", decoded.len()).into());
-    }
-    
-    
-}
-
 fn diagnose_xpub_string(xpub_str: &str) -> Result<(), Box<dyn std::error::Error>> {
     println!("Diagnosing xpub string: {}", xpub_str);
     println!("Length of string: {}", xpub_str.len());
@@ -350,6 +1716,15 @@ fn load_xpub_from_string_improved(xpub_str: &str) -> Result<ExtendedPublicKey<Ka
     Ok(xpub)
 }
 
+// The master key fingerprint and account path of the xpub from create_extended_public_key,
+// as written before the xpub in descriptors: [73c5da0a/44'/111111'/0']
+fn account_key_origin(master_key: &ExtendedPrivateKey<KaspaSecretKey>) -> descriptor::KeyOrigin {
+    descriptor::KeyOrigin {
+        fingerprint: master_key.public_key().fingerprint(),
+        path: vec!["44'".to_string(), "111111'".to_string(), "0'".to_string()],
+    }
+}
+
 // Modified to use KaspaPublicKey instead of secp256k1::PublicKey
 fn create_extended_public_key(master_key: &ExtendedPrivateKey<KaspaSecretKey>) -> Result<ExtendedPublicKey<KaspaSecp256k1PublicKey>, Box<dyn std::error::Error>> {
     // Create a context for Secp256k1 operations
@@ -381,8 +1756,6 @@ fn create_extended_public_key(master_key: &ExtendedPrivateKey<KaspaSecretKey>) -
     Ok(xpub)
 }
 
-
-
 fn create_master_key(seed: &[u8]) -> Result<ExtendedPrivateKey<KaspaSecretKey>, Box<dyn std::error::Error>> {
     // Generate master key from seed
     let master_key = ExtendedPrivateKey::<KaspaSecretKey>::new(seed)?;
@@ -416,159 +1789,105 @@ fn get_or_generate_mnemonic() -> Result<Mnemonic, Box<dyn std::error::Error>> {
     Ok(mnemonic)
 }
 
-fn generate_seed_from_mnemonic(mnemonic: &Mnemonic) -> Vec<u8> {
-    // For BIP39, we typically use an empty passphrase
-    let passphrase = "";
-    let seed_array = mnemonic.to_seed(passphrase);
-    seed_array.to_vec() // Convert [u8; 64] to Vec<u8>
+// A password for a new key file, typed twice
+fn read_new_password() -> Result<String, Box<dyn std::error::Error>> {
+    println!("Enter a password to encrypt the key file:");
+    let password = read_password()?;
+    if password.is_empty() {
+        return Err("The password must not be empty".into());
+    }
+    println!("Repeat the password:");
+    if read_password()? != password {
+        return Err("Passwords do not match".into());
+    }
+    Ok(password)
 }
-// Wrapper for thread errors
-#[derive(Debug)]
-struct ThreadError(String);
 
-impl std::fmt::Display for ThreadError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Thread error: {}", self.0)
+// The key messages are signed with: from an xprv/kprv (--xprv), a single hex or WIF key
+// (--private-key), or a file from export-xprv or export-key (--key-file); otherwise
+// from the mnemonic
+// The signing key, its address and where it comes from, for the review
+fn load_message_signing_key(args: &[String]) -> Result<(SecretKey, Address, String), Box<dyn std::error::Error>> {
+    if let Some(path) = option_value(args, "--key-file") {
+        let encrypted = keyfile::EncryptedKey::parse(&std::fs::read_to_string(path)?)?;
+        println!("Enter the key file password:");
+        let password = read_password()?;
+        return match encrypted.kind.as_str() {
+            "xprv" => derive_kaspa_key_and_address_from_xprv(&encrypted.decrypt("xprv", &password)?),
+            "wif" => {
+                let (private_key, prefix) = wif::decode(&encrypted.decrypt("wif", &password)?)?;
+                Ok((private_key, address_for_private_key(&private_key, prefix), SINGLE_KEY_PATH.to_string()))
+            }
+            other => Err(format!("Key file holds a {}, which can't sign messages", other).into()),
+        };
     }
+    if has_flag(args, "--xprv") {
+        println!("Enter the extended private key (xprv or kprv):");
+        return derive_kaspa_key_and_address_from_xprv(read_password()?.trim());
+    }
+    if has_flag(args, "--private-key") {
+        println!("Enter the private key (64 hex digits or WIF):");
+        let (private_key, prefix) = wif::parse_private_key(&read_password()?)?;
+        return Ok((private_key, address_for_private_key(&private_key, prefix), SINGLE_KEY_PATH.to_string()));
+    }
+    let mnemonic = get_or_generate_mnemonic()?;
+    let seed = generate_seed_from_mnemonic(&mnemonic);
+    let (private_key, address) = derive_kaspa_key_and_address(&seed)?;
+    Ok((private_key, address, "m/44'/111111'/0'/0/0".to_string()))
 }
 
-impl StdError for ThreadError {}
+// Same key and address as derive_kaspa_key_and_address, from an extended private key
+// instead of a seed: m/0/0 below an account key (m/44'/111111'/0'), or the full path
+// below a master key. The base58 checksum is checked when the key is decoded, and
+// testnet prefixes (ktrv, tprv) give testnet addresses. Also returns the path used.
+fn derive_kaspa_key_and_address_from_xprv(xprv: &str) -> Result<(SecretKey, Address, String), Box<dyn std::error::Error>> {
+    let extended_key = kaspa_bip32::ExtendedKey::from_str(xprv).map_err(|e| format!("Invalid extended private key: {}", e))?;
+    let prefix = match extended_key.prefix.as_str() {
+        "kprv" | "xprv" => Prefix::Mainnet,
+        "ktrv" | "tprv" => Prefix::Testnet,
+        other => return Err(format!("Expected an xprv or kprv, got a {} key", other).into()),
+    };
+    let key = ExtendedPrivateKey::<KaspaSecretKey>::try_from(extended_key)?;
+    let path = match key.attrs().depth {
+        0 => "m/44'/111111'/0'/0/0",
+        3 => "m/0/0",
+        depth => return Err(format!("Expected a master or account (m/44'/111111'/0') key, got a key at depth {}", depth).into()),
+    };
+    let child_key = key.derive_path(&DerivationPath::from_str(path)?)?;
 
-impl From<kaspa_bip32::Error> for ThreadError {
-    fn from(err: kaspa_bip32::Error) -> Self {
-        ThreadError(err.to_string())
-    }
+    let private_key = SecretKey::from_slice(&child_key.private_key().to_bytes())?;
+    let path = if path.starts_with("m/0") { "m/0/0 below the account key".to_string() } else { path.to_string() };
+    Ok((private_key, address_for_private_key(&private_key, prefix), path))
 }
 
-impl From<&str> for ThreadError {
-    fn from(s: &str) -> Self {
-        ThreadError(s.to_string())
-    }
+// The Schnorr address of a single key, as derive_kaspa_key_and_address makes it
+fn address_for_private_key(private_key: &SecretKey, prefix: Prefix) -> Address {
+    let public_key = secp256k1::PublicKey::from_secret_key(&Secp256k1::new(), private_key);
+    Address::new(prefix, Version::PubKey, &public_key.serialize()[1..33])
 }
 
-// Updated to use the correct PublicKey type
-fn check_address_belongs_to_xpub_parallel(
-    xpub: &ExtendedPublicKey<KaspaSecp256k1PublicKey>,
-    address_to_check: &str,
-    search_limit: u32,
-    num_threads: usize
-) -> Result<Option<u32>, Box<dyn std::error::Error>> {
-    // Parse the address to check
-    let address_to_check = Address::constructor(address_to_check);
-    let address_to_check_str = address_to_check.to_string();
-    
-    // Derive the change path first (external chain is 0)
-    let change_path = DerivationPath::from_str("m/0")?;
-    let change_xpub = xpub.clone().derive_path(&change_path)?;
-    
-    // Share the xpub and result across threads
-    let change_xpub = Arc::new(change_xpub);
-    let found_index = Arc::new(Mutex::new(None));
-    let should_exit = Arc::new(Mutex::new(false));
-    
-    // Create thread handles
-    let mut handles = vec![];
-    
-    // Calculate chunk size for each thread
-    let chunk_size = (search_limit as usize + num_threads - 1) / num_threads;
-    
-    // Spawn threads
-    for thread_id in 0..num_threads {
-        // Calculate range for this thread
-        let start = thread_id as u32 * chunk_size as u32;
-        let end = std::cmp::min(start + chunk_size as u32, search_limit);
-        
-        // Skip if we're already past the search limit
-        if start >= search_limit {
-            continue;
-        }
-        
-        // Clone Arc references for this thread
-        let change_xpub = Arc::clone(&change_xpub);
-        let found_index = Arc::clone(&found_index);
-        let should_exit = Arc::clone(&should_exit);
-        let address_to_check_str = address_to_check_str.clone();
-        
-        // Spawn the thread
-        let handle = thread::spawn(move || -> Result<(), ThreadError> {
-            // Check if we should exit early
-            if *should_exit.lock().unwrap() {
-                return Ok(());
-            }
-            
-            // Process this thread's range
-            for i in start..end {
-                // Check if another thread found the address
-                if *should_exit.lock().unwrap() {
-                    break;
-                }
-                
-                // Derive the i-th child
-                let index_path = DerivationPath::from_str(&format!("m/{}", i))?;
-                let child_xpub = <ExtendedPublicKey<kaspa_bip32::secp256k1::PublicKey> as Clone>::clone(&change_xpub).derive_path(&index_path)?;
-                
-                // Get the public key
-                let public_key = child_xpub.public_key();
-                
-                // Extract the X coordinate from the compressed public key
-                let pubkey_bytes = public_key.serialize();
-                let x_only_pubkey = match pubkey_bytes[0] {
-                    0x02 | 0x03 => &pubkey_bytes[1..33], // Take 32 bytes after the prefix byte
-                    _ => return Err("Unexpected public key format".into()),
-                };
-                
-                // Create the Kaspa address
-                let derived_address = Address::new(Prefix::Mainnet, Version::PubKey, x_only_pubkey);
-                 
-                //println!("Derived Address: {}, Index Path: {}",derived_address,index_path);
-                
-                // Check if this address matches the one we're looking for
-                if derived_address.to_string() == address_to_check_str {
-                    // Address found, update the shared result
-                    let mut found = found_index.lock().unwrap();
-                    *found = Some(i);
-                    
-                    // Signal other threads to exit
-                    let mut exit = should_exit.lock().unwrap();
-                    *exit = true;
-                    
-                    break;
-                }
-            }
-            
-            Ok(())
-        });
-        
-        handles.push(handle);
-    }
-    
-    // Wait for all threads to complete
-    for handle in handles {
-        match handle.join() {
-            Ok(result) => {
-                if let Err(e) = result {
-                    return Err(Box::new(ThreadError(format!("Thread error: {}", e))));
-                }
-            }
-            Err(_) => {
-                return Err("Thread panicked".into());
-            }
-        }
-    }
-    
-    // Return the result
-    Ok(*found_index.lock().unwrap())
+fn generate_seed_from_mnemonic(mnemonic: &Mnemonic) -> Vec<u8> {
+    // For BIP39, we typically use an empty passphrase
+    let passphrase = "";
+    let seed_array = mnemonic.to_seed(passphrase);
+    seed_array.to_vec() // Convert [u8; 64] to Vec<u8>
 }
 
 fn derive_kaspa_key_and_address(
     seed: &[u8],
+) -> Result<(SecretKey, Address), Box<dyn std::error::Error>> {
+    // Use BIP44 derivation path for Kaspa:
+    // m/44'/111111'/0'/0/0 (Kaspa uses 111111 as its coin type)
+    derive_kaspa_key_and_address_at(seed, "m/44'/111111'/0'/0/0")
+}
+
+fn derive_kaspa_key_and_address_at(
+    seed: &[u8],
+    path_str: &str,
 ) -> Result<(SecretKey, Address), Box<dyn std::error::Error>> {
     // Create a context for Secp256k1 operations
     let secp = Secp256k1::new();
 
-    // Use BIP44 derivation path for Kaspa:
-    // m/44'/111111'/0'/0/0 (Kaspa uses 111111 as its coin type)
-    let path_str = "m/44'/111111'/0'/0/0";
     let path = DerivationPath::from_str(path_str)?;
 
     // Generate master key from seed using KaspaSecretKey
@@ -601,99 +1920,184 @@ fn derive_kaspa_key_and_address(
     Ok((private_key, address))
 }
 
-fn sign_message(
-    message: &str,
-    private_key: &SecretKey,
-) -> Result<String, Box<dyn std::error::Error>> {
-    // Create a context for Secp256k1 operations
-    let secp = Secp256k1::new();
-
-    // Hash the message (Kaspa typically uses double SHA256)
-    let message_hash = double_sha256(message);
-
-    // Create a secp256k1 message object from the hash
-    let secp_message = Message::from_slice(&message_hash)?;
-
-    // Sign the message
-    let signature = secp.sign_ecdsa(&secp_message, private_key);
+// Same digest as double_sha256, but reads the data in chunks so large files never sit in memory
+fn double_sha256_reader<R: Read>(mut reader: R) -> io::Result<([u8; 32], u64)> {
+    let mut hasher1 = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher1.update(&buffer[..read]);
+        total += read as u64;
+    }
+    let first_hash = hasher1.finalize();
 
-    // Return the signature as a hex string
-    Ok(hex::encode(signature.serialize_compact()))
+    let mut hasher2 = Sha256::new();
+    hasher2.update(first_hash);
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&hasher2.finalize());
+    Ok((output, total))
 }
 
-fn verify_signature(
-    message: &str,
-    signature_hex: &str,
-    address: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    // Step 1: Parse the Kaspa address
-    let kaspa_address = Address::constructor(address);
-
-    // Step 2: Extract the public key data from the address
-    // Kaspa address contains the X coordinate of the public key
-    let pubkey_data = &kaspa_address.payload;
-
-    // Step 3: Create a context for Secp256k1 operations
-    let secp = Secp256k1::new();
-
-    // Hash the message
-    let message_hash = double_sha256(message);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
 
-    // Create a secp256k1 message object from the hash
-    let secp_message = Message::from_slice(&message_hash)?;
+    use crate::common::{entries, field, fixture};
 
-    // Parse the signature from hex
-    let signature_bytes = hex::decode(signature_hex)?;
-    let signature = secp256k1::ecdsa::Signature::from_compact(&signature_bytes)?;
-
-    // We need to reconstruct the full public key from just the X coordinate
-    // This requires trying both possible Y coordinates (even and odd)
+    fn batch_row(entry: &Value, algorithm: Option<&str>) -> batch::BatchRow {
+        let mut row: batch::BatchRow = serde_json::from_value(entry.clone()).unwrap();
+        row.algorithm = algorithm.map(str::to_string).or(row.algorithm);
+        row
+    }
 
-    // Try with even Y coordinate (02 prefix)
-    let mut even_pubkey_bytes = vec![0x02];
-    even_pubkey_bytes.extend_from_slice(pubkey_data);
+    #[test]
+    fn signer_derives_vector_addresses() {
+        let vectors = fixture(include_str!("../tests/vectors/kaspa_addresses.json"));
+        for vector in entries(&vectors, "vectors") {
+            let mnemonic = Mnemonic::parse_in(Language::English, field(vector, "mnemonic")).unwrap();
+            let seed = generate_seed_from_mnemonic(&mnemonic);
+            let master_key = create_master_key(&seed).unwrap();
+            assert_eq!(create_extended_public_key(&master_key).unwrap().to_string(None), field(vector, "xpub"));
+            let origin = account_key_origin(&master_key);
+            assert_eq!(hex::encode(origin.fingerprint), field(vector, "master_fingerprint"));
+            assert_eq!(format!("m/{}", origin.path.join("/")), field(vector, "account_path"));
+
+            for entry in entries(vector, "addresses") {
+                let (_, address) = derive_kaspa_key_and_address_at(&seed, field(entry, "path")).unwrap();
+                assert_eq!(address.to_string(), field(entry, "address"), "address at {}", field(entry, "path"));
+            }
+            let (_, address) = derive_kaspa_key_and_address(&seed).unwrap();
+            assert_eq!(address.to_string(), field(&entries(vector, "addresses")[0], "address"));
+
+            // A kpub loads like its xpub wherever the tool takes one
+            let kpub = descriptor::load_xpub(field(vector, "kpub")).unwrap();
+            let keys = derive_chain_public_keys(&kpub, 0, 0, 5).unwrap();
+            for ((_, public_key), entry) in keys.iter().zip(entries(vector, "addresses")) {
+                let address = Address::new(Prefix::Mainnet, Version::PubKey, &public_key.serialize()[1..33]);
+                assert_eq!(address.to_string(), field(entry, "address"));
+            }
+        }
+    }
 
-    // Try with odd Y coordinate (03 prefix)
-    let mut odd_pubkey_bytes = vec![0x03];
-    odd_pubkey_bytes.extend_from_slice(pubkey_data);
+    #[test]
+    fn derive_ranges_are_bounded() {
+        let vectors = fixture(include_str!("../tests/vectors/kaspa_addresses.json"));
+        let kpub = descriptor::load_xpub(field(&entries(&vectors, "vectors")[0], "kpub")).unwrap();
+        // Rejected before anything is derived or allocated
+        assert!(derive_chain_public_keys(&kpub, 0, 0, MAX_DERIVE_COUNT + 1).is_err());
+        assert!(derive_chain_public_keys(&kpub, 0, 0, u32::MAX).is_err());
+        assert!(derive_chain_public_keys(&kpub, 0, (1 << 31) - 1, 2).is_err());
+        let last = derive_chain_public_keys(&kpub, 0, (1 << 31) - 1, 1).unwrap();
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].0, (1 << 31) - 1);
+    }
 
-    // Try to parse both possible public keys
-    let even_pubkey_result = secp256k1::PublicKey::from_slice(&even_pubkey_bytes);
-    let odd_pubkey_result = secp256k1::PublicKey::from_slice(&odd_pubkey_bytes);
+    #[test]
+    fn private_key_vectors() {
+        let vectors = fixture(include_str!("../tests/vectors/message_signing.json"));
+        for vector in entries(&vectors, "ecdsa") {
+            let (private_key, prefix) = wif::parse_private_key(field(vector, "private_key")).unwrap();
+            assert_eq!(address_for_private_key(&private_key, prefix).to_string(), field(vector, "address"));
+
+            let encoded = wif::encode(&private_key, prefix).unwrap();
+            assert_eq!(wif::parse_private_key(&encoded).unwrap(), (private_key, prefix));
+            let mut tampered = bs58::decode(&encoded).into_vec().unwrap();
+            tampered[37] ^= 1;
+            assert!(wif::decode(&bs58::encode(tampered).into_string()).is_err(), "WIF with a bad checksum accepted");
+        }
+    }
 
-    // Try verification with both possible public keys
-    match (even_pubkey_result, odd_pubkey_result) {
-        (Ok(even_pubkey), _) => {
-            if secp
-                .verify_ecdsa(&secp_message, &signature, &even_pubkey)
-                .is_ok()
-            {
-                return Ok(true);
+    #[test]
+    fn message_signing_vectors() {
+        let vectors = fixture(include_str!("../tests/vectors/message_signing.json"));
+        for algorithm in ["ecdsa", "schnorr"] {
+            for vector in entries(&vectors, algorithm) {
+                let result = batch::verify_row(&batch_row(vector, Some(algorithm)));
+                assert!(result.valid, "{} signature of {:?}: {}", algorithm, field(vector, "message"), result.reason);
             }
         }
-        (_, Ok(odd_pubkey)) => {
-            if secp
-                .verify_ecdsa(&secp_message, &signature, &odd_pubkey)
-                .is_ok()
-            {
-                return Ok(true);
-            }
+    }
+
+    #[test]
+    fn invalid_signatures_are_rejected() {
+        let vectors = fixture(include_str!("../tests/vectors/message_signing.json"));
+        for invalid in entries(&vectors, "invalid") {
+            let result = batch::verify_row(&batch_row(invalid, None));
+            assert!(!result.valid, "{} {} verified", field(invalid, "algorithm"), field(invalid, "case"));
         }
-        _ => {}
     }
 
-    // If we reach here, neither key verified the signature
-    Ok(false)
-}
+    #[test]
+    fn transaction_review_shows_amounts_fee_and_signed_inputs() {
+        use transaction::{ScriptPublicKey, Transaction, TransactionInput, TransactionOutpoint, TransactionOutput, UtxoEntry};
+        // P2PK scripts of the abandon key at m/44'/111111'/0'/0/0 and of another key
+        let ours = hex::decode("201bacea84ca721c95d67ecace19bc499a77c03726bc8739af637bcd89abaaf058ac").unwrap();
+        let other = hex::decode("20fcef4c106cf11135bbd70f02a726a92162d2fb8b22f0469126f800862ad884e8ac").unwrap();
+        let input = |index: u32, amount: u64, script: &[u8], signature_script: Vec<u8>| TransactionInput {
+            previous_outpoint: TransactionOutpoint { transaction_id: vec![0xab; 32], index },
+            signature_script,
+            sequence: 0,
+            sig_op_count: 1,
+            utxo_entry: UtxoEntry {
+                amount,
+                script_public_key: ScriptPublicKey { version: 0, script: script.to_vec() },
+                block_daa_score: 0,
+                is_coinbase: false,
+            },
+        };
+        let mut tx = Transaction {
+            version: 0,
+            inputs: vec![input(0, 100_000_000, &ours, vec![]), input(1, 300, &other, vec![])],
+            outputs: vec![TransactionOutput {
+                value: 99_990_000,
+                script_public_key: ScriptPublicKey { version: 0, script: other.clone() },
+            }],
+            lock_time: 0,
+            subnetwork_id: vec![0; 20],
+            gas: 0,
+            payload: vec![],
+            mass: 0,
+        };
+        let paths: std::collections::HashMap<Vec<u8>, String> = [(ours.clone(), "m/44'/111111'/0'/0/0".to_string())].into();
+
+        let review = render_transaction_review(&tx, &paths);
+        let address = script::address_from_script(&ours, Prefix::Mainnet).unwrap().to_string();
+        assert_eq!(address, "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh");
+        assert!(review.contains(&format!("1.00000000 KAS {} [ours m/44'/111111'/0'/0/0] (signing)", address)));
+        assert!(review.contains("  #0 0.99990000 KAS kaspa:"));
+        assert!(review.contains("Fee: 0.00010300 KAS"));
+        assert!(review.ends_with("Signing:\n  #0 with the key at m/44'/111111'/0'/0/0"));
+
+        tx.inputs[0].signature_script = vec![0x41; 66];
+        tx.outputs[0].value = 200_000_000;
+        let review = render_transaction_review(&tx, &paths);
+        assert!(review.contains("(already signed)"));
+        assert!(review.contains("WARNING: Outputs exceed inputs by 0.99999700 KAS"));
+        assert!(review.ends_with("Signing: no inputs pay to this wallet"));
+    }
 
-fn double_sha256(data: &str) -> [u8; 32] {
-    let mut hasher1 = Sha256::new();
-    hasher1.update(data.as_bytes());
-    let first_hash = hasher1.finalize();
+    #[test]
+    fn thread_count_is_bounded() {
+        let args = |threads: &str| vec!["--threads".to_string(), threads.to_string()];
+        assert_eq!(thread_count(&[], 8).unwrap(), 8);
+        assert_eq!(thread_count(&[], 0).unwrap(), 1);
+        assert_eq!(thread_count(&args("3"), 8).unwrap(), 3);
+        assert_eq!(thread_count(&args("32"), 8).unwrap(), 32);
+        assert!(thread_count(&args("33"), 8).is_err());
+        assert!(thread_count(&args("100000"), 8).is_err());
+        assert!(thread_count(&args("0"), 8).is_err());
+        assert!(thread_count(&args("-1"), 8).is_err());
+    }
 
-    let mut hasher2 = Sha256::new();
-    hasher2.update(first_hash);
-    let mut output = [0u8; 32];
-    output.copy_from_slice(&hasher2.finalize());
-    output
+    #[test]
+    fn reserves_verify_requires_the_expected_challenge() {
+        let args: Vec<String> = ["verify", "--proof", "proof.json"].iter().map(|arg| arg.to_string()).collect();
+        let error = reserves_command(&args).unwrap_err();
+        assert_eq!(error.to_string(), "Missing --challenge <expected text>");
+    }
 }
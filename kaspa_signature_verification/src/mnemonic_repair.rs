@@ -0,0 +1,281 @@
+// Recovery of a BIP39 phrase with one missing, swapped or mistyped word.
+// Candidates are filtered by checksum first and then matched against a known
+// account xpub or receive address, using the same thread layout as the xpub search.
+use bip39::{Language, Mnemonic};
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_bip32::DerivationPath;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::{create_extended_public_key, create_master_key, generate_seed_from_mnemonic, KaspaXPub, ThreadError};
+
+/// What a repaired phrase must reproduce to be accepted.
+pub enum RepairTarget {
+    /// The account xpub at m/44'/111111'/0'
+    Xpub(KaspaXPub),
+    /// A receive address at m/44'/111111'/0'/0/i, searched within the first `window` indices
+    Address { address: String, window: u32 },
+}
+
+/// Builds every candidate phrase reachable from `words` by a single repair.
+///
+/// - one missing word (when the phrase is one word short of a valid length)
+/// - an unknown word replaced by the wordlist entries within `max_distance` edits,
+///   or by every word when nothing is that close
+/// - two swapped words
+/// - one valid but wrong word replaced by the wordlist entries within `max_distance` edits
+pub fn repair_candidates(words: &[String], max_distance: usize) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let wordlist = Language::English.word_list();
+    let is_known = |word: &str| wordlist.binary_search(&word).is_ok();
+    let unknown: Vec<usize> = (0..words.len()).filter(|&i| !is_known(&words[i])).collect();
+
+    let mut candidates = Vec::new();
+    if [11, 14, 17, 20, 23].contains(&words.len()) {
+        if !unknown.is_empty() {
+            return Err("Only one word can be repaired: the phrase is both missing a word and contains unknown words".into());
+        }
+        for position in 0..=words.len() {
+            for word in wordlist.iter() {
+                let mut candidate: Vec<&str> = words.iter().map(String::as_str).collect();
+                candidate.insert(position, word);
+                candidates.push(candidate.join(" "));
+            }
+        }
+        return Ok(candidates);
+    }
+
+    if ![12, 15, 18, 21, 24].contains(&words.len()) {
+        return Err(format!("Invalid phrase length: got {} words, expected 12 to 24 words or one less", words.len()).into());
+    }
+
+    match unknown.len() {
+        0 => {
+            // Swapped words
+            for i in 0..words.len() {
+                for j in i + 1..words.len() {
+                    let mut candidate = words.to_vec();
+                    candidate.swap(i, j);
+                    candidates.push(candidate.join(" "));
+                }
+            }
+            // Valid words that were mistyped into another valid word
+            for position in 0..words.len() {
+                for word in similar_words(&words[position], max_distance) {
+                    if word != words[position] {
+                        let mut candidate = words.to_vec();
+                        candidate[position] = word.to_string();
+                        candidates.push(candidate.join(" "));
+                    }
+                }
+            }
+        }
+        1 => {
+            let position = unknown[0];
+            let mut replacements = similar_words(&words[position], max_distance);
+            if replacements.is_empty() {
+                replacements = wordlist.to_vec();
+            }
+            for word in replacements {
+                let mut candidate = words.to_vec();
+                candidate[position] = word.to_string();
+                candidates.push(candidate.join(" "));
+            }
+        }
+        _ => {
+            let unknown_words: Vec<&str> = unknown.iter().map(|&i| words[i].as_str()).collect();
+            return Err(format!("Only one word can be repaired, found unknown words: {}", unknown_words.join(", ")).into());
+        }
+    }
+    Ok(candidates)
+}
+
+fn similar_words(word: &str, max_distance: usize) -> Vec<&'static str> {
+    Language::English
+        .word_list()
+        .iter()
+        .copied()
+        .filter(|candidate| edit_distance(word, candidate) <= max_distance)
+        .collect()
+}
+
+// Optimal string alignment distance: Levenshtein plus adjacent transpositions
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+fn candidate_matches(mnemonic: &Mnemonic, target: &RepairTarget) -> Result<bool, ThreadError> {
+    let seed = generate_seed_from_mnemonic(mnemonic);
+    let master_key = create_master_key(&seed).map_err(|e| ThreadError(e.to_string()))?;
+    let account_xpub = create_extended_public_key(&master_key).map_err(|e| ThreadError(e.to_string()))?;
+
+    match target {
+        RepairTarget::Xpub(xpub) => Ok(account_xpub.public_key() == xpub.public_key()
+            && account_xpub.attrs().chain_code == xpub.attrs().chain_code),
+        RepairTarget::Address { address, window } => {
+            let change_path = DerivationPath::from_str("m/0")?;
+            let change_xpub = account_xpub.derive_path(&change_path)?;
+            for i in 0..*window {
+                let index_path = DerivationPath::from_str(&format!("m/{}", i))?;
+                let child_xpub = change_xpub.clone().derive_path(&index_path)?;
+                let pubkey_bytes = child_xpub.public_key().serialize();
+                let derived_address = Address::new(Prefix::Mainnet, Version::PubKey, &pubkey_bytes[1..33]);
+                if derived_address.to_string() == *address {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// Searches the candidates in parallel and returns the first phrase whose keys match the target.
+pub fn find_matching_mnemonic_parallel(
+    candidates: Vec<String>,
+    target: RepairTarget,
+    num_threads: usize,
+) -> Result<Option<Mnemonic>, Box<dyn std::error::Error>> {
+    let target = Arc::new(target);
+    let found = Arc::new(Mutex::new(None));
+    let should_exit = Arc::new(Mutex::new(false));
+
+    // Calculate chunk size for each thread
+    let chunk_size = std::cmp::max(1, candidates.len().div_ceil(num_threads));
+    let mut handles = vec![];
+
+    for chunk in candidates.chunks(chunk_size) {
+        let chunk = chunk.to_vec();
+        let target = Arc::clone(&target);
+        let found = Arc::clone(&found);
+        let should_exit = Arc::clone(&should_exit);
+
+        let handle = thread::spawn(move || -> Result<(), ThreadError> {
+            for phrase in chunk {
+                // Check if another thread found the phrase
+                if *should_exit.lock().unwrap() {
+                    break;
+                }
+
+                // Cheap checksum filter before the expensive seed derivation
+                let mnemonic = match Mnemonic::parse_in(Language::English, &phrase) {
+                    Ok(mnemonic) => mnemonic,
+                    Err(_) => continue,
+                };
+
+                if candidate_matches(&mnemonic, &target)? {
+                    *found.lock().unwrap() = Some(mnemonic);
+                    *should_exit.lock().unwrap() = true;
+                    break;
+                }
+            }
+            Ok(())
+        });
+        handles.push(handle);
+    }
+
+    // Wait for all threads to complete
+    for handle in handles {
+        match handle.join() {
+            Ok(result) => result?,
+            Err(_) => return Err("Thread panicked".into()),
+        }
+    }
+
+    let result = found.lock().unwrap().take();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn words(phrase: &str) -> Vec<String> {
+        phrase.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn account_xpub() -> KaspaXPub {
+        let mnemonic = Mnemonic::parse_in(Language::English, PHRASE).unwrap();
+        create_extended_public_key(&create_master_key(&generate_seed_from_mnemonic(&mnemonic)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn missing_word_candidates() {
+        let mut damaged = words(PHRASE);
+        damaged.remove(4);
+        let candidates = repair_candidates(&damaged, 2).unwrap();
+        assert_eq!(candidates.len(), 12 * 2048);
+        assert!(candidates.iter().any(|candidate| candidate == PHRASE));
+    }
+
+    #[test]
+    fn swapped_word_candidates() {
+        let mut damaged = words(PHRASE);
+        damaged.swap(3, 11);
+        let candidates = repair_candidates(&damaged, 2).unwrap();
+        assert!(candidates.iter().any(|candidate| candidate == PHRASE));
+    }
+
+    #[test]
+    fn mistyped_word_candidates() {
+        assert_eq!(edit_distance("abandon", "abnadon"), 1);
+        assert_eq!(edit_distance("abandon", "abandom"), 1);
+        assert_eq!(edit_distance("about", "above"), 2);
+
+        // An unknown word is replaced by the close wordlist entries only
+        let candidates = repair_candidates(&words(&PHRASE.replace("about", "abuot")), 1).unwrap();
+        assert!(candidates.iter().any(|candidate| candidate == PHRASE));
+        assert!(candidates.iter().all(|candidate| edit_distance(candidate.rsplit(' ').next().unwrap(), "abuot") <= 1));
+
+        // A valid word typed as another valid word is also tried
+        let candidates = repair_candidates(&words(&PHRASE.replace("about", "above")), 2).unwrap();
+        assert!(candidates.iter().any(|candidate| candidate == PHRASE));
+    }
+
+    #[test]
+    fn rejects_unrepairable_phrases() {
+        assert!(repair_candidates(&words(&PHRASE.replace("abandon", "xyzzy")), 2).is_err());
+        assert!(repair_candidates(&words(PHRASE)[..10], 2).is_err());
+    }
+
+    #[test]
+    fn checksum_filter_and_search() {
+        // "abandon" x12 fails the checksum, so it is skipped even though it comes first
+        let bad_checksum = ["abandon"; 12].join(" ");
+        let found = find_matching_mnemonic_parallel(vec![bad_checksum.clone()], RepairTarget::Xpub(account_xpub()), 2).unwrap();
+        assert!(found.is_none());
+
+        let mut damaged = words(PHRASE);
+        damaged.swap(0, 11);
+        let mut candidates = vec![bad_checksum];
+        candidates.extend(repair_candidates(&damaged, 2).unwrap());
+        let found = find_matching_mnemonic_parallel(candidates, RepairTarget::Xpub(account_xpub()), 4).unwrap();
+        assert_eq!(found.map(|mnemonic| mnemonic.to_string()).as_deref(), Some(PHRASE));
+
+        let target = RepairTarget::Address {
+            address: "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh".to_string(),
+            window: 1,
+        };
+        let found = find_matching_mnemonic_parallel(repair_candidates(&damaged, 2).unwrap(), target, 4).unwrap();
+        assert_eq!(found.map(|mnemonic| mnemonic.to_string()).as_deref(), Some(PHRASE));
+    }
+}
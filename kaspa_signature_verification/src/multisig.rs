@@ -0,0 +1,438 @@
+// M-of-N multisig wallets and their pay-to-script-hash addresses.
+//
+// A wallet is a threshold and a list of cosigners, each either an account xpub
+// (its key at m/<chain>/<index> is used) or a fixed public key. The redeem script
+// at an index is built from every cosigner's key there, and its BLAKE2b hash is
+// the payload of the `Version::ScriptHash` address.
+//
+// Message bundles collect the cosigners' signatures over one message as JSON,
+// alongside the redeem script, so anyone can check the M-of-N threshold is met.
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_bip32::DerivationPath;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::descriptor::DescriptorKey;
+use crate::script::{script_hash, MultisigScript};
+use crate::{load_xpub_from_string, KaspaXPub, ThreadError};
+
+/// A wallet definition as written in a JSON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletDefinition {
+    /// Number of signatures required
+    pub threshold: usize,
+    /// Account xpubs or hex public keys (33-byte compressed, or 32-byte x-only for Schnorr),
+    /// optionally prefixed with their key origin
+    pub cosigners: Vec<String>,
+    /// Sort the keys of each redeem script by their bytes
+    #[serde(default)]
+    pub sorted: bool,
+    /// OP_CHECKMULTISIG_ECDSA with compressed keys instead of Schnorr x-only keys
+    #[serde(default)]
+    pub ecdsa: bool,
+}
+
+pub enum Cosigner {
+    Xpub(KaspaXPub),
+    PublicKey(Vec<u8>),
+}
+
+pub struct MultisigWallet {
+    pub required: usize,
+    pub cosigners: Vec<Cosigner>,
+    pub sorted: bool,
+    pub ecdsa: bool,
+}
+
+impl MultisigWallet {
+    pub fn from_definition(definition: &WalletDefinition) -> Result<MultisigWallet, Box<dyn std::error::Error>> {
+        let mut cosigners = Vec::new();
+        for cosigner in &definition.cosigners {
+            // Cosigners may carry a key origin, as in descriptors: [fingerprint/path]xpub
+            let cosigner = DescriptorKey::parse(cosigner)?.key;
+            let cosigner = cosigner.as_str();
+            let key_len = if definition.ecdsa { vec![33] } else { vec![32, 33] };
+            match hex::decode(cosigner) {
+                Ok(key) if key_len.contains(&key.len()) => {
+                    if key.len() == 33 {
+                        secp256k1::PublicKey::from_slice(&key)?;
+                    }
+                    cosigners.push(Cosigner::PublicKey(key));
+                }
+                Ok(key) => return Err(format!("Invalid cosigner public key length: {} bytes", key.len()).into()),
+                Err(_) => cosigners.push(Cosigner::Xpub(load_xpub_from_string(cosigner)?)),
+            }
+        }
+        let wallet = MultisigWallet {
+            required: definition.threshold,
+            cosigners,
+            sorted: definition.sorted,
+            ecdsa: definition.ecdsa,
+        };
+        // Catch threshold and key count errors before anything is derived
+        wallet.script_at(0, 0)?;
+        Ok(wallet)
+    }
+
+    pub fn parse(json: &str) -> Result<MultisigWallet, Box<dyn std::error::Error>> {
+        MultisigWallet::from_definition(&serde_json::from_str(json)?)
+    }
+
+    /// Every cosigner's key at m/<chain>/<index>, as it appears in the redeem script.
+    pub fn keys_at(&self, chain: u32, index: u32) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+        let path = DerivationPath::from_str(&format!("m/{}/{}", chain, index))?;
+        self.cosigners
+            .iter()
+            .map(|cosigner| {
+                let key = match cosigner {
+                    Cosigner::Xpub(xpub) => xpub.clone().derive_path(&path)?.public_key().serialize().to_vec(),
+                    Cosigner::PublicKey(key) => key.clone(),
+                };
+                // Schnorr scripts hold the x coordinate only
+                Ok(if !self.ecdsa && key.len() == 33 { key[1..].to_vec() } else { key })
+            })
+            .collect()
+    }
+
+    pub fn script_at(&self, chain: u32, index: u32) -> Result<MultisigScript, Box<dyn std::error::Error>> {
+        Ok(MultisigScript::new(self.required, self.keys_at(chain, index)?, self.ecdsa, self.sorted)?)
+    }
+
+    pub fn address_at(&self, chain: u32, index: u32, prefix: Prefix) -> Result<Address, Box<dyn std::error::Error>> {
+        let redeem_script = self.script_at(chain, index)?.to_script();
+        Ok(Address::new(prefix, Version::ScriptHash, &script_hash(&redeem_script)))
+    }
+}
+
+/// Searches the receive and change chains of `wallet` for the P2SH address, within the
+/// first `window` indices of each. Returns the (chain, index) it was found at.
+pub fn find_address_parallel(
+    wallet: Arc<MultisigWallet>,
+    address: &str,
+    window: u32,
+    num_threads: usize,
+) -> Result<Option<(u32, u32)>, Box<dyn std::error::Error>> {
+    let address = Address::try_from(address)?;
+    if address.version != Version::ScriptHash {
+        return Err(format!("{} is not a pay-to-script-hash address", address).into());
+    }
+    let target = Arc::new(address.payload.to_vec());
+    let found = Arc::new(Mutex::new(None));
+    let should_exit = Arc::new(Mutex::new(false));
+
+    // Calculate chunk size for each thread
+    let chunk_size = std::cmp::max(1, (window as usize).div_ceil(num_threads)) as u32;
+    let mut handles = vec![];
+
+    for start in (0..window).step_by(chunk_size as usize) {
+        let end = std::cmp::min(start + chunk_size, window);
+        let wallet = Arc::clone(&wallet);
+        let target = Arc::clone(&target);
+        let found = Arc::clone(&found);
+        let should_exit = Arc::clone(&should_exit);
+
+        let handle = thread::spawn(move || -> Result<(), ThreadError> {
+            for index in start..end {
+                for chain in 0..2 {
+                    // Check if another thread found the address
+                    if *should_exit.lock().unwrap() {
+                        return Ok(());
+                    }
+
+                    let script = wallet
+                        .script_at(chain, index)
+                        .map_err(|e| ThreadError(e.to_string()))?;
+                    if script_hash(&script.to_script())[..] == target[..] {
+                        *found.lock().unwrap() = Some((chain, index));
+                        *should_exit.lock().unwrap() = true;
+                        return Ok(());
+                    }
+                }
+            }
+            Ok(())
+        });
+        handles.push(handle);
+    }
+
+    // Wait for all threads to complete
+    for handle in handles {
+        match handle.join() {
+            Ok(result) => result?,
+            Err(_) => return Err("Thread panicked".into()),
+        }
+    }
+
+    let result = *found.lock().unwrap();
+    Ok(result)
+}
+
+/// One cosigner's signature in a multisig message bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CosignerSignature {
+    /// 33-byte compressed public key (hex)
+    pub public_key: String,
+    /// Compact ECDSA signature over double_sha256(message) (hex)
+    pub signature: String,
+}
+
+/// A message signed by the cosigners of a P2SH multisig address. Each cosigner signs
+/// the message exactly as a single-key signature, so signatures can be collected one at a time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigMessage {
+    pub message: String,
+    pub address: String,
+    pub threshold: usize,
+    pub redeem_script: String,
+    pub signatures: Vec<CosignerSignature>,
+}
+
+impl MultisigMessage {
+    pub fn new(message: &str, redeem_script: &[u8], prefix: Prefix) -> Result<MultisigMessage, Box<dyn std::error::Error>> {
+        let multisig = MultisigScript::parse(redeem_script).ok_or("Not a multisig redeem script")?;
+        Ok(MultisigMessage {
+            message: message.to_string(),
+            address: Address::new(prefix, Version::ScriptHash, &script_hash(redeem_script)).to_string(),
+            threshold: multisig.required,
+            redeem_script: hex::encode(redeem_script),
+            signatures: Vec::new(),
+        })
+    }
+
+    pub fn parse(json: &str) -> Result<MultisigMessage, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// The redeem script, after checking it is the one the address commits to.
+    pub fn multisig_script(&self) -> Result<MultisigScript, Box<dyn std::error::Error>> {
+        let redeem_script = hex::decode(&self.redeem_script)?;
+        let address = Address::try_from(self.address.as_str())?;
+        if address.version != Version::ScriptHash || address.payload[..] != script_hash(&redeem_script)[..] {
+            return Err(format!("The redeem script does not hash to {}", self.address).into());
+        }
+        let multisig = MultisigScript::parse(&redeem_script).ok_or("Not a multisig redeem script")?;
+        if multisig.required != self.threshold {
+            return Err(format!(
+                "Bundle threshold {} does not match the redeem script's {}",
+                self.threshold, multisig.required
+            )
+            .into());
+        }
+        Ok(multisig)
+    }
+
+    /// Adds or replaces the signature of one cosigner.
+    pub fn add_signature(&mut self, public_key: &[u8], signature: String) {
+        let public_key = hex::encode(public_key);
+        self.signatures.retain(|existing| !existing.public_key.eq_ignore_ascii_case(&public_key));
+        self.signatures.push(CosignerSignature { public_key, signature });
+    }
+
+    /// Checks every signature, returning each public key with whether its signature is valid
+    /// for a key of the redeem script. Each key of the redeem script counts once, however its
+    /// public key is written: upper or lower case hex, or either parity for Schnorr x-only keys.
+    pub fn verify(&self) -> Result<Vec<(String, bool)>, Box<dyn std::error::Error>> {
+        let multisig = self.multisig_script()?;
+        let secp = secp256k1::Secp256k1::verification_only();
+        let message = secp256k1::Message::from_slice(&crate::double_sha256(self.message.as_bytes()))?;
+
+        let mut results: Vec<(String, bool)> = Vec::new();
+        let mut counted: Vec<usize> = Vec::new();
+        for cosigner in &self.signatures {
+            let verified = (|| -> Option<usize> {
+                let public_key = secp256k1::PublicKey::from_slice(&hex::decode(&cosigner.public_key).ok()?).ok()?;
+                let script_key_index = script_key_index(&multisig, &public_key)?;
+                let signature = secp256k1::ecdsa::Signature::from_compact(&hex::decode(&cosigner.signature).ok()?).ok()?;
+                secp.verify_ecdsa(&message, &signature, &public_key).ok()?;
+                Some(script_key_index)
+            })();
+            match verified {
+                Some(index) if counted.contains(&index) => continue,
+                Some(index) => {
+                    counted.push(index);
+                    results.push((cosigner.public_key.clone(), true));
+                }
+                None => results.push((cosigner.public_key.clone(), false)),
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// Position of a public key among the redeem script's keys, compared in the form the script
+/// holds them: compressed for ECDSA, x-only for Schnorr.
+fn script_key_index(multisig: &MultisigScript, public_key: &secp256k1::PublicKey) -> Option<usize> {
+    let compressed = public_key.serialize();
+    multisig.pubkeys.iter().position(|key| {
+        if multisig.ecdsa { key[..] == compressed[..] } else { key[..] == compressed[1..] }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Account xpubs (m/44'/111111'/0') of the "abandon ... about", "legal winner ... yellow"
+    // and "zoo ... vote" mnemonics, as in tests/vectors/kaspa_addresses.json
+    const XPUBS: [&str; 3] = [
+        "xpub6CWzHoo4nAhEpu2Pwyo5j6Em2Rz9y77gFPGPhZAHWyTGTPBRDCU95ABmQceLqsKyboi3BKwhnjyVgGx3FHAqPNCfSVsse8QKz1bSc69Gsxf",
+        "xpub6CX2RE3Cc7bnFH4L2cVKgJmw7i6UunoAcUNsmA33YffJeMj6R7DFRqdDBDfDKy2Lw95sc3vRo5vd272YB8idoF9FpcU77s2KRaHpLKzDJrK",
+        "xpub6DKme8oaUEu1MnakCxVbvkntdub16wft92nAJoCCux4hLutYvcbWnZRuA6VE67fVe8G7cKceLZZKLsKkzYiAHm6NDCDdF6rQLBYf6BG9y88",
+    ];
+
+    fn wallet(threshold: usize, cosigners: &[&str], sorted: bool, ecdsa: bool) -> MultisigWallet {
+        MultisigWallet::from_definition(&WalletDefinition {
+            threshold,
+            cosigners: cosigners.iter().map(|cosigner| cosigner.to_string()).collect(),
+            sorted,
+            ecdsa,
+        })
+        .unwrap()
+    }
+
+    // Computed with an independent implementation of BIP32, the redeem script and the address encoding
+    #[test]
+    fn p2sh_address_vectors() {
+        let unsorted = wallet(2, &XPUBS, false, false);
+        assert_eq!(
+            hex::encode(unsorted.script_at(0, 0).unwrap().to_script()),
+            "52201bacea84ca721c95d67ecace19bc499a77c03726bc8739af637bcd89abaaf058208810d9921ff98ae1a92576d38610ee1d52245ea721980042de204d9f9920f6a520075486d05cea119d15af20cee6763d685ac68df5954c4c8bec190270d83e5ec353ae"
+        );
+        let vectors = [
+            (&unsorted, 0, 0, Prefix::Mainnet, "kaspa:pzjc2vah3l7tndy3rpcgku88lkydukq77443l3sfgf559dg94n06jnyyselc8"),
+            (&unsorted, 1, 5, Prefix::Mainnet, "kaspa:pp7efv7x3clj96njw960vsk6637z886329m0sdr4g94h9k0qm3xyqdpwwq73f"),
+            (&wallet(2, &XPUBS, true, false), 0, 0, Prefix::Mainnet, "kaspa:pz0lz7zzkxkqprn3dxg28a7d9udkuugm3pul05afp55p0a0aa4escqy9anz0d"),
+            (&wallet(2, &XPUBS, true, true), 0, 3, Prefix::Testnet, "kaspatest:pp6xrl25qwutntxy7jtpwah9x7kql80wqqh5zk4qssh0vxpvcczh7zfq27syh"),
+        ];
+        for (wallet, chain, index, prefix, expected) in vectors {
+            assert_eq!(wallet.address_at(chain, index, prefix).unwrap().to_string(), expected);
+        }
+
+        // Fixed keys, compressed or x-only, with a key origin, give the same script as the xpubs' 0/0 keys
+        let fixed = wallet(
+            1,
+            &[
+                "[73c5da0a/44'/111111'/0'/0/0]031bacea84ca721c95d67ecace19bc499a77c03726bc8739af637bcd89abaaf058",
+                "8810d9921ff98ae1a92576d38610ee1d52245ea721980042de204d9f9920f6a5",
+            ],
+            false,
+            false,
+        );
+        let expected = "kaspa:pqkg5evnj25heddfhyevshm9g0xtwdfgdg28da3mym35cat90hk8jvz8557cy";
+        assert_eq!(fixed.address_at(0, 0, Prefix::Mainnet).unwrap().to_string(), expected);
+        assert_eq!(wallet(1, &XPUBS[..2], false, false).address_at(0, 0, Prefix::Mainnet).unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn finds_wallet_addresses() {
+        let wallet = Arc::new(wallet(2, &XPUBS, false, false));
+        let address = "kaspa:pp7efv7x3clj96njw960vsk6637z886329m0sdr4g94h9k0qm3xyqdpwwq73f";
+        assert_eq!(find_address_parallel(Arc::clone(&wallet), address, 20, 4).unwrap(), Some((1, 5)));
+        assert_eq!(find_address_parallel(Arc::clone(&wallet), address, 5, 4).unwrap(), None);
+        let not_p2sh = "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh";
+        assert!(find_address_parallel(wallet, not_p2sh, 20, 4).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_wallets() {
+        let definition = |threshold, cosigners: &[&str], ecdsa| WalletDefinition {
+            threshold,
+            cosigners: cosigners.iter().map(|cosigner| cosigner.to_string()).collect(),
+            sorted: false,
+            ecdsa,
+        };
+        assert!(MultisigWallet::from_definition(&definition(3, &XPUBS[..2], false)).is_err());
+        assert!(MultisigWallet::from_definition(&definition(0, &XPUBS, false)).is_err());
+        // ECDSA scripts need compressed keys
+        let x_only = "8810d9921ff98ae1a92576d38610ee1d52245ea721980042de204d9f9920f6a5";
+        assert!(MultisigWallet::from_definition(&definition(1, &[x_only], true)).is_err());
+        assert!(MultisigWallet::from_definition(&definition(1, &["02ffff"], false)).is_err());
+    }
+
+    fn cosigner_key(byte: u8) -> (secp256k1::SecretKey, secp256k1::PublicKey) {
+        let private_key = secp256k1::SecretKey::from_slice(&[byte; 32]).unwrap();
+        (private_key, private_key.public_key(&secp256k1::Secp256k1::new()))
+    }
+
+    fn message_bundle(ecdsa: bool) -> MultisigMessage {
+        let pubkeys = [1, 2]
+            .iter()
+            .map(|&byte| {
+                let compressed = cosigner_key(byte).1.serialize();
+                if ecdsa { compressed.to_vec() } else { compressed[1..].to_vec() }
+            })
+            .collect();
+        let redeem_script = MultisigScript::new(2, pubkeys, ecdsa, false).unwrap().to_script();
+        MultisigMessage::new("Release funds for invoice 7", &redeem_script, Prefix::Mainnet).unwrap()
+    }
+
+    fn valid_count(bundle: &MultisigMessage) -> usize {
+        bundle.verify().unwrap().iter().filter(|(_, valid)| *valid).count()
+    }
+
+    #[test]
+    fn message_threshold() {
+        for ecdsa in [false, true] {
+            let mut bundle = message_bundle(ecdsa);
+            for byte in [1, 2] {
+                let (private_key, public_key) = cosigner_key(byte);
+                let signature = crate::sign_message(&bundle.message, &private_key).unwrap();
+                bundle.add_signature(&public_key.serialize(), signature);
+            }
+            assert_eq!(valid_count(&bundle), 2);
+
+            // A signature by a key outside the redeem script doesn't count
+            let (outsider, outsider_key) = cosigner_key(3);
+            bundle.signatures[1] = CosignerSignature {
+                public_key: hex::encode(outsider_key.serialize()),
+                signature: crate::sign_message(&bundle.message, &outsider).unwrap(),
+            };
+            assert_eq!(bundle.verify().unwrap()[1], (hex::encode(outsider_key.serialize()), false));
+
+            let mut tampered = message_bundle(ecdsa);
+            tampered.redeem_script = message_bundle(!ecdsa).redeem_script;
+            assert!(tampered.verify().is_err());
+        }
+    }
+
+    // One cosigner must not meet a 2-of-2 by listing their key twice in another form
+    #[test]
+    fn duplicate_cosigners_count_once() {
+        let (private_key, public_key) = cosigner_key(1);
+        let compressed = public_key.serialize();
+        for ecdsa in [false, true] {
+            let mut bundle = message_bundle(ecdsa);
+            bundle.add_signature(&compressed, crate::sign_message(&bundle.message, &private_key).unwrap());
+
+            // Same key in upper case hex
+            bundle.signatures.push(CosignerSignature {
+                public_key: hex::encode_upper(compressed),
+                signature: bundle.signatures[0].signature.clone(),
+            });
+            // The key of the other parity, which has the same x coordinate, signed with the negated private key
+            let mut other_parity = compressed;
+            other_parity[0] ^= 1;
+            let negated = private_key.negate();
+            assert_eq!(negated.public_key(&secp256k1::Secp256k1::new()).serialize(), other_parity);
+            bundle.signatures.push(CosignerSignature {
+                public_key: hex::encode(other_parity),
+                signature: crate::sign_message(&bundle.message, &negated).unwrap(),
+            });
+            // The same key again, exactly as written before
+            bundle.signatures.push(bundle.signatures[0].clone());
+
+            assert_eq!(valid_count(&bundle), 1, "ecdsa: {}", ecdsa);
+            assert_eq!(bundle.verify().unwrap()[0], (hex::encode(compressed), true));
+
+            // add_signature replaces the key whatever case it was written in
+            let upper = hex::encode_upper(compressed);
+            bundle.signatures = vec![CosignerSignature { public_key: upper, signature: "00".to_string() }];
+            bundle.add_signature(&compressed, crate::sign_message(&bundle.message, &private_key).unwrap());
+            assert_eq!(bundle.signatures.len(), 1);
+            assert_eq!(valid_count(&bundle), 1);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+// Signing of exact byte payloads (files, stdin, hex or base64) and the
+// detached signature file written next to them.
+use base64::Engine;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::PathBuf;
+
+use crate::double_sha256_reader;
+
+pub const SIGNATURE_ALGORITHM: &str = "ecdsa-secp256k1-double-sha256";
+const SIGNATURE_FILE_HEADER: &str = "Kaspa-Signature-Version: 1";
+
+/// Where the bytes to sign or verify come from.
+pub enum PayloadSource {
+    File(PathBuf),
+    Stdin,
+    Bytes(Vec<u8>),
+}
+
+impl PayloadSource {
+    /// Reads the source from `--file <path>`, `--stdin`, `--hex <bytes>` or `--base64 <bytes>`.
+    pub fn from_args(args: &[String]) -> Result<PayloadSource, Box<dyn std::error::Error>> {
+        let mut sources = Vec::new();
+        if let Some(path) = crate::option_value(args, "--file") {
+            sources.push(PayloadSource::File(PathBuf::from(path)));
+        }
+        if crate::has_flag(args, "--stdin") {
+            sources.push(PayloadSource::Stdin);
+        }
+        if let Some(data) = crate::option_value(args, "--hex") {
+            let data = data.strip_prefix("0x").unwrap_or(data);
+            sources.push(PayloadSource::Bytes(hex::decode(data)?));
+        }
+        if let Some(data) = crate::option_value(args, "--base64") {
+            sources.push(PayloadSource::Bytes(base64::engine::general_purpose::STANDARD.decode(data)?));
+        }
+
+        match sources.len() {
+            1 => Ok(sources.remove(0)),
+            0 => Err("No payload given: use --file <path>, --stdin, --hex <bytes> or --base64 <bytes>".into()),
+            _ => Err("Only one of --file, --stdin, --hex and --base64 can be used".into()),
+        }
+    }
+
+    /// Hashes the payload as a stream, returning the digest and the number of bytes read.
+    pub fn digest(&self) -> Result<([u8; 32], u64), Box<dyn std::error::Error>> {
+        self.digest_with_head(0, &mut Vec::new())
+    }
+
+    /// Like `digest`, also copying the first `limit` bytes into `head` for the signing review.
+    pub fn digest_with_head(&self, limit: usize, head: &mut Vec<u8>) -> Result<([u8; 32], u64), Box<dyn std::error::Error>> {
+        let inner: Box<dyn Read> = match self {
+            PayloadSource::File(path) => {
+                let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+                Box::new(BufReader::new(file))
+            }
+            PayloadSource::Stdin => Box::new(io::stdin().lock()),
+            PayloadSource::Bytes(bytes) => Box::new(bytes.as_slice()),
+        };
+        let reader = HeadReader { inner, head, limit };
+        Ok(double_sha256_reader(reader)?)
+    }
+
+    /// Reads the whole payload into memory, for formats that embed it.
+    pub fn read_to_end(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let bytes = match self {
+            PayloadSource::File(path) => {
+                std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            }
+            PayloadSource::Stdin => {
+                let mut buffer = Vec::new();
+                io::stdin().lock().read_to_end(&mut buffer)?;
+                buffer
+            }
+            PayloadSource::Bytes(bytes) => bytes.clone(),
+        };
+        Ok(bytes)
+    }
+
+    /// Default location of the detached signature for this payload, if it has one.
+    pub fn default_signature_path(&self) -> Option<PathBuf> {
+        match self {
+            PayloadSource::File(path) => {
+                let mut name = path.as_os_str().to_owned();
+                name.push(".kaspasig");
+                Some(PathBuf::from(name))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Passes reads through, keeping a copy of the first `limit` bytes
+struct HeadReader<'a, R> {
+    inner: R,
+    head: &'a mut Vec<u8>,
+    limit: usize,
+}
+
+impl<R: Read> Read for HeadReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let keep = read.min(self.limit.saturating_sub(self.head.len()));
+        self.head.extend_from_slice(&buf[..keep]);
+        Ok(read)
+    }
+}
+
+/// Contents of a detached signature file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetachedSignature {
+    pub algorithm: String,
+    pub address: String,
+    pub digest: [u8; 32],
+    pub size: u64,
+    pub signature: String,
+}
+
+impl DetachedSignature {
+    pub fn new(address: &str, digest: [u8; 32], size: u64, signature: String) -> DetachedSignature {
+        DetachedSignature {
+            algorithm: SIGNATURE_ALGORITHM.to_string(),
+            address: address.to_string(),
+            digest,
+            size,
+            signature,
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<DetachedSignature, Box<dyn std::error::Error>> {
+        let mut lines = contents.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next() != Some(SIGNATURE_FILE_HEADER) {
+            return Err("Not a Kaspa signature file: missing version header".into());
+        }
+
+        let (mut algorithm, mut address, mut digest, mut size, mut signature) = (None, None, None, None, None);
+        for line in lines {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid signature file line: {}", line))?;
+            let value = value.trim().to_string();
+            match key.trim() {
+                "Algorithm" => algorithm = Some(value),
+                "Address" => address = Some(value),
+                "Digest" => digest = Some(value),
+                "Size" => size = Some(value.parse::<u64>()?),
+                "Signature" => signature = Some(value),
+                other => return Err(format!("Unknown signature file field: {}", other).into()),
+            }
+        }
+
+        let algorithm = algorithm.ok_or("Signature file is missing the Algorithm field")?;
+        if algorithm != SIGNATURE_ALGORITHM {
+            return Err(format!("Unsupported signature algorithm: {}", algorithm).into());
+        }
+        let digest_bytes = hex::decode(digest.ok_or("Signature file is missing the Digest field")?)?;
+        let digest: [u8; 32] = digest_bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| "Signature file digest must be 32 bytes")?;
+
+        Ok(DetachedSignature {
+            algorithm,
+            address: address.ok_or("Signature file is missing the Address field")?,
+            digest,
+            size: size.ok_or("Signature file is missing the Size field")?,
+            signature: signature.ok_or("Signature file is missing the Signature field")?,
+        })
+    }
+}
+
+impl std::fmt::Display for DetachedSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", SIGNATURE_FILE_HEADER)?;
+        writeln!(f, "Algorithm: {}", self.algorithm)?;
+        writeln!(f, "Address: {}", self.address)?;
+        writeln!(f, "Digest: {}", hex::encode(self.digest))?;
+        writeln!(f, "Size: {}", self.size)?;
+        writeln!(f, "Signature: {}", self.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{double_sha256, sign_digest, verify_digest_signature};
+    use secp256k1::SecretKey;
+
+    const ADDRESS: &str = "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh";
+    const PRIVATE_KEY: &str = "24cd8d2875e8e17e4f88fbc554834a89b7b3e0c6f9d84fae36883604359ba440";
+
+    #[test]
+    fn streaming_digest_matches_in_memory_digest() {
+        // Larger than the 64 KiB read buffer, and not a multiple of it
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let path = std::env::temp_dir().join(format!("kaspa_payload_test_{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let from_file = PayloadSource::File(path.clone()).digest();
+        let _ = std::fs::remove_file(&path);
+
+        let expected = (double_sha256(&data), data.len() as u64);
+        assert_eq!(from_file.unwrap(), expected);
+        assert_eq!(PayloadSource::Bytes(data.clone()).digest().unwrap(), expected);
+        assert_eq!(PayloadSource::Bytes(vec![]).digest().unwrap(), (double_sha256(b""), 0));
+
+        // The head kept for the review doesn't change the digest
+        let mut head = Vec::new();
+        assert_eq!(PayloadSource::Bytes(data.clone()).digest_with_head(600, &mut head).unwrap(), expected);
+        assert_eq!(head, data[..600]);
+        let mut head = Vec::new();
+        PayloadSource::Bytes(b"short".to_vec()).digest_with_head(600, &mut head).unwrap();
+        assert_eq!(head, b"short");
+    }
+
+    #[test]
+    fn detached_signature_round_trip() {
+        let private_key = SecretKey::from_slice(&hex::decode(PRIVATE_KEY).unwrap()).unwrap();
+        let (digest, size) = PayloadSource::Bytes(b"release-1.0.tar.gz contents".to_vec()).digest().unwrap();
+        let detached = DetachedSignature::new(ADDRESS, digest, size, sign_digest(&digest, &private_key).unwrap());
+
+        let parsed = DetachedSignature::parse(&detached.to_string()).unwrap();
+        assert_eq!(parsed, detached);
+        assert!(verify_digest_signature(&parsed.digest, &parsed.signature, &parsed.address).unwrap());
+
+        let (other_digest, _) = PayloadSource::Bytes(b"release-1.1.tar.gz contents".to_vec()).digest().unwrap();
+        assert!(!verify_digest_signature(&other_digest, &parsed.signature, &parsed.address).unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_signature_files() {
+        let detached = DetachedSignature::new(ADDRESS, [7u8; 32], 3, "00".repeat(64)).to_string();
+        assert!(DetachedSignature::parse(&detached.replace("Kaspa-Signature-Version: 1", "")).is_err());
+        assert!(DetachedSignature::parse(&detached.replace(SIGNATURE_ALGORITHM, "schnorr")).is_err());
+        assert!(DetachedSignature::parse(&detached.replace(&hex::encode([7u8; 32]), "0707")).is_err());
+        let without_size: String = detached.lines().filter(|line| !line.starts_with("Size")).collect::<Vec<_>>().join("\n");
+        assert!(DetachedSignature::parse(&without_size).is_err());
+        assert!(DetachedSignature::parse(&format!("{}Comment: hello\n", detached)).is_err());
+    }
+
+    #[test]
+    fn default_signature_path() {
+        let source = PayloadSource::File(PathBuf::from("dir/release.tar.gz"));
+        assert_eq!(source.default_signature_path(), Some(PathBuf::from("dir/release.tar.gz.kaspasig")));
+        assert_eq!(PayloadSource::Stdin.default_signature_path(), None);
+    }
+}
@@ -0,0 +1,503 @@
+// Partially Signed Kaspa Transactions.
+//
+// The JSON layout follows rusty-kaspa's PSKT (camelCase fields, one object per
+// PSKT with global, inputs and outputs), and bundles are written the same way:
+// "PSKB" followed by the hex encoded JSON array. Fields this tool doesn't use
+// are kept as-is so a bundle round-trips through other signers unchanged.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::script::{
+    push_data, signature_push, MultisigScript, OP_CHECKSIG, OP_CHECKSIG_ECDSA, OP_DATA_32, OP_DATA_33,
+};
+use crate::sighash::{calc_schnorr_signature_hash, SigHashType, SIG_HASH_ALL};
+use crate::transaction::{
+    ScriptPublicKey, Transaction, TransactionInput, TransactionOutpoint, TransactionOutput, UtxoEntry,
+};
+
+const BUNDLE_PREFIX: &str = "PSKB";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Global {
+    #[serde(default)]
+    pub tx_version: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_lock_time: Option<u64>,
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartialSignature {
+    Schnorr(String),
+    Ecdsa(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PsktInput {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utxo_entry: Option<UtxoEntry>,
+    pub previous_outpoint: TransactionOutpoint,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    /// Compressed public key (hex) -> signature
+    #[serde(default)]
+    pub partial_sigs: BTreeMap<String, PartialSignature>,
+    #[serde(default = "default_sighash_type")]
+    pub sighash_type: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_hex_bytes")]
+    pub redeem_script: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sig_op_count: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_hex_bytes")]
+    pub final_script_sig: Option<Vec<u8>>,
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PsktOutput {
+    pub amount: u64,
+    pub script_public_key: ScriptPublicKey,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_hex_bytes")]
+    pub redeem_script: Option<Vec<u8>>,
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pskt {
+    pub global: Global,
+    pub inputs: Vec<PsktInput>,
+    pub outputs: Vec<PsktOutput>,
+}
+
+/// A set of PSKTs together with how it was framed, so it can be written back the same way.
+pub struct Bundle {
+    pub pskts: Vec<Pskt>,
+    pub prefixed: bool,
+}
+
+impl PsktInput {
+    /// The creator's sigOpCount, or the one the redeem or pay-to-public-key script implies.
+    /// The sighash commits to it, so guessing wrong would make every signature invalid.
+    pub fn sig_op_count(&self, index: usize) -> Result<u8, Box<dyn std::error::Error>> {
+        if let Some(count) = self.sig_op_count {
+            return Ok(count);
+        }
+        match &self.redeem_script {
+            Some(redeem_script) => match MultisigScript::parse(redeem_script) {
+                Some(multisig) => Ok(multisig.pubkeys.len() as u8),
+                None => Err(format!("Input {} has a non-multisig redeem script and no sigOpCount", index).into()),
+            },
+            None => {
+                let spk = &self
+                    .utxo_entry
+                    .as_ref()
+                    .ok_or_else(|| format!("Input {} has no UTXO entry", index))?
+                    .script_public_key
+                    .script;
+                let is_p2pk = (spk.len() == 34 && spk[0] == OP_DATA_32 && spk[33] == OP_CHECKSIG)
+                    || (spk.len() == 35 && spk[0] == OP_DATA_33 && spk[34] == OP_CHECKSIG_ECDSA);
+                if !is_p2pk {
+                    return Err(format!("Input {} spends a non-standard script and has no sigOpCount", index).into());
+                }
+                Ok(1)
+            }
+        }
+    }
+}
+
+fn default_sighash_type() -> u8 {
+    SIG_HASH_ALL.to_u8()
+}
+
+impl Bundle {
+    /// Accepts a "PSKB<hex>" bundle, a JSON array of PSKTs or a single PSKT object.
+    pub fn parse(input: &str) -> Result<Bundle, Box<dyn std::error::Error>> {
+        let input = input.trim();
+        let (json, prefixed) = match input.strip_prefix(BUNDLE_PREFIX) {
+            Some(hex_json) => (String::from_utf8(hex::decode(hex_json)?)?, true),
+            None => (input.to_string(), false),
+        };
+        let pskts = match serde_json::from_str::<Value>(&json)? {
+            Value::Array(_) => serde_json::from_str::<Vec<Pskt>>(&json)?,
+            _ => vec![serde_json::from_str::<Pskt>(&json)?],
+        };
+        Ok(Bundle { pskts, prefixed })
+    }
+
+    pub fn serialize(&self) -> Result<String, Box<dyn std::error::Error>> {
+        if self.prefixed {
+            Ok(format!("{}{}", BUNDLE_PREFIX, hex::encode(serde_json::to_string(&self.pskts)?)))
+        } else if self.pskts.len() == 1 {
+            Ok(serde_json::to_string_pretty(&self.pskts[0])?)
+        } else {
+            Ok(serde_json::to_string_pretty(&self.pskts)?)
+        }
+    }
+}
+
+impl Pskt {
+    /// The transaction being signed, with empty signature scripts unless finalized.
+    pub fn unsigned_transaction(&self) -> Result<Transaction, Box<dyn std::error::Error>> {
+        let inputs = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                Ok(TransactionInput {
+                    previous_outpoint: input.previous_outpoint.clone(),
+                    signature_script: input.final_script_sig.clone().unwrap_or_default(),
+                    sequence: input.sequence.unwrap_or(u64::MAX),
+                    sig_op_count: input.sig_op_count(i)?,
+                    utxo_entry: input
+                        .utxo_entry
+                        .clone()
+                        .ok_or_else(|| format!("Input {} has no UTXO entry", i))?,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| TransactionOutput {
+                value: output.amount,
+                script_public_key: output.script_public_key.clone(),
+            })
+            .collect();
+
+        Ok(Transaction {
+            version: self.global.tx_version,
+            inputs,
+            outputs,
+            lock_time: self.global.fallback_lock_time.unwrap_or(0),
+            subnetwork_id: vec![0u8; 20],
+            gas: 0,
+            payload: vec![],
+            mass: 0,
+        })
+    }
+
+    /// Sighash of one input, for the sighash type the PSKT creator requested.
+    pub fn signature_hash(&self, input_index: usize) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+        let hash_type = SigHashType::from_u8(self.inputs[input_index].sighash_type)
+            .map_err(|e| format!("Input {}: {}", input_index, e))?;
+        Ok(calc_schnorr_signature_hash(&self.unsigned_transaction()?, input_index, hash_type))
+    }
+
+    /// Sum of the spent UTXOs minus the sum of the outputs, when every UTXO entry is known.
+    pub fn fee(&self) -> Option<u64> {
+        let inputs: Option<u64> = self
+            .inputs
+            .iter()
+            .map(|input| input.utxo_entry.as_ref().map(|entry| entry.amount))
+            .sum();
+        let outputs: u64 = self.outputs.iter().map(|output| output.amount).sum();
+        inputs.and_then(|inputs| inputs.checked_sub(outputs))
+    }
+
+    fn same_transaction(&self, other: &Pskt) -> bool {
+        self.global.tx_version == other.global.tx_version
+            && self.inputs.len() == other.inputs.len()
+            && self.outputs.len() == other.outputs.len()
+            && self
+                .inputs
+                .iter()
+                .zip(&other.inputs)
+                .all(|(a, b)| a.previous_outpoint == b.previous_outpoint)
+            && self
+                .outputs
+                .iter()
+                .zip(&other.outputs)
+                .all(|(a, b)| a.amount == b.amount && a.script_public_key == b.script_public_key)
+    }
+
+    /// Merges the signatures and data of another signer's copy of the same PSKT.
+    pub fn combine(&mut self, other: &Pskt) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.same_transaction(other) {
+            return Err("Cannot combine PSKTs for different transactions".into());
+        }
+        for (input, other_input) in self.inputs.iter_mut().zip(&other.inputs) {
+            for (pubkey, signature) in &other_input.partial_sigs {
+                match input.partial_sigs.get(pubkey) {
+                    Some(existing) if existing != signature => {
+                        return Err(format!("Conflicting signatures for public key {}", pubkey).into());
+                    }
+                    _ => {
+                        input.partial_sigs.insert(pubkey.clone(), signature.clone());
+                    }
+                }
+            }
+            if input.utxo_entry.is_none() {
+                input.utxo_entry = other_input.utxo_entry.clone();
+            }
+            if input.redeem_script.is_none() {
+                input.redeem_script = other_input.redeem_script.clone();
+            }
+            if input.final_script_sig.is_none() {
+                input.final_script_sig = other_input.final_script_sig.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the final signature script of every input from its partial signatures.
+    pub fn finalize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        for (i, input) in self.inputs.iter_mut().enumerate() {
+            if input.final_script_sig.is_some() {
+                continue;
+            }
+            let signatures: BTreeMap<Vec<u8>, Vec<u8>> = input
+                .partial_sigs
+                .iter()
+                .map(|(pubkey, signature)| match signature {
+                    PartialSignature::Schnorr(sig) => Ok((hex::decode(pubkey)?, hex::decode(sig)?)),
+                    PartialSignature::Ecdsa(sig) => Ok((hex::decode(pubkey)?, hex::decode(sig)?)),
+                })
+                .collect::<Result<_, Box<dyn std::error::Error>>>()?;
+
+            let script = match &input.redeem_script {
+                None => {
+                    // Pay-to-public-key: the single signature of the key in the script, Schnorr or ECDSA
+                    let spk = &input
+                        .utxo_entry
+                        .as_ref()
+                        .ok_or_else(|| format!("Input {} has no UTXO entry", i))?
+                        .script_public_key
+                        .script;
+                    let signature = signatures
+                        .iter()
+                        .find(|(pubkey, _)| {
+                            pubkey.len() == 33
+                                && ((spk.len() == 34 && pubkey[1..] == spk[1..33])
+                                    || (spk.len() == 35 && pubkey[..] == spk[1..34]))
+                        })
+                        .map(|(_, signature)| signature)
+                        .ok_or_else(|| format!("Input {} is not signed by the key it pays to", i))?;
+                    signature_push(signature, input.sighash_type)
+                }
+                Some(redeem_script) => {
+                    let multisig = MultisigScript::parse(redeem_script)
+                        .ok_or_else(|| format!("Input {} has an unsupported redeem script", i))?;
+                    // Signatures go in the order of the keys in the redeem script
+                    let mut script = Vec::new();
+                    let mut count = 0;
+                    for key in &multisig.pubkeys {
+                        if count == multisig.required {
+                            break;
+                        }
+                        let signature = signatures.iter().find(|(pubkey, _)| {
+                            if multisig.ecdsa { *pubkey == key } else { pubkey.len() == 33 && pubkey[1..] == key[..] }
+                        });
+                        if let Some((_, signature)) = signature {
+                            let mut with_type = signature.clone();
+                            with_type.push(input.sighash_type);
+                            push_data(&mut script, &with_type);
+                            count += 1;
+                        }
+                    }
+                    if count < multisig.required {
+                        return Err(format!(
+                            "Input {} has {} of the {} required signatures",
+                            i, count, multisig.required
+                        )
+                        .into());
+                    }
+                    push_data(&mut script, redeem_script);
+                    script
+                }
+            };
+            input.final_script_sig = Some(script);
+            input.partial_sigs.clear();
+        }
+        Ok(())
+    }
+}
+
+// Serde helper for optional byte fields written as hex strings
+mod optional_hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_some(&hex::encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| hex::decode(&s).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{p2pk_script, script_hash, OP_BLAKE2B, OP_DATA_65, OP_EQUAL};
+    use crate::transaction::schnorr_sign;
+    use secp256k1::{schnorr::Signature, Message, PublicKey, Secp256k1, SecretKey, XOnlyPublicKey};
+
+    fn key(byte: u8) -> (SecretKey, PublicKey) {
+        let private_key = SecretKey::from_slice(&[byte; 32]).unwrap();
+        (private_key, private_key.public_key(&Secp256k1::new()))
+    }
+
+    fn input(index: u32, script: Vec<u8>, redeem_script: Option<Vec<u8>>) -> PsktInput {
+        PsktInput {
+            utxo_entry: Some(UtxoEntry {
+                amount: 50_000,
+                script_public_key: ScriptPublicKey { version: 0, script },
+                block_daa_score: 0,
+                is_coinbase: false,
+            }),
+            previous_outpoint: TransactionOutpoint { transaction_id: vec![index as u8; 32], index },
+            sequence: None,
+            partial_sigs: BTreeMap::new(),
+            sighash_type: SIG_HASH_ALL.to_u8(),
+            redeem_script,
+            sig_op_count: None,
+            final_script_sig: None,
+            other: BTreeMap::new(),
+        }
+    }
+
+    // Input 0 pays to a 2-of-2 of keys 1 and 2 through P2SH, input 1 to key 3 directly
+    fn pskt() -> Pskt {
+        let pubkeys = [1, 2].iter().map(|&byte| key(byte).1.x_only_public_key().0.serialize().to_vec()).collect();
+        let redeem_script = MultisigScript::new(2, pubkeys, false, true).unwrap().to_script();
+        let mut p2sh = vec![OP_BLAKE2B];
+        push_data(&mut p2sh, &script_hash(&redeem_script));
+        p2sh.push(OP_EQUAL);
+        let p2pk = p2pk_script(&key(3).1.serialize()[1..]);
+
+        Pskt {
+            global: Global { tx_version: 0, fallback_lock_time: None, other: BTreeMap::new() },
+            inputs: vec![input(0, p2sh, Some(redeem_script)), input(1, p2pk.clone(), None)],
+            outputs: vec![PsktOutput {
+                amount: 90_000,
+                script_public_key: ScriptPublicKey { version: 0, script: p2pk },
+                redeem_script: None,
+                other: BTreeMap::new(),
+            }],
+        }
+    }
+
+    // What `pskt sign` does for one key and input
+    fn sign(pskt: &mut Pskt, input_index: usize, byte: u8) {
+        let (private_key, public_key) = key(byte);
+        let sighash = pskt.signature_hash(input_index).unwrap();
+        let signature = schnorr_sign(&Secp256k1::new(), &mut rand::thread_rng(), &sighash, &private_key).unwrap();
+        pskt.inputs[input_index]
+            .partial_sigs
+            .insert(hex::encode(public_key.serialize()), PartialSignature::Schnorr(hex::encode(signature)));
+    }
+
+    fn verify(pskt: &Pskt, input_index: usize, signature: &[u8], byte: u8) -> bool {
+        let sighash = Message::from_slice(&pskt.signature_hash(input_index).unwrap()).unwrap();
+        let public_key = XOnlyPublicKey::from(key(byte).1);
+        let signature = Signature::from_slice(signature).unwrap();
+        Secp256k1::new().verify_schnorr(&signature, &sighash, &public_key).is_ok()
+    }
+
+    #[test]
+    fn sig_op_count_is_required_or_derived() {
+        let mut pskt = pskt();
+        let tx = pskt.unsigned_transaction().unwrap();
+        assert_eq!((tx.inputs[0].sig_op_count, tx.inputs[1].sig_op_count), (2, 1));
+
+        pskt.inputs[0].sig_op_count = Some(5);
+        assert_eq!(pskt.unsigned_transaction().unwrap().inputs[0].sig_op_count, 5);
+
+        pskt.inputs[1].utxo_entry.as_mut().unwrap().script_public_key.script = vec![OP_EQUAL];
+        assert!(pskt.unsigned_transaction().is_err());
+        pskt.inputs[1].redeem_script = Some(vec![OP_EQUAL]);
+        assert!(pskt.unsigned_transaction().is_err());
+    }
+
+    #[test]
+    fn sign_combine_and_finalize() {
+        // Each cosigner signs their own copy
+        let (mut first, mut second) = (pskt(), pskt());
+        sign(&mut first, 0, 1);
+        sign(&mut first, 1, 3);
+        sign(&mut second, 0, 2);
+        for (pubkey, signature) in &first.inputs[0].partial_sigs {
+            let PartialSignature::Schnorr(signature) = signature else { panic!("expected a Schnorr signature") };
+            assert_eq!(pubkey, &hex::encode(key(1).1.serialize()));
+            assert!(verify(&first, 0, &hex::decode(signature).unwrap(), 1));
+        }
+
+        // One signature is not enough for the 2-of-2
+        assert!(first.clone().finalize().is_err());
+
+        let mut combined = first.clone();
+        combined.combine(&second).unwrap();
+        assert_eq!(combined.inputs[0].partial_sigs.len(), 2);
+        combined.combine(&second).unwrap();
+        assert_eq!(combined.inputs[0].partial_sigs.len(), 2);
+        combined.finalize().unwrap();
+        assert!(combined.inputs.iter().all(|input| input.partial_sigs.is_empty()));
+
+        // Multisig: one push per signature in redeem script key order, then the redeem script
+        let redeem_script = combined.inputs[0].redeem_script.clone().unwrap();
+        let multisig = MultisigScript::parse(&redeem_script).unwrap();
+        let script = combined.inputs[0].final_script_sig.clone().unwrap();
+        let mut expected_tail = Vec::new();
+        push_data(&mut expected_tail, &redeem_script);
+        assert_eq!(script.len(), 2 * 66 + expected_tail.len());
+        assert_eq!(script[132..], expected_tail[..]);
+        for (i, pushed) in script[..132].chunks(66).enumerate() {
+            assert_eq!((pushed[0], pushed[65]), (OP_DATA_65, SIG_HASH_ALL.to_u8()));
+            let byte = if multisig.pubkeys[i][..] == key(1).1.serialize()[1..] { 1 } else { 2 };
+            assert!(verify(&pskt(), 0, &pushed[1..65], byte));
+        }
+
+        // Pay-to-public-key: just the signature
+        let script = combined.inputs[1].final_script_sig.clone().unwrap();
+        assert_eq!((script.len(), script[0], script[65]), (66, OP_DATA_65, SIG_HASH_ALL.to_u8()));
+        assert!(verify(&pskt(), 1, &script[1..65], 3));
+
+        // The finalized transaction carries the signature scripts
+        let tx = combined.unsigned_transaction().unwrap();
+        assert_eq!(tx.inputs[0].signature_script, combined.inputs[0].final_script_sig.clone().unwrap());
+    }
+
+    #[test]
+    fn combine_rejects_mismatches() {
+        let (mut first, mut second) = (pskt(), pskt());
+        sign(&mut first, 1, 3);
+        sign(&mut second, 1, 3);
+        // Schnorr signatures are randomized, so two signatures for the same key conflict
+        assert!(first.clone().combine(&second).is_err());
+
+        let mut other = pskt();
+        other.outputs[0].amount += 1;
+        assert!(first.combine(&other).is_err());
+        let mut other = pskt();
+        other.inputs[1].previous_outpoint.index = 7;
+        assert!(first.combine(&other).is_err());
+    }
+
+    #[test]
+    fn bundle_round_trip() {
+        let mut with_unknown = pskt();
+        with_unknown.inputs[0].other.insert("proprietaries".to_string(), serde_json::json!({"x": 1}));
+        let json = serde_json::to_string(&vec![with_unknown.clone(), pskt()]).unwrap();
+        let bundle = Bundle::parse(&format!("{}{}", BUNDLE_PREFIX, hex::encode(&json))).unwrap();
+        assert!(bundle.prefixed);
+        assert_eq!(bundle.pskts, vec![with_unknown, pskt()]);
+        assert_eq!(Bundle::parse(&bundle.serialize().unwrap()).unwrap().pskts, bundle.pskts);
+
+        let single = Bundle::parse(&serde_json::to_string(&pskt()).unwrap()).unwrap();
+        assert!(!single.prefixed);
+        assert_eq!(single.pskts, vec![pskt()]);
+    }
+}
@@ -0,0 +1,90 @@
+// Python bindings (feature "python"), built into a wheel with maturin:
+//
+//     maturin develop --profile release-lib
+//
+// Searches run with the GIL released, so other Python threads keep going.
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::ffi::KASPA_MAX_SEARCH_WINDOW;
+use crate::{check_address_belongs_to_xpub_parallel, derive_xpub_address, load_xpub_from_string, KaspaXPub};
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// An account extended public key (xpub).
+#[pyclass(name = "ExtendedPublicKey", module = "kaspa_message_signer", frozen)]
+struct PyExtendedPublicKey {
+    xpub: KaspaXPub,
+}
+
+#[pymethods]
+impl PyExtendedPublicKey {
+    #[new]
+    fn new(xpub: &str) -> PyResult<Self> {
+        load_xpub(xpub)
+    }
+
+    /// Mainnet address at m/<chain>/<index>.
+    #[pyo3(signature = (index, chain = 0))]
+    fn derive_address(&self, index: u32, chain: u32) -> PyResult<String> {
+        Ok(derive_xpub_address(&self.xpub, chain, index).map_err(value_error)?.to_string())
+    }
+
+    /// Index of `address` among the first `window` receive addresses, or None.
+    /// `window` must be between 1 and 10,000,000, the C library's limit.
+    #[pyo3(signature = (address, window = 1000, threads = None))]
+    fn find_address(&self, py: Python<'_>, address: &str, window: u32, threads: Option<usize>) -> PyResult<Option<u32>> {
+        if window == 0 || window > KASPA_MAX_SEARCH_WINDOW {
+            return Err(value_error(format!("window must be between 1 and {}", KASPA_MAX_SEARCH_WINDOW)));
+        }
+        let threads = threads.unwrap_or_else(num_cpus::get);
+        if threads == 0 {
+            return Err(value_error("threads must be at least 1"));
+        }
+        let xpub = self.xpub.clone();
+        let address = address.trim().to_string();
+        py.allow_threads(move || {
+            check_address_belongs_to_xpub_parallel(&xpub, &address, window, threads).map_err(|e| e.to_string())
+        })
+        .map_err(value_error)
+    }
+}
+
+/// Checks a hex compact ECDSA signature of `message` by `address`.
+/// Returns False for a wrong signature and raises ValueError for malformed input.
+#[pyfunction]
+fn verify_signature(message: &str, signature: &str, address: &str) -> PyResult<bool> {
+    crate::verify_signature(message, signature.trim(), address.trim()).map_err(value_error)
+}
+
+/// Checks a hex BIP340 Schnorr signature of `message` by a Schnorr `address`.
+/// Returns False for a wrong signature and raises ValueError for malformed input.
+#[pyfunction]
+fn verify_schnorr_signature(message: &str, signature: &str, address: &str) -> PyResult<bool> {
+    crate::verify_schnorr_signature(message, signature.trim(), address.trim()).map_err(value_error)
+}
+
+/// Parses an xpub string.
+#[pyfunction]
+fn load_xpub(xpub: &str) -> PyResult<PyExtendedPublicKey> {
+    Ok(PyExtendedPublicKey { xpub: load_xpub_from_string(xpub.trim()).map_err(value_error)? })
+}
+
+/// Mainnet address at m/<chain>/<index> below an xpub string.
+#[pyfunction]
+#[pyo3(signature = (xpub, index, chain = 0))]
+fn derive_address(xpub: &str, index: u32, chain: u32) -> PyResult<String> {
+    load_xpub(xpub)?.derive_address(index, chain)
+}
+
+#[pymodule]
+fn kaspa_message_signer(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyExtendedPublicKey>()?;
+    m.add_function(wrap_pyfunction!(verify_signature, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_schnorr_signature, m)?)?;
+    m.add_function(wrap_pyfunction!(load_xpub, m)?)?;
+    m.add_function(wrap_pyfunction!(derive_address, m)?)?;
+    Ok(())
+}
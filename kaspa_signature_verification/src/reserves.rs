@@ -0,0 +1,219 @@
+// Proof of reserves: one challenge signed by the key of every address in a set of
+// derivation ranges, checked against a UTXO snapshot to total the proven balance.
+//
+// Each signature is an ordinary message signature over the challenge, so any
+// single entry can also be checked with `verify`.
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::transaction::UtxoEntry;
+use crate::verify_signature;
+
+/// A range of address indices on one chain (0 receive, 1 change), inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressRange {
+    pub chain: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl AddressRange {
+    /// Parses comma separated `<chain>:<start>-<end>` ranges, e.g. "0:0-999,1:0-99".
+    pub fn parse_list(input: &str) -> Result<Vec<AddressRange>, Box<dyn std::error::Error>> {
+        input
+            .split(',')
+            .map(|range| {
+                let range = range.trim();
+                let (chain, indices) = range
+                    .split_once(':')
+                    .ok_or_else(|| format!("Invalid range '{}': expected <chain>:<start>-<end>", range))?;
+                let (start, end) = indices
+                    .split_once('-')
+                    .ok_or_else(|| format!("Invalid range '{}': expected <chain>:<start>-<end>", range))?;
+                let range = AddressRange {
+                    chain: chain.trim().parse()?,
+                    start: start.trim().parse()?,
+                    end: end.trim().parse()?,
+                };
+                if range.chain > 1 || range.start > range.end {
+                    return Err(format!("Invalid range {}:{}-{}", range.chain, range.start, range.end).into());
+                }
+                Ok(range)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofEntry {
+    pub address: String,
+    pub path: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReservesProof {
+    pub challenge: String,
+    pub entries: Vec<ProofEntry>,
+}
+
+// A snapshot row is either {"address", "amount"} or an RPC UTXO with "utxoEntry"
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotEntry {
+    address: String,
+    #[serde(default)]
+    amount: Option<u64>,
+    #[serde(default)]
+    utxo_entry: Option<UtxoEntry>,
+}
+
+/// Balance per address from a UTXO snapshot.
+pub fn parse_snapshot(json: &str) -> Result<BTreeMap<String, u64>, Box<dyn std::error::Error>> {
+    let entries: Vec<SnapshotEntry> = serde_json::from_str(json)?;
+    let mut balances = BTreeMap::new();
+    for entry in entries {
+        let amount = entry
+            .amount
+            .or(entry.utxo_entry.map(|utxo| utxo.amount))
+            .ok_or_else(|| format!("Snapshot entry for {} has no amount", entry.address))?;
+        let balance = balances.entry(entry.address).or_insert(0u64);
+        *balance = balance
+            .checked_add(amount)
+            .ok_or("Snapshot balance overflows")?;
+    }
+    Ok(balances)
+}
+
+/// Outcome of checking a proof.
+pub struct ReservesReport {
+    pub valid: usize,
+    pub invalid: Vec<(String, String)>,
+    /// Sum of the snapshot balances of addresses with a valid signature
+    pub proven_balance: u64,
+    /// Snapshot addresses the proof doesn't cover
+    pub unproven_addresses: usize,
+}
+
+impl ReservesProof {
+    pub fn parse(json: &str) -> Result<ReservesProof, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Checks every signature over the challenge and totals the proven balances.
+    /// An address listed twice is only counted once.
+    pub fn verify(&self, balances: &BTreeMap<String, u64>) -> ReservesReport {
+        let mut proven = HashSet::new();
+        let mut invalid = Vec::new();
+        for entry in &self.entries {
+            match verify_signature(&self.challenge, &entry.signature, &entry.address) {
+                Ok(true) => {
+                    proven.insert(entry.address.as_str());
+                }
+                Ok(false) => invalid.push((entry.address.clone(), "signature does not match".to_string())),
+                Err(e) => invalid.push((entry.address.clone(), e.to_string())),
+            }
+        }
+
+        let proven_balance = proven.iter().filter_map(|address| balances.get(*address)).sum();
+        let unproven_addresses = balances.keys().filter(|address| !proven.contains(address.as_str())).count();
+        ReservesReport {
+            valid: proven.len(),
+            invalid,
+            proven_balance,
+            unproven_addresses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign_message;
+    use kaspa_addresses::{Address, Prefix, Version};
+    use secp256k1::{Secp256k1, SecretKey};
+
+    const CHALLENGE: &str = "Reserves of example.org at DAA score 81000000";
+
+    fn entry(byte: u8, challenge: &str) -> ProofEntry {
+        let private_key = SecretKey::from_slice(&[byte; 32]).unwrap();
+        let public_key = private_key.public_key(&Secp256k1::new()).serialize();
+        ProofEntry {
+            address: Address::new(Prefix::Mainnet, Version::PubKey, &public_key[1..33]).to_string(),
+            path: format!("m/44'/111111'/0'/0/{}", byte),
+            signature: sign_message(challenge, &private_key).unwrap(),
+        }
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(
+            AddressRange::parse_list("0:0-999, 1:5-5").unwrap(),
+            vec![
+                AddressRange { chain: 0, start: 0, end: 999 },
+                AddressRange { chain: 1, start: 5, end: 5 }
+            ]
+        );
+        for invalid in ["2:0-9", "0:9-0", "0:0", "0-9", "0:a-9", ""] {
+            assert!(AddressRange::parse_list(invalid).is_err(), "{} accepted", invalid);
+        }
+    }
+
+    #[test]
+    fn snapshot_balances() {
+        let (first, second) = (entry(1, CHALLENGE).address, entry(2, CHALLENGE).address);
+        let json = format!(
+            r#"[{{"address": "{first}", "amount": 100}},
+                {{"address": "{first}", "utxoEntry": {{"amount": 50, "scriptPublicKey": {{"version": 0, "script": ""}}}}}},
+                {{"address": "{second}", "amount": 7}}]"#
+        );
+        let balances = parse_snapshot(&json).unwrap();
+        assert_eq!(balances, BTreeMap::from([(first.clone(), 150), (second, 7)]));
+
+        assert!(parse_snapshot(&format!(r#"[{{"address": "{first}"}}]"#)).is_err());
+        let overflow = format!(r#"[{{"address": "{first}", "amount": {max}}}, {{"address": "{first}", "amount": 1}}]"#, max = u64::MAX);
+        assert!(parse_snapshot(&overflow).is_err());
+    }
+
+    #[test]
+    fn prove_and_verify() {
+        let proof = ReservesProof {
+            challenge: CHALLENGE.to_string(),
+            entries: vec![entry(1, CHALLENGE), entry(2, CHALLENGE)],
+        };
+        let proof = ReservesProof::parse(&serde_json::to_string(&proof).unwrap()).unwrap();
+        let unlisted = entry(3, CHALLENGE).address;
+        let balances = BTreeMap::from([
+            (proof.entries[0].address.clone(), 1_000),
+            (proof.entries[1].address.clone(), 234),
+            (unlisted, 5),
+        ]);
+
+        let report = proof.verify(&balances);
+        assert_eq!((report.valid, report.invalid.len()), (2, 0));
+        assert_eq!((report.proven_balance, report.unproven_addresses), (1_234, 1));
+
+        // A signature over another challenge, or for another address, proves nothing
+        let mut forged = proof.clone();
+        forged.entries[1].signature = entry(2, "another challenge").signature;
+        forged.entries.push(ProofEntry { address: entry(3, CHALLENGE).address, ..entry(4, CHALLENGE) });
+        let report = forged.verify(&balances);
+        assert_eq!((report.valid, report.invalid.len(), report.proven_balance), (1, 2, 1_000));
+        assert_eq!(report.invalid[0].0, proof.entries[1].address);
+    }
+
+    #[test]
+    fn duplicate_addresses_count_once() {
+        let first = entry(1, CHALLENGE);
+        let upper_case = ProofEntry { address: first.address.to_uppercase(), ..first.clone() };
+        let proof = ReservesProof {
+            challenge: CHALLENGE.to_string(),
+            entries: vec![first.clone(), first.clone(), entry(2, CHALLENGE), upper_case],
+        };
+        let balances = BTreeMap::from([(first.address.clone(), 1_000)]);
+        let report = proof.verify(&balances);
+        // Upper case addresses don't parse, so they can't be counted as another address either
+        assert_eq!((report.valid, report.invalid.len()), (2, 1));
+        assert_eq!((report.proven_balance, report.unproven_addresses), (1_000, 0));
+    }
+}
@@ -0,0 +1,211 @@
+// Hardware-wallet style review of a message before it is signed: the exact text, with
+// everything but printable ASCII escaped so hidden characters and look-alike letters
+// show up, the digest the signature actually covers, and the key that makes it. Long
+// messages are shortened to their start and end, binary-looking ones to a hex preview,
+// and messages that look like a transaction or a sighash come with a warning.
+use serde_json::Value;
+
+// Messages longer than this show only their first and last characters
+const MAX_SHOWN: usize = 600;
+/// Bytes of a streamed payload to keep for the review: all of it if it fits on screen
+pub const PREVIEW_BYTES: usize = MAX_SHOWN;
+const HEAD_SHOWN: usize = 400;
+const TAIL_SHOWN: usize = 150;
+const HEX_PREVIEW: usize = 64;
+
+/// The message with \n, \r, \t, \\ and \u{...} escapes. Line breaks are kept after
+/// their escape so multi-line messages stay readable.
+pub fn escape_visible(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\\' => escaped.push_str("\\\\"),
+            ' '..='~' => escaped.push(c),
+            c => escaped.push_str(&format!("\\u{{{:04x}}}", c as u32)),
+        }
+    }
+    escaped
+}
+
+fn is_plain(c: char) -> bool {
+    matches!(c, ' '..='~' | '\n' | '\r' | '\t')
+}
+
+/// Warnings for messages that may authorize more than they appear to.
+pub fn warnings(message: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let trimmed = message.trim();
+    let hex = trimmed.strip_prefix("0x").unwrap_or(trimmed);
+    let is_hex = !hex.is_empty() && hex.len().is_multiple_of(2) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    if is_hex && hex.len() == 64 {
+        warnings.push("The message is a 32-byte hex value, like a transaction sighash. Signing it may authorize a transaction.".to_string());
+    } else if is_hex && hex.len() > 64 {
+        warnings.push(format!("The message is {} bytes of hex-encoded data, possibly a serialized transaction.", hex.len() / 2));
+    }
+    if let Ok(Value::Object(object)) = serde_json::from_str::<Value>(trimmed) {
+        let keys: Vec<&str> = ["inputs", "outputs", "transaction", "pskt", "sighash"]
+            .into_iter()
+            .filter(|key| object.contains_key(*key))
+            .collect();
+        if !keys.is_empty() {
+            warnings.push(format!("The message is JSON with {} fields, like a transaction. Sign transactions with sign-tx or pskt sign instead.", keys.join(", ")));
+        }
+    }
+    if message.chars().any(|c| !is_plain(c)) {
+        warnings.push("The message contains characters that are not plain ASCII (shown as \\u{...}).".to_string());
+    }
+    warnings
+}
+
+/// The review shown before signing `message`; `digest` is what the signature covers.
+pub fn render(message: &str, digest: &[u8; 32], address: &str, key_path: &str) -> String {
+    render_payload(message.as_bytes(), message.len() as u64, digest, address, key_path)
+}
+
+/// Like `render`, for a payload of `size` bytes of which only the start, `head`, is at
+/// hand, as when a large file is hashed as a stream.
+pub fn render_payload(head: &[u8], size: u64, digest: &[u8; 32], address: &str, key_path: &str) -> String {
+    let message = String::from_utf8_lossy(head);
+    let chars: Vec<char> = message.chars().collect();
+    let unusual = chars.iter().filter(|c| !is_plain(**c)).count();
+    let not_shown = size.saturating_sub(head.len() as u64);
+    let line_count = message.lines().count().max(1);
+    let length = if not_shown > 0 {
+        format!("{} bytes, the first {} shown", size, head.len())
+    } else {
+        format!("{} bytes, {} line{}", size, line_count, if line_count == 1 { "" } else { "s" })
+    };
+    let mut lines = vec![
+        "Review before signing".to_string(),
+        format!("  Address: {}", address),
+        format!("  Key:     {}", key_path),
+        format!("  Length:  {}", length),
+        format!("  Digest:  {} (what the signature covers)", hex::encode(digest)),
+    ];
+
+    // More than a quarter unusual characters reads as binary: show bytes, not text
+    if unusual * 4 > chars.len() {
+        let preview = &head[..head.len().min(HEX_PREVIEW)];
+        let more = size - preview.len() as u64;
+        lines.push(format!("  Message: binary-looking, {} of {} characters not printable ASCII", unusual, chars.len()));
+        lines.push(format!(
+            "    hex: {}{}",
+            hex::encode(preview),
+            if more > 0 { format!(" ... ({} more bytes)", more) } else { String::new() }
+        ));
+    } else {
+        lines.push("  Message:".to_string());
+        let mut shown = if chars.len() > MAX_SHOWN {
+            let head: String = chars[..HEAD_SHOWN].iter().collect();
+            let tail: String = chars[chars.len() - TAIL_SHOWN..].iter().collect();
+            format!(
+                "{}\n[... {} characters not shown ...]\n{}",
+                escape_visible(&head),
+                chars.len() - HEAD_SHOWN - TAIL_SHOWN,
+                escape_visible(&tail)
+            )
+        } else {
+            escape_visible(&message)
+        };
+        if not_shown > 0 {
+            shown.push_str(&format!("\n[... {} more bytes not shown ...]", not_shown));
+        }
+        lines.extend(shown.lines().map(|line| format!("    | {}", line)));
+    }
+
+    for warning in warnings(&message) {
+        lines.push(format!("  WARNING: {}", warning));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh";
+
+    #[test]
+    fn escapes_everything_but_printable_ascii() {
+        assert_eq!(escape_visible("plain text ~!"), "plain text ~!");
+        assert_eq!(escape_visible("a\tb\\c\rd"), "a\\tb\\\\c\\rd");
+        assert_eq!(escape_visible("line 1\nline 2"), "line 1\\n\nline 2");
+        // Look-alikes and invisible characters
+        assert_eq!(escape_visible("p\u{0430}y"), "p\\u{0430}y");
+        assert_eq!(escape_visible("a\u{200b}b\u{202e}"), "a\\u{200b}b\\u{202e}");
+        assert_eq!(escape_visible("\u{0}\u{1f600}"), "\\u{0000}\\u{1f600}");
+    }
+
+    #[test]
+    fn warns_about_transaction_like_messages() {
+        assert!(warnings("Login to example.com at 2024-05-01").is_empty());
+        assert!(warnings("cafe").is_empty());
+        let sighash = "8a12dc89".repeat(8);
+        assert_eq!(warnings(&sighash).len(), 1);
+        assert!(warnings(&format!("0x{}", sighash))[0].contains("sighash"));
+        assert!(warnings(&"00".repeat(100))[0].contains("100 bytes"));
+        // Odd-length hex is not bytes
+        assert!(warnings(&"0".repeat(65)).is_empty());
+
+        let json = warnings(r#"{"inputs": [], "outputs": [], "note": "x"}"#);
+        assert_eq!(json.len(), 1);
+        assert!(json[0].contains("inputs, outputs"));
+        assert!(warnings(r#"{"note": "x"}"#).is_empty());
+        assert!(warnings("pay\u{0430}")[0].contains("not plain ASCII"));
+        assert!(warnings("two\nlines\twith tabs\r\n").is_empty());
+    }
+
+    #[test]
+    fn renders_short_text_whole() {
+        let digest = [7u8; 32];
+        let review = render("Hello\nKaspa", &digest, ADDRESS, "m/44'/111111'/0'/0/0");
+        assert!(review.contains(&format!("Address: {}", ADDRESS)));
+        assert!(review.contains("Key:     m/44'/111111'/0'/0/0"));
+        assert!(review.contains("Length:  11 bytes, 2 lines"));
+        assert!(review.contains(&hex::encode(digest)));
+        assert!(review.ends_with("    | Hello\\n\n    | Kaspa"));
+        assert!(!review.contains("WARNING"));
+    }
+
+    #[test]
+    fn shortens_long_text() {
+        let message: String = (0..1000).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+        let review = render(&message, &[0; 32], ADDRESS, "key");
+        assert!(review.contains(&format!("[... {} characters not shown ...]", 1000 - HEAD_SHOWN - TAIL_SHOWN)));
+        assert!(review.contains(&format!("    | {}", &message[..HEAD_SHOWN])));
+        assert!(review.contains(&format!("    | {}", &message[1000 - TAIL_SHOWN..])));
+        assert!(!review.contains(&message[..HEAD_SHOWN + 1]));
+
+        // Exactly at the limit nothing is cut
+        let review = render(&message[..MAX_SHOWN], &[0; 32], ADDRESS, "key");
+        assert!(!review.contains("not shown"));
+    }
+
+    #[test]
+    fn shows_binary_as_hex() {
+        let bytes: Vec<u8> = (0..100u8).map(|i| i.wrapping_mul(37) | 0x80).collect();
+        let message = String::from_utf8_lossy(&bytes).into_owned();
+        let review = render(&message, &[0; 32], ADDRESS, "key");
+        assert!(review.contains("Message: binary-looking"));
+        let shown = &message.as_bytes()[..HEX_PREVIEW];
+        assert!(review.contains(&format!("hex: {} ... ({} more bytes)", hex::encode(shown), message.len() - HEX_PREVIEW)));
+        assert!(review.contains("WARNING: The message contains characters that are not plain ASCII"));
+
+        // A few unusual characters in text are escaped, not hex
+        let review = render("caf\u{e9} au lait", &[0; 32], ADDRESS, "key");
+        assert!(review.contains("    | caf\\u{00e9} au lait"));
+    }
+
+    #[test]
+    fn renders_the_start_of_a_streamed_payload() {
+        let review = render_payload(b"first bytes", 5000, &[0; 32], ADDRESS, "key");
+        assert!(review.contains("Length:  5000 bytes, the first 11 shown"));
+        assert!(review.contains("    | first bytes\n    | [... 4989 more bytes not shown ...]"));
+
+        let review = render_payload(&[0xff; 10], 5000, &[0; 32], ADDRESS, "key");
+        assert!(review.contains(&format!("hex: {} ... (4990 more bytes)", "ff".repeat(10))));
+    }
+}
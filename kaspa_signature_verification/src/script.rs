@@ -0,0 +1,157 @@
+// Kaspa script building blocks shared by transaction signing and PSKT finalization.
+use blake2b_simd::Params;
+use kaspa_addresses::{Address, Prefix, Version};
+
+pub const OP_DATA_32: u8 = 0x20;
+pub const OP_DATA_33: u8 = 0x21;
+pub const OP_DATA_65: u8 = 0x41;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_PUSHDATA4: u8 = 0x4e;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_CHECKMULTISIG_ECDSA: u8 = 0xa9;
+pub const OP_CHECKSIG_ECDSA: u8 = 0xab;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKMULTISIG: u8 = 0xae;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_BLAKE2B: u8 = 0xaa;
+
+/// Pay-to-public-key script for a Schnorr x-only public key: OP_DATA_32 <key> OP_CHECKSIG.
+pub fn p2pk_script(x_only_pubkey: &[u8]) -> Vec<u8> {
+    let mut script = Vec::with_capacity(34);
+    script.push(OP_DATA_32);
+    script.extend_from_slice(x_only_pubkey);
+    script.push(OP_CHECKSIG);
+    script
+}
+
+/// Pay-to-public-key script for an ECDSA compressed public key: OP_DATA_33 <key> OP_CHECKSIG_ECDSA.
+pub fn p2pk_ecdsa_script(compressed_pubkey: &[u8]) -> Vec<u8> {
+    let mut script = Vec::with_capacity(35);
+    script.push(OP_DATA_33);
+    script.extend_from_slice(compressed_pubkey);
+    script.push(OP_CHECKSIG_ECDSA);
+    script
+}
+
+/// Unkeyed BLAKE2b-256 of a redeem script, the payload of a `Version::ScriptHash` address.
+pub fn script_hash(redeem_script: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(Params::new().hash_length(32).hash(redeem_script).as_bytes());
+    hash
+}
+
+/// Address paid to by a standard script public key, if it is one.
+pub fn address_from_script(script: &[u8], prefix: Prefix) -> Option<Address> {
+    match script {
+        [OP_DATA_32, key @ .., OP_CHECKSIG] if key.len() == 32 => Some(Address::new(prefix, Version::PubKey, key)),
+        [OP_DATA_33, key @ .., OP_CHECKSIG_ECDSA] if key.len() == 33 => {
+            Some(Address::new(prefix, Version::PubKeyECDSA, key))
+        }
+        [OP_BLAKE2B, OP_DATA_32, hash @ .., OP_EQUAL] if hash.len() == 32 => {
+            Some(Address::new(prefix, Version::ScriptHash, hash))
+        }
+        _ => None,
+    }
+}
+
+/// Appends `data` to `script` with the smallest push opcode that fits it.
+pub fn push_data(script: &mut Vec<u8>, data: &[u8]) {
+    match data.len() {
+        len @ 0..=75 => script.push(len as u8),
+        len @ 76..=255 => {
+            script.push(OP_PUSHDATA1);
+            script.push(len as u8);
+        }
+        len @ 256..=65535 => {
+            script.push(OP_PUSHDATA2);
+            script.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+        len => {
+            script.push(OP_PUSHDATA4);
+            script.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+    }
+    script.extend_from_slice(data);
+}
+
+/// Signature script of a P2PK input: OP_DATA_65 <64-byte Schnorr or compact ECDSA signature || sighash type>.
+pub fn signature_push(signature: &[u8], sighash_type: u8) -> Vec<u8> {
+    let mut script = Vec::with_capacity(66);
+    script.push(OP_DATA_65);
+    script.extend_from_slice(signature);
+    script.push(sighash_type);
+    script
+}
+
+/// A parsed `OP_m <pubkey>... OP_n OP_CHECKMULTISIG[_ECDSA]` redeem script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigScript {
+    pub required: usize,
+    /// 32-byte x-only keys for Schnorr, 33-byte compressed keys for ECDSA
+    pub pubkeys: Vec<Vec<u8>>,
+    pub ecdsa: bool,
+}
+
+impl MultisigScript {
+    /// Checks the key sizes and threshold; `sorted` orders the keys by their bytes first,
+    /// so every cosigner gets the same script regardless of the order keys were listed in.
+    pub fn new(required: usize, mut pubkeys: Vec<Vec<u8>>, ecdsa: bool, sorted: bool) -> Result<MultisigScript, String> {
+        let key_len = if ecdsa { 33 } else { 32 };
+        if let Some(key) = pubkeys.iter().find(|key| key.len() != key_len) {
+            return Err(format!("Expected {}-byte public keys, got {} bytes", key_len, key.len()));
+        }
+        if pubkeys.is_empty() || pubkeys.len() > 16 {
+            return Err(format!("A multisig script takes 1 to 16 keys, got {}", pubkeys.len()));
+        }
+        if required == 0 || required > pubkeys.len() {
+            return Err(format!("Cannot require {} of {} signatures", required, pubkeys.len()));
+        }
+        if sorted {
+            pubkeys.sort();
+        }
+        Ok(MultisigScript { required, pubkeys, ecdsa })
+    }
+
+    /// The redeem script: OP_m <pubkey>... OP_n OP_CHECKMULTISIG[_ECDSA].
+    pub fn to_script(&self) -> Vec<u8> {
+        let mut script = vec![OP_1 + self.required as u8 - 1];
+        for key in &self.pubkeys {
+            push_data(&mut script, key);
+        }
+        script.push(OP_1 + self.pubkeys.len() as u8 - 1);
+        script.push(if self.ecdsa { OP_CHECKMULTISIG_ECDSA } else { OP_CHECKMULTISIG });
+        script
+    }
+
+    pub fn parse(script: &[u8]) -> Option<MultisigScript> {
+        let (&first, rest) = script.split_first()?;
+        let (&last, rest) = rest.split_last()?;
+        let (&count_op, mut keys) = rest.split_last()?;
+        if !(OP_1..=OP_16).contains(&first) || !(OP_1..=OP_16).contains(&count_op) {
+            return None;
+        }
+        let ecdsa = match last {
+            OP_CHECKMULTISIG => false,
+            OP_CHECKMULTISIG_ECDSA => true,
+            _ => return None,
+        };
+        let key_len = if ecdsa { 33 } else { 32 };
+
+        let mut pubkeys = Vec::new();
+        while let Some((&push, rest)) = keys.split_first() {
+            if push as usize != key_len || rest.len() < key_len {
+                return None;
+            }
+            pubkeys.push(rest[..key_len].to_vec());
+            keys = &rest[key_len..];
+        }
+
+        let required = (first - OP_1 + 1) as usize;
+        if pubkeys.len() != (count_op - OP_1 + 1) as usize || required > pubkeys.len() {
+            return None;
+        }
+        Some(MultisigScript { required, pubkeys, ecdsa })
+    }
+}
@@ -0,0 +1,297 @@
+// Local HTTP/JSON service exposing verification to other programs.
+//
+// Each method is served as `POST /<method>` with a JSON object body, and as a
+// JSON-RPC 2.0 call on `POST /rpc`. Signing is off unless the server was started
+// with a mnemonic. Requests beyond the concurrency limit are turned away with 503
+// rather than queued, and every request is logged as one JSON line on stderr.
+use kaspa_addresses::Address;
+use secp256k1::SecretKey;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::batch::{self, BatchRow};
+use crate::{check_address_belongs_to_xpub_with, double_sha256, recover_signature_addresses, sign_message};
+
+pub struct ServeConfig {
+    /// Largest request body accepted, in bytes
+    pub max_body: usize,
+    /// Requests handled at the same time; more are answered with 503
+    pub max_concurrent: usize,
+    /// Largest `window` a match-xpub request may search
+    pub max_window: u32,
+    /// Threads each match-xpub search uses
+    pub search_threads: usize,
+    /// Key and address to sign with, when signing is enabled
+    pub signer: Option<(SecretKey, Address)>,
+}
+
+/// An error answered with an HTTP status (or the matching JSON-RPC code).
+#[derive(Debug)]
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> ApiError {
+        ApiError { status: 400, message: message.into() }
+    }
+
+    fn rpc_code(&self) -> i64 {
+        match self.status {
+            400 => -32602,
+            404 => -32601,
+            _ => -32000,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct VerifyParams {
+    address: String,
+    message: String,
+    signature: String,
+    #[serde(default)]
+    algorithm: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RecoverParams {
+    message: String,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct MatchXpubParams {
+    xpub: String,
+    address: String,
+    #[serde(default)]
+    window: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct SignParams {
+    message: String,
+}
+
+fn params<T: for<'de> Deserialize<'de>>(value: Value) -> Result<T, ApiError> {
+    serde_json::from_value(value).map_err(|e| ApiError::bad_request(format!("invalid params: {}", e)))
+}
+
+/// Runs one method with its JSON params.
+fn call(config: &ServeConfig, method: &str, value: Value) -> Result<Value, ApiError> {
+    match method {
+        "verify" => {
+            let p: VerifyParams = params(value)?;
+            let row = BatchRow {
+                line: 0,
+                address: p.address,
+                message: p.message,
+                signature: p.signature,
+                algorithm: p.algorithm,
+            };
+            let result = batch::verify_row(&row);
+            if result.valid {
+                Ok(json!({ "valid": true }))
+            } else {
+                Ok(json!({ "valid": false, "reason": result.reason }))
+            }
+        }
+        "recover" => {
+            let p: RecoverParams = params(value)?;
+            let addresses = recover_signature_addresses(&double_sha256(p.message.as_bytes()), p.signature.trim())
+                .map_err(|e| ApiError::bad_request(e.to_string()))?;
+            let addresses: Vec<String> = addresses.iter().map(Address::to_string).collect();
+            Ok(json!({ "addresses": addresses }))
+        }
+        "match-xpub" => {
+            let p: MatchXpubParams = params(value)?;
+            let window = p.window.unwrap_or(1000);
+            if window == 0 || window > config.max_window {
+                return Err(ApiError::bad_request(format!("window must be between 1 and {}", config.max_window)));
+            }
+            let account = crate::descriptor::load_account_descriptor(p.xpub.trim()).map_err(|e| ApiError::bad_request(e.to_string()))?;
+            let xpub = account.xpub().map_err(|e| ApiError::bad_request(e.to_string()))?;
+            Address::try_from(p.address.trim()).map_err(|e| ApiError::bad_request(format!("invalid address: {}", e)))?;
+            // Addresses of the descriptor's network and type; bare keys are mainnet Schnorr
+            let ecdsa = account.address_type == crate::descriptor::AddressType::PubKeyEcdsa;
+            let found = check_address_belongs_to_xpub_with(&xpub, p.address.trim(), window, config.search_threads, account.network, ecdsa)
+                .map_err(|e| ApiError { status: 500, message: e.to_string() })?;
+            Ok(match found {
+                Some(index) => json!({ "found": true, "index": index, "path": format!("m/0/{}", index) }),
+                None => json!({ "found": false, "window": window }),
+            })
+        }
+        "sign" => {
+            let (private_key, address) = config.signer.as_ref().ok_or(ApiError {
+                status: 403,
+                message: "signing is disabled; start the server with --enable-signing".to_string(),
+            })?;
+            let p: SignParams = params(value)?;
+            let signature = sign_message(&p.message, private_key).map_err(|e| ApiError { status: 500, message: e.to_string() })?;
+            Ok(json!({ "address": address.to_string(), "signature": signature }))
+        }
+        other => Err(ApiError { status: 404, message: format!("unknown method: {}", other) }),
+    }
+}
+
+/// Handles a JSON-RPC 2.0 request object. Errors are part of the reply, so the status is always 200.
+fn call_rpc(config: &ServeConfig, body: &[u8]) -> Value {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": e.to_string() } }),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32600, "message": "missing method" } });
+    };
+    let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+    match call(config, method, params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.rpc_code(), "message": e.message } }),
+    }
+}
+
+/// Reads the body, refusing anything larger than `max_body` whether or not a length was sent.
+fn read_body(request: &mut Request, max_body: usize) -> Result<Vec<u8>, ApiError> {
+    let too_large = || ApiError { status: 413, message: format!("request body is larger than {} bytes", max_body) };
+    if request.body_length().is_some_and(|length| length > max_body) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(max_body as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| ApiError::bad_request(format!("cannot read body: {}", e)))?;
+    if body.len() > max_body {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+/// Routes one request to its method, returning the status and JSON reply.
+fn handle(config: &ServeConfig, request: &mut Request) -> (u16, Value) {
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    let result = match (request.method(), path.as_str()) {
+        (Method::Get, "/health") => Ok(json!({ "status": "ok", "signing": config.signer.is_some() })),
+        (Method::Post, "/rpc") => match read_body(request, config.max_body) {
+            Ok(body) => return (200, call_rpc(config, &body)),
+            Err(e) => Err(e),
+        },
+        (Method::Post, route) => read_body(request, config.max_body).and_then(|body| {
+            let value = serde_json::from_slice(&body).map_err(|e| ApiError::bad_request(format!("invalid JSON: {}", e)))?;
+            call(config, route.trim_start_matches('/'), value)
+        }),
+        _ => Err(ApiError { status: 405, message: "use POST /<method>, POST /rpc or GET /health".to_string() }),
+    };
+    match result {
+        Ok(value) => (200, value),
+        Err(e) => (e.status, json!({ "error": e.message })),
+    }
+}
+
+fn respond(request: Request, status: u16, value: &Value) {
+    let header = Header::from_bytes("Content-Type", "application/json").expect("static header");
+    let response = Response::from_string(value.to_string()).with_status_code(status).with_header(header);
+    // The client may already be gone; there is nobody left to tell
+    let _ = request.respond(response);
+}
+
+/// One structured log line per request. Message and signature contents are never logged.
+fn log_request(remote: Option<SocketAddr>, method: &str, path: &str, status: u16, started: Instant, error: Option<&str>) {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let mut line = json!({
+        "ts": timestamp as u64,
+        "remote": remote.map(|addr| addr.to_string()),
+        "method": method,
+        "path": path,
+        "status": status,
+        "duration_ms": started.elapsed().as_secs_f64() * 1000.0,
+    });
+    if let Some(error) = error {
+        line["error"] = json!(error);
+    }
+    eprintln!("{}", line);
+}
+
+/// Binds the server and returns it with the address it listens on (useful with port 0).
+pub fn bind(address: &str) -> Result<(Server, SocketAddr), Box<dyn std::error::Error>> {
+    let server = Server::http(address).map_err(|e| format!("Cannot listen on {}: {}", address, e))?;
+    let local = server.server_addr().to_ip().ok_or("Server is not listening on a TCP address")?;
+    Ok((server, local))
+}
+
+/// Accepts requests forever, handling up to `max_concurrent` of them at once on their own threads.
+pub fn run(server: Server, config: ServeConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Arc::new(config);
+    let in_flight = Arc::new(Mutex::new(0usize));
+
+    loop {
+        let request = server.recv()?;
+        let started = Instant::now();
+        let remote = request.remote_addr().copied();
+        let method = request.method().to_string();
+        let path = request.url().split('?').next().unwrap_or("").to_string();
+
+        {
+            let mut count = in_flight.lock().unwrap();
+            if *count >= config.max_concurrent {
+                drop(count);
+                let message = "too many concurrent requests";
+                respond(request, 503, &json!({ "error": message }));
+                log_request(remote, &method, &path, 503, started, Some(message));
+                continue;
+            }
+            *count += 1;
+        }
+
+        let config = Arc::clone(&config);
+        let slot = InFlightSlot(Arc::clone(&in_flight));
+        thread::spawn(move || {
+            // Frees the slot when the thread ends, even if handling the request panics
+            let _slot = slot;
+            let mut request = request;
+            let (status, value) = handle(&config, &mut request);
+            respond(request, status, &value);
+            let error = value.get("error").map(|error| match error {
+                Value::String(message) => message.clone(),
+                other => other["message"].as_str().unwrap_or_default().to_string(),
+            });
+            log_request(remote, &method, &path, status, started, error.as_deref());
+        });
+    }
+}
+
+// One of the `max_concurrent` request slots, given back when dropped
+struct InFlightSlot(Arc<Mutex<usize>>);
+
+impl Drop for InFlightSlot {
+    fn drop(&mut self) {
+        let mut count = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *count -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panicking_handler_frees_its_slot() {
+        let in_flight = Arc::new(Mutex::new(1usize));
+        let slot = InFlightSlot(Arc::clone(&in_flight));
+        let handle = thread::spawn(move || {
+            let _slot = slot;
+            panic!("handler bug");
+        });
+        assert!(handle.join().is_err());
+        assert_eq!(*in_flight.lock().unwrap(), 0);
+    }
+}
@@ -0,0 +1,379 @@
+// Kaspa transaction signature hashing, as in rusty-kaspa.
+//
+// Every hash is BLAKE2b-256 keyed with "TransactionSigningHash" and integers
+// are written little-endian. ECDSA signatures sign the Schnorr sighash once more
+// through SHA-256 seeded with sha256("TransactionSigningHashECDSA").
+use blake2b_simd::Params;
+use sha2::{Digest, Sha256};
+
+use crate::transaction::{ScriptPublicKey, Transaction, TransactionOutput};
+
+const SUBNETWORK_ID_NATIVE: [u8; 20] = [0u8; 20];
+const ZERO_HASH: [u8; 32] = [0u8; 32];
+const SIG_HASH_MASK: u8 = 0b0000_0111;
+
+pub const SIG_HASH_ALL: SigHashType = SigHashType(0b0000_0001);
+pub const SIG_HASH_NONE: SigHashType = SigHashType(0b0000_0010);
+pub const SIG_HASH_SINGLE: SigHashType = SigHashType(0b0000_0100);
+pub const SIG_HASH_ANY_ONE_CAN_PAY: SigHashType = SigHashType(0b1000_0000);
+
+/// Which parts of the transaction a signature commits to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigHashType(u8);
+
+impl SigHashType {
+    /// Accepts the six combinations consensus allows.
+    pub fn from_u8(value: u8) -> Result<SigHashType, String> {
+        let base = value & !SIG_HASH_ANY_ONE_CAN_PAY.0;
+        if ![SIG_HASH_ALL.0, SIG_HASH_NONE.0, SIG_HASH_SINGLE.0].contains(&base) {
+            return Err(format!("Invalid sighash type {:#04x}", value));
+        }
+        Ok(SigHashType(value))
+    }
+
+    pub const fn to_u8(self) -> u8 {
+        self.0
+    }
+
+    pub fn is_sighash_all(self) -> bool {
+        self.0 & SIG_HASH_MASK == SIG_HASH_ALL.0
+    }
+
+    pub fn is_sighash_none(self) -> bool {
+        self.0 & SIG_HASH_MASK == SIG_HASH_NONE.0
+    }
+
+    pub fn is_sighash_single(self) -> bool {
+        self.0 & SIG_HASH_MASK == SIG_HASH_SINGLE.0
+    }
+
+    pub fn is_sighash_anyone_can_pay(self) -> bool {
+        self.0 & SIG_HASH_ANY_ONE_CAN_PAY.0 == SIG_HASH_ANY_ONE_CAN_PAY.0
+    }
+}
+
+impl std::fmt::Display for SigHashType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let base = if self.is_sighash_all() {
+            "ALL"
+        } else if self.is_sighash_none() {
+            "NONE"
+        } else {
+            "SINGLE"
+        };
+        if self.is_sighash_anyone_can_pay() {
+            write!(f, "{}|ANYONECANPAY", base)
+        } else {
+            write!(f, "{}", base)
+        }
+    }
+}
+
+fn signing_hasher() -> blake2b_simd::State {
+    Params::new()
+        .hash_length(32)
+        .key(b"TransactionSigningHash")
+        .to_state()
+}
+
+fn finalize(hasher: &blake2b_simd::State) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    output.copy_from_slice(hasher.finalize().as_bytes());
+    output
+}
+
+fn hash_script_public_key(hasher: &mut blake2b_simd::State, spk: &ScriptPublicKey) {
+    hasher.update(&spk.version.to_le_bytes());
+    hasher.update(&(spk.script.len() as u64).to_le_bytes());
+    hasher.update(&spk.script);
+}
+
+fn hash_output(hasher: &mut blake2b_simd::State, output: &TransactionOutput) {
+    hasher.update(&output.value.to_le_bytes());
+    hash_script_public_key(hasher, &output.script_public_key);
+}
+
+fn previous_outputs_hash(tx: &Transaction, hash_type: SigHashType) -> [u8; 32] {
+    if hash_type.is_sighash_anyone_can_pay() {
+        return ZERO_HASH;
+    }
+    let mut hasher = signing_hasher();
+    for input in &tx.inputs {
+        hasher.update(&input.previous_outpoint.transaction_id);
+        hasher.update(&input.previous_outpoint.index.to_le_bytes());
+    }
+    finalize(&hasher)
+}
+
+fn sequences_hash(tx: &Transaction, hash_type: SigHashType) -> [u8; 32] {
+    if hash_type.is_sighash_single() || hash_type.is_sighash_none() || hash_type.is_sighash_anyone_can_pay() {
+        return ZERO_HASH;
+    }
+    let mut hasher = signing_hasher();
+    for input in &tx.inputs {
+        hasher.update(&input.sequence.to_le_bytes());
+    }
+    finalize(&hasher)
+}
+
+fn sig_op_counts_hash(tx: &Transaction, hash_type: SigHashType) -> [u8; 32] {
+    if hash_type.is_sighash_anyone_can_pay() {
+        return ZERO_HASH;
+    }
+    let mut hasher = signing_hasher();
+    for input in &tx.inputs {
+        hasher.update(&[input.sig_op_count]);
+    }
+    finalize(&hasher)
+}
+
+fn outputs_hash(tx: &Transaction, hash_type: SigHashType, input_index: usize) -> [u8; 32] {
+    if hash_type.is_sighash_none() {
+        return ZERO_HASH;
+    }
+    let mut hasher = signing_hasher();
+    if hash_type.is_sighash_single() {
+        // Only the output at the same index, if there is one
+        match tx.outputs.get(input_index) {
+            Some(output) => hash_output(&mut hasher, output),
+            None => return ZERO_HASH,
+        }
+    } else {
+        for output in &tx.outputs {
+            hash_output(&mut hasher, output);
+        }
+    }
+    finalize(&hasher)
+}
+
+fn payload_hash(tx: &Transaction) -> [u8; 32] {
+    if tx.subnetwork_id == SUBNETWORK_ID_NATIVE {
+        return ZERO_HASH;
+    }
+    let mut hasher = signing_hasher();
+    hasher.update(&(tx.payload.len() as u64).to_le_bytes());
+    hasher.update(&tx.payload);
+    finalize(&hasher)
+}
+
+/// The hash a Schnorr signature for input `input_index` signs.
+pub fn calc_schnorr_signature_hash(tx: &Transaction, input_index: usize, hash_type: SigHashType) -> [u8; 32] {
+    let input = &tx.inputs[input_index];
+    let mut hasher = signing_hasher();
+    hasher.update(&tx.version.to_le_bytes());
+    hasher.update(&previous_outputs_hash(tx, hash_type));
+    hasher.update(&sequences_hash(tx, hash_type));
+    hasher.update(&sig_op_counts_hash(tx, hash_type));
+    hasher.update(&input.previous_outpoint.transaction_id);
+    hasher.update(&input.previous_outpoint.index.to_le_bytes());
+    hash_script_public_key(&mut hasher, &input.utxo_entry.script_public_key);
+    hasher.update(&input.utxo_entry.amount.to_le_bytes());
+    hasher.update(&input.sequence.to_le_bytes());
+    hasher.update(&[input.sig_op_count]);
+    hasher.update(&outputs_hash(tx, hash_type, input_index));
+    hasher.update(&tx.lock_time.to_le_bytes());
+    hasher.update(&tx.subnetwork_id);
+    hasher.update(&tx.gas.to_le_bytes());
+    hasher.update(&payload_hash(tx));
+    hasher.update(&[hash_type.to_u8()]);
+    finalize(&hasher)
+}
+
+/// The hash an ECDSA signature for input `input_index` signs.
+pub fn calc_ecdsa_signature_hash(tx: &Transaction, input_index: usize, hash_type: SigHashType) -> [u8; 32] {
+    let schnorr_hash = calc_schnorr_signature_hash(tx, input_index, hash_type);
+    let mut hasher = Sha256::new();
+    hasher.update(Sha256::digest(b"TransactionSigningHashECDSA"));
+    hasher.update(schnorr_hash);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{TransactionInput, TransactionOutpoint, UtxoEntry};
+
+    const SIG_HASH_ALL_ANYONE_CAN_PAY: SigHashType = SigHashType(SIG_HASH_ALL.0 | SIG_HASH_ANY_ONE_CAN_PAY.0);
+    const SIG_HASH_NONE_ANYONE_CAN_PAY: SigHashType = SigHashType(SIG_HASH_NONE.0 | SIG_HASH_ANY_ONE_CAN_PAY.0);
+    const SIG_HASH_SINGLE_ANYONE_CAN_PAY: SigHashType = SigHashType(SIG_HASH_SINGLE.0 | SIG_HASH_ANY_ONE_CAN_PAY.0);
+
+    // The transactions of rusty-kaspa's consensus-core sighash tests
+    fn native_transaction() -> Transaction {
+        let prev_tx_id = hex::decode("880eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3").unwrap();
+        let script_1 = hex::decode("208325613d2eeaf7176ac6c670b13c0043156c427438ed72d74b7800862ad884e8ac").unwrap();
+        let script_2 = hex::decode("20fcef4c106cf11135bbd70f02a726a92162d2fb8b22f0469126f800862ad884e8ac").unwrap();
+        let spk = |script: &Vec<u8>| ScriptPublicKey { version: 0, script: script.clone() };
+        let input = |index: u32, amount: u64, script: &Vec<u8>| TransactionInput {
+            previous_outpoint: TransactionOutpoint { transaction_id: prev_tx_id.clone(), index },
+            signature_script: vec![],
+            sequence: index as u64,
+            sig_op_count: 0,
+            utxo_entry: UtxoEntry { amount, script_public_key: spk(script), block_daa_score: 0, is_coinbase: false },
+        };
+
+        Transaction {
+            version: 0,
+            inputs: vec![input(0, 100, &script_1), input(1, 200, &script_2), input(2, 300, &script_2)],
+            outputs: vec![
+                TransactionOutput { value: 300, script_public_key: spk(&script_2) },
+                TransactionOutput { value: 300, script_public_key: spk(&script_1) },
+            ],
+            lock_time: 1615462089000,
+            subnetwork_id: SUBNETWORK_ID_NATIVE.to_vec(),
+            gas: 0,
+            payload: vec![],
+            mass: 0,
+        }
+    }
+
+    fn subnetwork_transaction() -> Transaction {
+        let mut tx = native_transaction();
+        tx.subnetwork_id = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        tx.gas = 250;
+        tx.payload = vec![10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20];
+        tx
+    }
+
+    fn check(name: &str, tx: &Transaction, hash_type: SigHashType, input_index: usize, expected: &str) {
+        assert_eq!(
+            hex::encode(calc_schnorr_signature_hash(tx, input_index, hash_type)),
+            expected,
+            "test {} failed",
+            name
+        );
+    }
+
+    #[test]
+    fn sighash_all() {
+        let tx = native_transaction();
+        check("native-all-0", &tx, SIG_HASH_ALL, 0, "03b7ac6927b2b67100734c3cc313ff8c2e8b3ce3e746d46dd660b706a916b1f5");
+
+        let mut modified = tx.clone();
+        modified.inputs[1].previous_outpoint.index = 2;
+        check("native-all-0-modify-input-1", &modified, SIG_HASH_ALL, 0, "a9f563d86c0ef19ec2e4f483901d202e90150580b6123c3d492e26e7965f488c");
+
+        let mut modified = tx.clone();
+        modified.outputs[1].value = 100;
+        check("native-all-0-modify-output-1", &modified, SIG_HASH_ALL, 0, "aad2b61bd2405dfcf7294fc2be85f325694f02dda22d0af30381cb50d8295e0a");
+
+        let mut modified = tx.clone();
+        modified.inputs[1].sequence = 12345;
+        check("native-all-0-modify-sequence-1", &modified, SIG_HASH_ALL, 0, "0818bd0a3703638d4f01014c92cf866a8903cab36df2fa2506dc0d06b94295e8");
+    }
+
+    #[test]
+    fn sighash_all_anyone_can_pay() {
+        let hash_type = SIG_HASH_ALL_ANYONE_CAN_PAY;
+        let tx = native_transaction();
+        let expected = "24821e466e53ff8e5fa93257cb17bb06131a48be4ef282e87f59d2bdc9afebc2";
+        check("native-all-anyonecanpay-0", &tx, hash_type, 0, expected);
+
+        let mut modified = tx.clone();
+        modified.inputs[0].previous_outpoint.index = 2;
+        check("native-all-anyonecanpay-0-modify-input-0", &modified, hash_type, 0, "d09cb639f335ee69ac71f2ad43fd9e59052d38a7d0638de4cf989346588a7c38");
+
+        // Other inputs are not committed to
+        let mut modified = tx.clone();
+        modified.inputs[1].previous_outpoint.index = 2;
+        check("native-all-anyonecanpay-0-modify-input-1", &modified, hash_type, 0, expected);
+
+        let mut modified = tx.clone();
+        modified.inputs[1].sequence = 12345;
+        check("native-all-anyonecanpay-0-modify-sequence", &modified, hash_type, 0, expected);
+    }
+
+    #[test]
+    fn sighash_none() {
+        let tx = native_transaction();
+        let expected = "38ce4bc93cf9116d2e377b33ff8449c665b7b5e2f2e65303c543b9afdaa4bbba";
+        check("native-none-0", &tx, SIG_HASH_NONE, 0, expected);
+
+        let mut modified = tx.clone();
+        modified.outputs[1].value = 100;
+        check("native-none-0-modify-output-1", &modified, SIG_HASH_NONE, 0, expected);
+
+        let mut modified = tx.clone();
+        modified.inputs[0].sequence = 12345;
+        check("native-none-0-modify-sequence-0", &modified, SIG_HASH_NONE, 0, "d9efdd5edaa0d3fd0133ee3ab731d8c20e0a1b9f3c0581601ae2075db1109268");
+
+        let mut modified = tx.clone();
+        modified.inputs[1].sequence = 12345;
+        check("native-none-0-modify-sequence-1", &modified, SIG_HASH_NONE, 0, expected);
+    }
+
+    #[test]
+    fn sighash_none_anyone_can_pay() {
+        let hash_type = SIG_HASH_NONE_ANYONE_CAN_PAY;
+        let tx = native_transaction();
+        check("native-none-anyonecanpay-0", &tx, hash_type, 0, "06aa9f4239491e07bb2b6bda6b0657b921aeae51e193d2c5bf9e81439cfeafa0");
+
+        let mut modified = tx.clone();
+        modified.inputs[0].utxo_entry.amount = 666;
+        check("native-none-anyonecanpay-0-modify-amount-spent", &modified, hash_type, 0, "f07f45f3634d3ea8c0f2cb676f56e20993edf9be07a83bf0dfdb3debcf1441bf");
+
+        let mut modified = tx.clone();
+        modified.inputs[0].utxo_entry.script_public_key.script.extend_from_slice(&[1, 2, 3]);
+        check("native-none-anyonecanpay-0-modify-script-public-key", &modified, hash_type, 0, "20a525c54dc33b2a61201f05233c086dbe8e06e9515775181ed96550b4f2d714");
+    }
+
+    #[test]
+    fn sighash_single() {
+        let tx = native_transaction();
+        let expected = "44a0b407ff7b239d447743dd503f7ad23db5b2ee4d25279bd3dffaf6b474e005";
+        check("native-single-0", &tx, SIG_HASH_SINGLE, 0, expected);
+
+        let mut modified = tx.clone();
+        modified.outputs[1].value = 100;
+        check("native-single-0-modify-output-1", &modified, SIG_HASH_SINGLE, 0, expected);
+
+        let mut modified = tx.clone();
+        modified.inputs[0].sequence = 12345;
+        check("native-single-0-modify-sequence-0", &modified, SIG_HASH_SINGLE, 0, "83796d22879718eee1165d4aace667bb6778075dab579c32c57be945f466a451");
+
+        let mut modified = tx.clone();
+        modified.inputs[1].sequence = 12345;
+        check("native-single-0-modify-sequence-1", &modified, SIG_HASH_SINGLE, 0, expected);
+
+        let expected = "022ad967192f39d8d5895d243e025ec14cc7a79708c5e364894d4eff3cecb1b0";
+        check("native-single-2-no-corresponding-output", &tx, SIG_HASH_SINGLE, 2, expected);
+        let mut modified = tx.clone();
+        modified.outputs[1].value = 100;
+        check("native-single-2-no-corresponding-output-modify-output-1", &modified, SIG_HASH_SINGLE, 2, expected);
+    }
+
+    #[test]
+    fn sighash_single_anyone_can_pay() {
+        let hash_type = SIG_HASH_SINGLE_ANYONE_CAN_PAY;
+        let tx = native_transaction();
+        check("native-single-anyonecanpay-0", &tx, hash_type, 0, "43b20aba775050cf9ba8d5e48fc7ed2dc6c071d23f30382aea58b7c59cfb8ed7");
+        check("native-single-anyonecanpay-2-no-corresponding-output", &tx, hash_type, 2, "846689131fb08b77f83af1d3901076732ef09d3f8fdff945be89aa4300562e5f");
+    }
+
+    #[test]
+    fn sighash_subnetwork() {
+        let tx = subnetwork_transaction();
+        check("subnetwork-all-0", &tx, SIG_HASH_ALL, 0, "b2f421c933eb7e1a91f1d9e1efa3f120fe419326c0dbac487752189522550e0c");
+
+        let mut modified = tx.clone();
+        modified.payload = vec![6, 6, 6, 4, 2, 0, 1, 3, 3, 7];
+        check("subnetwork-all-modify-payload", &modified, SIG_HASH_ALL, 0, "12ab63b9aea3d58db339245a9b6e9cb6075b2253615ce0fb18104d28de4435a1");
+
+        let mut modified = tx.clone();
+        modified.gas = 1234;
+        check("subnetwork-all-modify-gas", &modified, SIG_HASH_ALL, 0, "2501edfc0068d591160c4bd98646c6e6892cdc051182a8be3ccd6d67f104fd17");
+
+        let mut modified = tx.clone();
+        modified.subnetwork_id = vec![6, 6, 6, 4, 2, 0, 1, 3, 3, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        check("subnetwork-all-subnetwork-id", &modified, SIG_HASH_ALL, 0, "a5d1230ede0dfcfd522e04123a7bcd721462fed1d3a87352031a4f6e3c4389b6");
+    }
+
+    #[test]
+    fn sighash_type_from_u8() {
+        for value in [0x01, 0x02, 0x04, 0x81, 0x82, 0x84] {
+            assert_eq!(SigHashType::from_u8(value).unwrap().to_u8(), value);
+        }
+        for value in [0x00, 0x03, 0x80, 0x05, 0xff] {
+            assert!(SigHashType::from_u8(value).is_err());
+        }
+    }
+}
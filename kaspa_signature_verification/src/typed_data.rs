@@ -0,0 +1,308 @@
+// Typed data signing with domain separation.
+//
+// The signed digest commits to the domain and to the canonical JSON payload:
+//
+//   domain_hash  = double_sha256(DOMAIN_TAG || sha256(name) || sha256(network) || sha256(version) || expiry as u64 BE)
+//   payload_hash = double_sha256(canonical JSON of message)
+//   digest       = double_sha256(TYPED_DATA_PREFIX || domain_hash || payload_hash)
+//
+// Canonical JSON has object keys sorted by their UTF-8 bytes, no insignificant
+// whitespace and integer-only numbers, so every signer produces the same bytes.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use kaspa_addresses::Prefix;
+
+use crate::clearsign::network_name;
+use crate::double_sha256;
+
+const DOMAIN_TAG: &[u8] = b"KaspaTypedDataDomain(v1)";
+const TYPED_DATA_PREFIX: &[u8] = b"\x19Kaspa Signed Typed Data:\n";
+
+/// Where and until when a signed payload is valid.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Domain {
+    /// Application the payload is meant for
+    pub name: String,
+    /// mainnet, testnet, simnet or devnet
+    pub network: String,
+    pub version: String,
+    /// Unix time in seconds after which the payload is no longer accepted
+    pub expiry: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypedData {
+    pub domain: Domain,
+    pub message: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedTypedData {
+    pub domain: Domain,
+    pub message: Value,
+    pub address: String,
+    pub signature: String,
+}
+
+/// What the verifier expects the domain to be.
+pub struct ExpectedDomain<'a> {
+    pub name: &'a str,
+    pub version: Option<&'a str>,
+    /// Network the verifier runs on; payloads for any other network are replays
+    pub network: Option<Prefix>,
+}
+
+impl Domain {
+    pub fn hash(&self) -> [u8; 32] {
+        let mut data = DOMAIN_TAG.to_vec();
+        data.extend_from_slice(&Sha256::digest(self.name.as_bytes()));
+        data.extend_from_slice(&Sha256::digest(self.network.as_bytes()));
+        data.extend_from_slice(&Sha256::digest(self.version.as_bytes()));
+        data.extend_from_slice(&self.expiry.to_be_bytes());
+        double_sha256(&data)
+    }
+
+    /// Address prefix of the domain network; signers use it for the signing address.
+    pub fn prefix(&self) -> Result<Prefix, Box<dyn std::error::Error>> {
+        match self.network.as_str() {
+            "mainnet" => Ok(Prefix::Mainnet),
+            "testnet" => Ok(Prefix::Testnet),
+            "simnet" => Ok(Prefix::Simnet),
+            "devnet" => Ok(Prefix::Devnet),
+            other => Err(format!("Unknown domain network: {}", other).into()),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.name.trim().is_empty() {
+            return Err("The domain name must not be empty".into());
+        }
+        self.prefix()?;
+        Ok(())
+    }
+}
+
+impl TypedData {
+    pub fn parse(json: &str) -> Result<TypedData, Box<dyn std::error::Error>> {
+        let data: TypedData = serde_json::from_str(json)?;
+        data.domain.validate()?;
+        canonical_json(&data.message)?;
+        Ok(data)
+    }
+
+    /// The digest that gets signed.
+    pub fn digest(&self) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+        let payload_hash = double_sha256(canonical_json(&self.message)?.as_bytes());
+        let mut data = TYPED_DATA_PREFIX.to_vec();
+        data.extend_from_slice(&self.domain.hash());
+        data.extend_from_slice(&payload_hash);
+        Ok(double_sha256(&data))
+    }
+}
+
+impl SignedTypedData {
+    pub fn typed_data(&self) -> TypedData {
+        TypedData {
+            domain: self.domain.clone(),
+            message: self.message.clone(),
+        }
+    }
+
+    /// Checks everything except the signature itself: domain name and version, the
+    /// network against the expected one and the signing address, and the expiry against `now`.
+    pub fn check_domain(&self, expected: &ExpectedDomain, now: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.domain.validate()?;
+        if self.domain.name != expected.name {
+            return Err(format!(
+                "Wrong domain: payload is for '{}', expected '{}'",
+                self.domain.name, expected.name
+            )
+            .into());
+        }
+        if let Some(version) = expected.version.filter(|version| self.domain.version != *version) {
+            return Err(format!(
+                "Wrong domain version: payload is for '{}', expected '{}'",
+                self.domain.version, version
+            )
+            .into());
+        }
+        if let Some(network) = expected.network.filter(|network| self.domain.prefix().ok() != Some(*network)) {
+            return Err(format!(
+                "Wrong network: payload is for {}, expected {}",
+                self.domain.network,
+                network_name(network)
+            )
+            .into());
+        }
+        let address = kaspa_addresses::Address::try_from(self.address.as_str())?;
+        if network_name(address.prefix) != self.domain.network {
+            return Err(format!(
+                "Wrong network: payload is for {}, but the address is a {} address",
+                self.domain.network,
+                network_name(address.prefix)
+            )
+            .into());
+        }
+        if now >= self.domain.expiry {
+            return Err(format!("Payload expired at {} (now {})", self.domain.expiry, now).into());
+        }
+        Ok(())
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Serializes a JSON value in canonical form.
+pub fn canonical_json(value: &Value) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = String::new();
+    write_canonical(value, &mut out)?;
+    Ok(out)
+}
+
+fn write_canonical(value: &Value, out: &mut String) -> Result<(), Box<dyn std::error::Error>> {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => out.push_str(&serde_json::to_string(value)?),
+        Value::Number(number) => {
+            if !(number.is_i64() || number.is_u64()) {
+                return Err(format!("Only integer numbers can be signed, got {}", number).into());
+            }
+            out.push_str(&number.to_string());
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push(':');
+                write_canonical(&map[key], out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign_digest, verify_digest_signature};
+    use kaspa_addresses::{Address, Version};
+    use secp256k1::SecretKey;
+    use serde_json::json;
+
+    const PRIVATE_KEY: &str = "24cd8d2875e8e17e4f88fbc554834a89b7b3e0c6f9d84fae36883604359ba440";
+
+    fn typed_data(network: &str, expiry: u64) -> TypedData {
+        TypedData {
+            domain: Domain {
+                name: "example.org".to_string(),
+                network: network.to_string(),
+                version: "1".to_string(),
+                expiry,
+            },
+            message: json!({"action": "login", "nonce": 42}),
+        }
+    }
+
+    fn sign(data: &TypedData) -> SignedTypedData {
+        let private_key = SecretKey::from_slice(&hex::decode(PRIVATE_KEY).unwrap()).unwrap();
+        let public_key = private_key.public_key(&secp256k1::Secp256k1::new()).serialize();
+        let prefix = data.domain.prefix().unwrap();
+        SignedTypedData {
+            domain: data.domain.clone(),
+            message: data.message.clone(),
+            address: Address::new(prefix, Version::PubKey, &public_key[1..33]).to_string(),
+            signature: sign_digest(&data.digest().unwrap(), &private_key).unwrap(),
+        }
+    }
+
+    #[test]
+    fn canonical_json_form() {
+        let value: Value = serde_json::from_str(r#"{ "b": [1, {"z": null, "a": true}], "a": "xé\"", "B": -7 }"#).unwrap();
+        assert_eq!(canonical_json(&value).unwrap(), r#"{"B":-7,"a":"xé\"","b":[1,{"a":true,"z":null}]}"#);
+        assert!(canonical_json(&json!({"amount": 1.5})).is_err());
+        assert!(TypedData::parse(r#"{"domain":{"name":"a","network":"mainnet","version":"1","expiry":1},"message":[0.1]}"#).is_err());
+    }
+
+    #[test]
+    fn digest_commits_to_domain_and_payload() {
+        let data = typed_data("mainnet", 2_000_000_000);
+        let reordered: Value = serde_json::from_str(r#"{"nonce": 42, "action": "login"}"#).unwrap();
+        assert_eq!(TypedData { message: reordered, ..data.clone() }.digest().unwrap(), data.digest().unwrap());
+
+        let mut expected = TYPED_DATA_PREFIX.to_vec();
+        expected.extend_from_slice(&data.domain.hash());
+        expected.extend_from_slice(&double_sha256(br#"{"action":"login","nonce":42}"#));
+        assert_eq!(data.digest().unwrap(), double_sha256(&expected));
+
+        let mut changed = data.clone();
+        changed.domain.expiry += 1;
+        assert_ne!(changed.digest().unwrap(), data.digest().unwrap());
+        changed = data.clone();
+        changed.domain.network = "testnet".to_string();
+        assert_ne!(changed.digest().unwrap(), data.digest().unwrap());
+        changed = data.clone();
+        changed.message = json!({"action": "login", "nonce": 43});
+        assert_ne!(changed.digest().unwrap(), data.digest().unwrap());
+    }
+
+    #[test]
+    fn signed_on_every_network() {
+        for network in ["mainnet", "testnet", "simnet", "devnet"] {
+            let signed = sign(&typed_data(network, 2_000_000_000));
+            let expected = ExpectedDomain { name: "example.org", version: Some("1"), network: signed.domain.prefix().ok() };
+            assert!(signed.check_domain(&expected, 1_700_000_000).is_ok(), "{}", network);
+            assert!(verify_digest_signature(&signed.typed_data().digest().unwrap(), &signed.signature, &signed.address).unwrap());
+        }
+        assert!(typed_data("mainnet2", 1).domain.prefix().is_err());
+    }
+
+    #[test]
+    fn rejects_expired_and_wrong_domain() {
+        let signed = sign(&typed_data("mainnet", 2_000_000_000));
+        let expected = ExpectedDomain { name: "example.org", version: None, network: None };
+        assert!(signed.check_domain(&expected, 2_000_000_000).is_err());
+        assert!(signed.check_domain(&ExpectedDomain { name: "evil.example", ..expected }, 0).is_err());
+        assert!(signed.check_domain(&ExpectedDomain { version: Some("2"), ..expected }, 0).is_err());
+
+        let testnet = sign(&typed_data("testnet", 2_000_000_000));
+        let replayed = SignedTypedData { address: signed.address.clone(), ..testnet.clone() };
+        assert!(replayed.check_domain(&expected, 0).is_err());
+    }
+
+    #[test]
+    fn rejects_payloads_for_another_network() {
+        // Properly signed for testnet by the same key, then replayed to a mainnet verifier
+        let testnet = sign(&typed_data("testnet", 2_000_000_000));
+        assert!(verify_digest_signature(&testnet.typed_data().digest().unwrap(), &testnet.signature, &testnet.address).unwrap());
+        let anywhere = ExpectedDomain { name: "example.org", version: None, network: None };
+        assert!(testnet.check_domain(&anywhere, 0).is_ok());
+
+        let mainnet = ExpectedDomain { network: Some(Prefix::Mainnet), ..anywhere };
+        let error = testnet.check_domain(&mainnet, 0).unwrap_err().to_string();
+        assert_eq!(error, "Wrong network: payload is for testnet, expected mainnet");
+        assert!(sign(&typed_data("mainnet", 2_000_000_000)).check_domain(&mainnet, 0).is_ok());
+    }
+}
@@ -0,0 +1,317 @@
+// Vanity addresses: keys whose address starts or ends with a chosen pattern.
+//
+// Addresses are bech32, so every character is one of 32 and a pattern of n characters
+// takes about 32^n tries. The first character after "kaspa:" is always 'q' (the version
+// byte starts with five zero bits), so prefixes are matched after "kaspa:q". For Schnorr
+// addresses the next character carries two bits of the key and has 4 possible values;
+// ECDSA addresses always go on with "yp" (version bits 001, then the 02/03 key prefix).
+// Keys are either random or the indices below an xpub, searched in the same thread
+// layout as the xpub search.
+use kaspa_addresses::{Address, Prefix, Version};
+use rand::RngCore;
+use secp256k1::{Secp256k1, SecretKey};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{derive_chain_public_keys, KaspaXPub, ThreadError};
+
+const CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+// Values of the character after 'q' in Schnorr addresses: version bits 000, then two key bits
+const SCHNORR_SECOND: &str = "qpzr";
+// The characters after 'q' in every ECDSA address
+const ECDSA_START: &str = "yp";
+
+pub struct Pattern {
+    prefix: String,
+    suffix: String,
+    ecdsa: bool,
+}
+
+impl Pattern {
+    /// Validates the pattern (case-insensitively) against the bech32 alphabet and what
+    /// the address type allows. A prefix may be given with or without "kaspa:q".
+    pub fn new(prefix: Option<&str>, suffix: Option<&str>, ecdsa: bool) -> Result<Pattern, Box<dyn std::error::Error>> {
+        let normalize = |pattern: &str| -> Result<String, Box<dyn std::error::Error>> {
+            let pattern = pattern.trim().to_lowercase();
+            if let Some(c) = pattern.chars().find(|c| !CHARSET.contains(*c)) {
+                return Err(format!("'{}' never appears in an address: bech32 uses only {} (no 1, b, i or o)", c, CHARSET).into());
+            }
+            Ok(pattern)
+        };
+        let prefix = match prefix {
+            Some(prefix) => {
+                let prefix = prefix.trim().to_lowercase();
+                let prefix = match prefix.split_once(':') {
+                    Some((_, body)) => body.strip_prefix('q').ok_or("Kaspa addresses always start with 'q' after the ':'")?.to_string(),
+                    None => prefix,
+                };
+                normalize(&prefix)?
+            }
+            None => String::new(),
+        };
+        let suffix = normalize(suffix.unwrap_or(""))?;
+        if prefix.is_empty() && suffix.is_empty() {
+            return Err("Give a --prefix or --suffix to search for".into());
+        }
+        if ecdsa {
+            if !prefix.starts_with(ECDSA_START) && !ECDSA_START.starts_with(&prefix) {
+                return Err(format!("ECDSA addresses always start with kaspa:q{}", ECDSA_START).into());
+            }
+        } else if let Some(first) = prefix.chars().next().filter(|first| !SCHNORR_SECOND.contains(*first)) {
+            return Err(format!("Schnorr addresses can't start with kaspa:q{}: the next character is one of q, p, z, r", first).into());
+        }
+        Ok(Pattern { prefix, suffix, ecdsa })
+    }
+
+    /// Expected number of keys to try for one match.
+    pub fn difficulty(&self) -> f64 {
+        let prefix = match (self.ecdsa, self.prefix.len()) {
+            (_, 0) => 1.0,
+            (true, n) => 32f64.powi(n.saturating_sub(ECDSA_START.len()) as i32),
+            (false, n) => 4.0 * 32f64.powi(n as i32 - 1),
+        };
+        prefix * 32f64.powi(self.suffix.len() as i32)
+    }
+
+    pub fn matches(&self, address: &Address) -> bool {
+        let address = address.to_string();
+        let body = address.split_once(':').map_or(address.as_str(), |(_, body)| body);
+        body[1..].starts_with(&self.prefix) && body.ends_with(&self.suffix)
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.prefix.is_empty(), self.suffix.is_empty()) {
+            (false, true) => write!(f, "q{}...", self.prefix),
+            (true, false) => write!(f, "...{}", self.suffix),
+            _ => write!(f, "q{}...{}", self.prefix, self.suffix),
+        }
+    }
+}
+
+fn address_for_key(public_key: &[u8; 33], prefix: Prefix, ecdsa: bool) -> Address {
+    if ecdsa {
+        Address::new(prefix, Version::PubKeyECDSA, public_key)
+    } else {
+        Address::new(prefix, Version::PubKey, &public_key[1..33])
+    }
+}
+
+/// A rough duration for an estimate that may be anything from seconds to ages.
+pub fn format_estimate(seconds: f64) -> String {
+    match seconds {
+        s if s < 60.0 => format!("{:.0} seconds", s),
+        s if s < 3600.0 => format!("{:.1} minutes", s / 60.0),
+        s if s < 86400.0 => format!("{:.1} hours", s / 3600.0),
+        s if s < 365.0 * 86400.0 => format!("{:.1} days", s / 86400.0),
+        s => format!("{:.3e} years", s / (365.0 * 86400.0)),
+    }
+}
+
+// Waits for the threads, calling `progress` with the number of keys tried every few seconds
+fn wait_with_progress<T>(
+    handles: Vec<thread::JoinHandle<Result<(), ThreadError>>>,
+    tried: &Mutex<u64>,
+    found: &Mutex<Option<T>>,
+    progress: &dyn Fn(u64, Duration),
+) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    let start_time = Instant::now();
+    let mut last_report = Instant::now();
+    while !handles.iter().all(|handle| handle.is_finished()) {
+        thread::sleep(Duration::from_millis(100));
+        if last_report.elapsed() >= Duration::from_secs(5) {
+            progress(*tried.lock().unwrap(), start_time.elapsed());
+            last_report = Instant::now();
+        }
+    }
+
+    // Wait for all threads to complete
+    for handle in handles {
+        match handle.join() {
+            Ok(result) => result?,
+            Err(_) => return Err("Thread panicked".into()),
+        }
+    }
+    Ok(found.lock().unwrap().take())
+}
+
+/// Tries random keys until one matches, on `num_threads` threads.
+pub fn search_random_parallel(
+    pattern: Arc<Pattern>,
+    prefix: Prefix,
+    num_threads: usize,
+    progress: &dyn Fn(u64, Duration),
+) -> Result<(SecretKey, Address), Box<dyn std::error::Error>> {
+    let found = Arc::new(Mutex::new(None));
+    let tried = Arc::new(Mutex::new(0u64));
+    let should_exit = Arc::new(Mutex::new(false));
+    let mut handles = vec![];
+
+    for _ in 0..num_threads {
+        let pattern = Arc::clone(&pattern);
+        let found = Arc::clone(&found);
+        let tried = Arc::clone(&tried);
+        let should_exit = Arc::clone(&should_exit);
+
+        let handle = thread::spawn(move || -> Result<(), ThreadError> {
+            let secp = Secp256k1::new();
+            let mut rng = rand::thread_rng();
+            let mut key_bytes = [0u8; 32];
+            loop {
+                // Check if another thread found a key, counting tries in batches
+                if *should_exit.lock().unwrap() {
+                    return Ok(());
+                }
+                for _ in 0..1000 {
+                    rng.fill_bytes(&mut key_bytes);
+                    let Ok(private_key) = SecretKey::from_slice(&key_bytes) else { continue };
+                    let public_key = secp256k1::PublicKey::from_secret_key(&secp, &private_key).serialize();
+                    let address = address_for_key(&public_key, prefix, pattern.ecdsa);
+                    if pattern.matches(&address) {
+                        *found.lock().unwrap() = Some((private_key, address));
+                        *should_exit.lock().unwrap() = true;
+                        return Ok(());
+                    }
+                }
+                *tried.lock().unwrap() += 1000;
+            }
+        });
+        handles.push(handle);
+    }
+
+    wait_with_progress(handles, &tried, &found, progress)?.ok_or_else(|| "Search ended without a match".into())
+}
+
+/// Searches m/<chain>/<i> for the indices in `indices` below an xpub, returning a
+/// matching index and its address.
+pub fn search_xpub_parallel(
+    pattern: Arc<Pattern>,
+    xpub: &KaspaXPub,
+    chain: u32,
+    indices: std::ops::Range<u32>,
+    prefix: Prefix,
+    num_threads: usize,
+    progress: &dyn Fn(u64, Duration),
+) -> Result<Option<(u32, Address)>, Box<dyn std::error::Error>> {
+    let (start, end) = (indices.start, indices.end);
+    if end > 1 << 31 {
+        return Err("Index range goes past the last non-hardened index (2147483647)".into());
+    }
+    let count = end.saturating_sub(start);
+    let xpub = Arc::new(xpub.clone());
+    let found = Arc::new(Mutex::new(None));
+    let tried = Arc::new(Mutex::new(0u64));
+    let should_exit = Arc::new(Mutex::new(false));
+
+    // Calculate chunk size for each thread
+    let chunk_size = std::cmp::max(1, count.div_ceil(u32::try_from(num_threads).unwrap_or(u32::MAX).max(1)));
+    let mut handles = vec![];
+
+    for chunk_start in (start..end).step_by(chunk_size as usize) {
+        let chunk_end = std::cmp::min(chunk_start + chunk_size, end);
+        let pattern = Arc::clone(&pattern);
+        let xpub = Arc::clone(&xpub);
+        let found = Arc::clone(&found);
+        let tried = Arc::clone(&tried);
+        let should_exit = Arc::clone(&should_exit);
+
+        let handle = thread::spawn(move || -> Result<(), ThreadError> {
+            // Derive in batches so other threads' finds and progress are seen
+            for batch_start in (chunk_start..chunk_end).step_by(1000) {
+                if *should_exit.lock().unwrap() {
+                    return Ok(());
+                }
+                let batch_count = std::cmp::min(1000, chunk_end - batch_start);
+                let keys = derive_chain_public_keys(&xpub, chain, batch_start, batch_count)
+                    .map_err(|e| ThreadError(e.to_string()))?;
+                for (index, public_key) in keys {
+                    let address = address_for_key(&public_key.serialize(), prefix, pattern.ecdsa);
+                    if pattern.matches(&address) {
+                        let mut found = found.lock().unwrap();
+                        // Keep the lowest index if several threads match
+                        if found.as_ref().is_none_or(|(found_index, _)| index < *found_index) {
+                            *found = Some((index, address));
+                        }
+                        *should_exit.lock().unwrap() = true;
+                        return Ok(());
+                    }
+                }
+                *tried.lock().unwrap() += batch_count as u64;
+            }
+            Ok(())
+        });
+        handles.push(handle);
+    }
+
+    wait_with_progress(handles, &tried, &found, progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHNORR: &str = "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh";
+    const ECDSA: &str = "kaspa:qyp3ht82sn98y8y46elv4nseh3ye5a7qxuntepee4a3hhnvf4w40qkq62g5j9h7";
+
+    #[test]
+    fn validates_patterns() {
+        assert!(Pattern::new(Some("qd6"), None, false).is_ok());
+        assert!(Pattern::new(Some("kaspa:qQD6"), Some("LQEH"), false).is_ok());
+        assert!(Pattern::new(None, Some("eh"), true).is_ok());
+        assert!(Pattern::new(Some("y"), None, true).is_ok());
+        assert!(Pattern::new(Some("yp3"), None, true).is_ok());
+
+        assert!(Pattern::new(None, None, false).is_err());
+        assert!(Pattern::new(Some(" "), Some(""), false).is_err());
+        // Not in the bech32 alphabet
+        assert!(Pattern::new(Some("qb"), None, false).is_err());
+        assert!(Pattern::new(None, Some("1"), false).is_err());
+        // "kaspa:" is always followed by 'q'
+        assert!(Pattern::new(Some("kaspa:pq"), None, false).is_err());
+        // The next character is one of q, p, z, r for Schnorr and always "yp" for ECDSA
+        assert!(Pattern::new(Some("y"), None, false).is_err());
+        assert!(Pattern::new(Some("q"), None, true).is_err());
+        assert!(Pattern::new(Some("yq"), None, true).is_err());
+    }
+
+    #[test]
+    fn difficulty_counts_free_characters() {
+        assert_eq!(Pattern::new(None, Some("eh"), false).unwrap().difficulty(), 1024.0);
+        // The first Schnorr character has only 4 values
+        assert_eq!(Pattern::new(Some("q"), None, false).unwrap().difficulty(), 4.0);
+        assert_eq!(Pattern::new(Some("qd6"), Some("h"), false).unwrap().difficulty(), 4.0 * 1024.0 * 32.0);
+        // "yp" is free for ECDSA
+        assert_eq!(Pattern::new(Some("yp"), None, true).unwrap().difficulty(), 1.0);
+        assert_eq!(Pattern::new(Some("yp3h"), None, true).unwrap().difficulty(), 1024.0);
+    }
+
+    #[test]
+    fn matches_after_the_leading_q() {
+        let schnorr = Address::try_from(SCHNORR).unwrap();
+        let ecdsa = Address::try_from(ECDSA).unwrap();
+        assert!(Pattern::new(Some("kaspa:qqd6e"), None, false).unwrap().matches(&schnorr));
+        assert!(Pattern::new(Some("qd6e"), Some("lqeh"), false).unwrap().matches(&schnorr));
+        assert!(!Pattern::new(Some("qd6f"), None, false).unwrap().matches(&schnorr));
+        assert!(!Pattern::new(None, Some("lqej"), false).unwrap().matches(&schnorr));
+        assert!(Pattern::new(Some("yp3ht"), Some("9h7"), true).unwrap().matches(&ecdsa));
+        assert!(!Pattern::new(Some("yp3hu"), None, true).unwrap().matches(&ecdsa));
+
+        // Testnet addresses match after their own prefix
+        let testnet = Address::try_from("kaspatest:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9ssxd5s7gn").unwrap();
+        assert!(Pattern::new(Some("qd6e"), Some("7gn"), false).unwrap().matches(&testnet));
+    }
+
+    #[test]
+    fn xpub_search_survives_any_thread_count() {
+        let xpub = crate::load_xpub_from_string(
+            "xpub6CWzHoo4nAhEpu2Pwyo5j6Em2Rz9y77gFPGPhZAHWyTGTPBRDCU95ABmQceLqsKyboi3BKwhnjyVgGx3FHAqPNCfSVsse8QKz1bSc69Gsxf",
+        )
+        .unwrap();
+        let pattern = Arc::new(Pattern::new(None, Some("lqeh"), false).unwrap());
+        // More threads than fit in a u32 used to become a zero divisor
+        let found = search_xpub_parallel(pattern, &xpub, 0, 0..3, Prefix::Mainnet, 1 << 32, &|_, _| {}).unwrap();
+        assert_eq!(found.map(|(index, address)| (index, address.to_string())), Some((0, SCHNORR.to_string())));
+    }
+}
@@ -0,0 +1,230 @@
+// Derivation schemes of the Kaspa wallets people bring seeds from.
+//
+// Current wallets use BIP44 with coin type 111111 and Schnorr addresses, but older
+// wallets used coin type 972 with hardened change and index steps, the Ledger app
+// spreads funds over several accounts, and some wallets hold ECDSA addresses. `detect-wallet` derives the receive and change
+// addresses of every scheme from the seed and reports the ones that reproduce a
+// known address, in the same thread layout as the xpub search.
+use kaspa_addresses::{Address, Version};
+use kaspa_bip32::{ChildNumber, DerivationPath};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::{create_master_key, derive_chain_public_keys, ThreadError};
+
+pub struct WalletScheme {
+    pub name: &'static str,
+    /// The wallets known to use the scheme
+    pub wallets: &'static str,
+    /// Account key path, with {account} for the account number
+    pub account_path: &'static str,
+    /// Account numbers searched
+    pub accounts: Range<u32>,
+    /// Phrase lengths these wallets create
+    pub word_counts: &'static [usize],
+    pub ecdsa: bool,
+    /// Change and index steps are hardened, so addresses need the private key
+    pub hardened_children: bool,
+}
+
+pub const WALLET_SCHEMES: &[WalletScheme] = &[
+    WalletScheme {
+        name: "bip44",
+        wallets: "Kaspa NG, Kaspium, Tangem, kaspad's kaspawallet, 24-word web wallets and the first Ledger account",
+        account_path: "m/44'/111111'/{account}'",
+        accounts: 0..1,
+        word_counts: &[12, 24],
+        ecdsa: false,
+        hardened_children: false,
+    },
+    WalletScheme {
+        name: "legacy-972",
+        wallets: "KDX and 12-word web wallets (wallet.kaspanet.io)",
+        account_path: "m/44'/972/{account}'",
+        accounts: 0..1,
+        word_counts: &[12],
+        ecdsa: false,
+        hardened_children: true,
+    },
+    WalletScheme {
+        name: "legacy-972-hardened",
+        wallets: "wallets deriving coin type 972 as a hardened index",
+        account_path: "m/44'/972'/{account}'",
+        accounts: 0..1,
+        word_counts: &[12, 24],
+        ecdsa: false,
+        hardened_children: false,
+    },
+    WalletScheme {
+        name: "ledger",
+        wallets: "Ledger Kaspa app (one account per Ledger Live account)",
+        account_path: "m/44'/111111'/{account}'",
+        // Account 0 is the bip44 path, which is searched already
+        accounts: 1..5,
+        word_counts: &[24],
+        ecdsa: false,
+        hardened_children: false,
+    },
+    WalletScheme {
+        name: "bip44-ecdsa",
+        wallets: "wallets holding ECDSA addresses",
+        account_path: "m/44'/111111'/{account}'",
+        accounts: 0..1,
+        word_counts: &[12, 24],
+        ecdsa: true,
+        hardened_children: false,
+    },
+];
+
+/// Where a scheme reproduced the address.
+pub struct SchemeMatch {
+    pub scheme: &'static WalletScheme,
+    /// Full derivation path of the address
+    pub path: String,
+}
+
+impl WalletScheme {
+    /// Path of an address below the account key, e.g. <chain>'/<i>' for hardened children
+    pub fn child_path(&self, chain: impl std::fmt::Display, index: impl std::fmt::Display) -> String {
+        let hardened = if self.hardened_children { "'" } else { "" };
+        format!("{}{}/{}{}", chain, hardened, index, hardened)
+    }
+}
+
+// Searches one account of one scheme, both chains, for the address payload
+fn search_account(
+    seed: &[u8],
+    scheme: &WalletScheme,
+    account: u32,
+    target: &Address,
+    window: u32,
+) -> Result<Option<String>, ThreadError> {
+    if (target.version == Version::PubKeyECDSA) != scheme.ecdsa || target.version == Version::ScriptHash {
+        return Ok(None);
+    }
+    let account_path = scheme.account_path.replace("{account}", &account.to_string());
+    let master_key = create_master_key(seed).map_err(|e| ThreadError(e.to_string()))?;
+    let account_key = master_key.derive_path(&DerivationPath::from_str(&account_path)?)?;
+    let account_xpub = account_key.public_key();
+    for chain in 0..2 {
+        // Derive in batches so a large window never holds every key at once
+        for batch_start in (0..window).step_by(1000) {
+            let batch_count = std::cmp::min(1000, window - batch_start);
+            let keys = if scheme.hardened_children {
+                let chain_key = account_key.derive_child(ChildNumber::new(chain, true)?)?;
+                (batch_start..batch_start + batch_count)
+                    .map(|index| Ok((index, *chain_key.derive_child(ChildNumber::new(index, true)?)?.public_key().public_key())))
+                    .collect::<Result<Vec<_>, ThreadError>>()?
+            } else {
+                derive_chain_public_keys(&account_xpub, chain, batch_start, batch_count)
+                    .map_err(|e| ThreadError(e.to_string()))?
+            };
+            for (index, public_key) in keys {
+                let public_key = public_key.serialize();
+                let payload = if scheme.ecdsa { &public_key[..] } else { &public_key[1..33] };
+                if target.payload[..] == *payload {
+                    return Ok(Some(format!("{}/{}", account_path, scheme.child_path(chain, index))));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Tries every scheme and account in parallel, searching the first `window` receive and
+/// change addresses of each. Returns all matches, in catalogue order.
+pub fn detect_wallet_parallel(
+    seed: &[u8],
+    address: &str,
+    window: u32,
+    num_threads: usize,
+) -> Result<Vec<SchemeMatch>, Box<dyn std::error::Error>> {
+    let target = Arc::new(Address::try_from(address)?);
+    let seed = Arc::new(seed.to_vec());
+    let jobs: Vec<(usize, u32)> = WALLET_SCHEMES
+        .iter()
+        .enumerate()
+        .flat_map(|(i, scheme)| scheme.accounts.clone().map(move |account| (i, account)))
+        .collect();
+    let found = Arc::new(Mutex::new(Vec::new()));
+
+    // Calculate chunk size for each thread
+    let chunk_size = std::cmp::max(1, jobs.len().div_ceil(num_threads));
+    let mut handles = vec![];
+
+    for chunk in jobs.chunks(chunk_size) {
+        let chunk = chunk.to_vec();
+        let seed = Arc::clone(&seed);
+        let target = Arc::clone(&target);
+        let found = Arc::clone(&found);
+
+        let handle = thread::spawn(move || -> Result<(), ThreadError> {
+            for (scheme, account) in chunk {
+                if let Some(path) = search_account(&seed, &WALLET_SCHEMES[scheme], account, &target, window)? {
+                    found.lock().unwrap().push((scheme, account, path));
+                }
+            }
+            Ok(())
+        });
+        handles.push(handle);
+    }
+
+    // Wait for all threads to complete
+    for handle in handles {
+        match handle.join() {
+            Ok(result) => result?,
+            Err(_) => return Err("Thread panicked".into()),
+        }
+    }
+
+    let mut found = std::mem::take(&mut *found.lock().unwrap());
+    found.sort();
+    Ok(found
+        .into_iter()
+        .map(|(scheme, _, path)| SchemeMatch { scheme: &WALLET_SCHEMES[scheme], path })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_seed_from_mnemonic;
+    use bip39::{Language, Mnemonic};
+
+    fn detect(address: &str) -> Vec<(&'static str, String)> {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = generate_seed_from_mnemonic(&Mnemonic::parse_in(Language::English, phrase).unwrap());
+        detect_wallet_parallel(&seed, address, 5, 4)
+            .unwrap()
+            .into_iter()
+            .map(|found| (found.scheme.name, found.path))
+            .collect()
+    }
+
+    #[test]
+    fn each_scheme_is_detected_once() {
+        let cases = [
+            ("bip44", "m/44'/111111'/0'/0/3", "kaspa:qqwn552u0tdqgcggarzeh2x5nh8lmkgzfg4nqay8vtl9pf975aw3ww9w4xy35"),
+            ("legacy-972", "m/44'/972/0'/1'/2'", "kaspa:qz33na7stu2mxr2v4wqc0clzzy8chkmav7dgvwhlmpkkj5xx4aa2uruz2kyfd"),
+            ("legacy-972-hardened", "m/44'/972'/0'/0/0", "kaspa:qqm6x579fdepfzh6lyzvyhu8ddfhepj7rke57xjjcwy5qt5hztf3260p8kwls"),
+            ("ledger", "m/44'/111111'/2'/0/1", "kaspa:qqsefeq2mfulrlr53yufgu2hzc4a36fn26cljh2cv23vvelk0vuuqkzsj6t9p"),
+            ("bip44-ecdsa", "m/44'/111111'/0'/1/0", "kaspa:qypjwc3zk7lk9l7wgjpv83q24gj7y8hsd5nueywzz76xfxpdqskn8ls3um27fck"),
+        ];
+        for (scheme, path, address) in cases {
+            assert_eq!(detect(address), vec![(scheme, path.to_string())], "{}", scheme);
+        }
+        assert_eq!(cases.len(), WALLET_SCHEMES.len());
+    }
+
+    #[test]
+    fn misses_outside_the_window() {
+        // m/44'/111111'/0'/0/5, one past the window, and a P2SH address no scheme derives
+        assert!(detect("kaspa:qpfx360pfz3gzq5pykfujhjffn4runcm96hduj79plndvsss355dwdtmje2ut").is_empty());
+        // m/44'/972/0'/0'/5' of the hardened legacy scheme, and its non-hardened m/44'/972/0'/1/2
+        assert!(detect("kaspa:qrvuhj37nnx4qavhpv7rjll6e2r9wmtxn5ds62n04fw36xc73ppjk0qsvzmqh").is_empty());
+        assert!(detect("kaspa:qq72u6f6ghqm4quqp4wpyvvfjgvqy5nplzgkt78uwlwg3tccexmsq8tjfxmya").is_empty());
+        assert!(detect("kaspa:pzjc2vah3l7tndy3rpcgku88lkydukq77443l3sfgf559dg94n06jnyyselc8").is_empty());
+    }
+}
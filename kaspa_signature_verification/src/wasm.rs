@@ -0,0 +1,140 @@
+// WebAssembly bindings (feature "wasm") for verifying in the browser:
+//
+//     cargo build --lib --release --target wasm32-unknown-unknown --features wasm
+//     wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/kaspa_message_signer.wasm
+//
+// The secp256k1 C library is compiled for wasm32 too, which needs clang. The tests
+// below run headlessly under Node with wasm-bindgen-test-runner (see .cargo/config.toml):
+//
+//     cargo test --lib --target wasm32-unknown-unknown --features wasm
+use kaspa_addresses::{Address, Prefix};
+use wasm_bindgen::prelude::*;
+
+use crate::{derive_xpub_address, load_xpub_from_string};
+
+fn js_error(e: impl ToString) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// A parsed address.
+#[wasm_bindgen]
+pub struct AddressInfo {
+    network: String,
+    version: u8,
+    payload: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl AddressInfo {
+    /// "mainnet", "testnet", "simnet" or "devnet"
+    #[wasm_bindgen(getter)]
+    pub fn network(&self) -> String {
+        self.network.clone()
+    }
+
+    /// 0 Schnorr public key, 1 ECDSA public key, 8 script hash
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+}
+
+/// Checks a hex compact ECDSA signature of `message` by `address`.
+/// Returns false for a wrong signature and throws for malformed input.
+#[wasm_bindgen(js_name = verifySignature)]
+pub fn verify_signature(message: &str, signature: &str, address: &str) -> Result<bool, JsError> {
+    crate::verify_signature(message, signature.trim(), address.trim()).map_err(js_error)
+}
+
+/// Checks a hex BIP340 Schnorr signature of `message` by a Schnorr `address`.
+/// Returns false for a wrong signature and throws for malformed input.
+#[wasm_bindgen(js_name = verifySchnorrSignature)]
+pub fn verify_schnorr_signature(message: &str, signature: &str, address: &str) -> Result<bool, JsError> {
+    crate::verify_schnorr_signature(message, signature.trim(), address.trim()).map_err(js_error)
+}
+
+/// Parses and validates an address, throwing if it is invalid.
+#[wasm_bindgen(js_name = parseAddress)]
+pub fn parse_address(address: &str) -> Result<AddressInfo, JsError> {
+    let address = Address::try_from(address.trim()).map_err(js_error)?;
+    let network = match address.prefix {
+        Prefix::Mainnet => "mainnet",
+        Prefix::Testnet => "testnet",
+        Prefix::Simnet => "simnet",
+        Prefix::Devnet => "devnet",
+    };
+    Ok(AddressInfo {
+        network: network.to_string(),
+        version: address.version as u8,
+        payload: address.payload.to_vec(),
+    })
+}
+
+/// Mainnet address at m/<chain>/<index> below an account xpub.
+#[wasm_bindgen(js_name = deriveAddress)]
+pub fn derive_address(xpub: &str, chain: u32, index: u32) -> Result<String, JsError> {
+    let xpub = load_xpub_from_string(xpub.trim()).map_err(js_error)?;
+    Ok(derive_xpub_address(&xpub, chain, index).map_err(js_error)?.to_string())
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    // Same vectors as tests/serve.rs, tests/c/ffi_harness.c and tests/python
+    const ADDRESS: &str = "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh";
+    const XPUB: &str = "xpub6CWzHoo4nAhEpu2Pwyo5j6Em2Rz9y77gFPGPhZAHWyTGTPBRDCU95ABmQceLqsKyboi3BKwhnjyVgGx3FHAqPNCfSVsse8QKz1bSc69Gsxf";
+    const SIGNATURE: &str = "7fcc8bfcf6a256aba35e7b6bbb31ca17cbbff944bf7680a2c2b1044df6e7bc6a4b0f290f135f3640c6462bbfdf014acf5a091e235382410d2589e0f006ffe340";
+
+    #[wasm_bindgen_test]
+    fn verifies_signatures() {
+        // JsError has no Debug, so results are compared through ok()
+        assert_eq!(verify_signature("login 42", SIGNATURE, ADDRESS).ok(), Some(true));
+        assert_eq!(verify_signature("login 43", SIGNATURE, ADDRESS).ok(), Some(false));
+        assert!(verify_signature("login 42", "zz", ADDRESS).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn parses_addresses() {
+        let Ok(info) = parse_address(ADDRESS) else { panic!("{} should parse", ADDRESS) };
+        assert_eq!(info.network(), "mainnet");
+        assert_eq!(info.version(), 0);
+        assert_eq!(info.payload().len(), 32);
+        assert!(parse_address("kaspa:notanaddress").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn derives_child_addresses() {
+        assert_eq!(derive_address(XPUB, 0, 0).ok().as_deref(), Some(ADDRESS));
+        assert!(derive_address(XPUB, 1, 0).ok().is_some_and(|address| address != ADDRESS));
+        assert!(derive_address("xpub-garbage", 0, 0).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn fixture_vectors() {
+        let vectors: serde_json::Value = serde_json::from_str(include_str!("../tests/vectors/message_signing.json")).unwrap();
+        let text = |value: &serde_json::Value, name: &str| value[name].as_str().unwrap().to_string();
+        let verify = |algorithm: &str, vector: &serde_json::Value| {
+            let (message, signature, address) = (text(vector, "message"), text(vector, "signature"), text(vector, "address"));
+            match algorithm {
+                "ecdsa" => verify_signature(&message, &signature, &address),
+                _ => verify_schnorr_signature(&message, &signature, &address),
+            }
+        };
+        for algorithm in ["ecdsa", "schnorr"] {
+            for vector in vectors[algorithm].as_array().unwrap() {
+                assert_eq!(verify(algorithm, vector).ok(), Some(true), "{} vector {}", algorithm, text(vector, "message"));
+            }
+        }
+        for invalid in vectors["invalid"].as_array().unwrap() {
+            let algorithm = text(invalid, "algorithm");
+            assert_ne!(verify(&algorithm, invalid).ok(), Some(true), "{} {} verified", algorithm, text(invalid, "case"));
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// Single private keys as written on paper wallets and used by older Kaspa tools:
+// 64 hex digits, or a WIF-style string. WIF is base58 of a version byte, the 32-byte
+// key and 0x01 (the public key is compressed), followed by the first 4 bytes of the
+// double SHA-256 of those bytes as a checksum. As in Bitcoin, the version byte is
+// 0x80 for mainnet keys and 0xef for testnet keys. Simnet and devnet have no version
+// byte of their own, so their keys are not written as WIF.
+use kaspa_addresses::Prefix;
+use secp256k1::SecretKey;
+
+use crate::double_sha256;
+
+const MAINNET_VERSION: u8 = 0x80;
+const TESTNET_VERSION: u8 = 0xef;
+const COMPRESSED: u8 = 0x01;
+
+/// The version byte for keys of a network, failing for networks without one.
+pub fn version(prefix: Prefix) -> Result<u8, Box<dyn std::error::Error>> {
+    match prefix {
+        Prefix::Mainnet => Ok(MAINNET_VERSION),
+        Prefix::Testnet => Ok(TESTNET_VERSION),
+        other => Err(format!("WIF has no version byte for {} keys: only mainnet and testnet keys can be exported", other).into()),
+    }
+}
+
+pub fn encode(private_key: &SecretKey, prefix: Prefix) -> Result<String, Box<dyn std::error::Error>> {
+    let mut bytes = vec![version(prefix)?];
+    bytes.extend_from_slice(&private_key.secret_bytes());
+    bytes.push(COMPRESSED);
+    let checksum = double_sha256(&bytes);
+    bytes.extend_from_slice(&checksum[..4]);
+    Ok(bs58::encode(bytes).into_string())
+}
+
+pub fn decode(wif: &str) -> Result<(SecretKey, Prefix), Box<dyn std::error::Error>> {
+    let bytes = bs58::decode(wif).into_vec().map_err(|e| format!("Invalid WIF private key: {}", e))?;
+    if bytes.len() != 38 || bytes[33] != COMPRESSED {
+        return Err(format!("Invalid WIF private key: expected 38 bytes ending in 01, got {} bytes", bytes.len()).into());
+    }
+    let (payload, checksum) = bytes.split_at(34);
+    if double_sha256(payload)[..4] != *checksum {
+        return Err("Invalid WIF private key: checksum mismatch".into());
+    }
+    let prefix = match payload[0] {
+        MAINNET_VERSION => Prefix::Mainnet,
+        TESTNET_VERSION => Prefix::Testnet,
+        other => return Err(format!("Invalid WIF private key: unknown version byte {:02x}", other).into()),
+    };
+    Ok((SecretKey::from_slice(&payload[1..33])?, prefix))
+}
+
+/// A private key given as 64 hex digits (a mainnet key) or in WIF.
+pub fn parse_private_key(input: &str) -> Result<(SecretKey, Prefix), Box<dyn std::error::Error>> {
+    let input = input.trim();
+    let hex_key = input.strip_prefix("0x").unwrap_or(input);
+    if hex_key.len() == 64 && hex_key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok((SecretKey::from_slice(&hex::decode(hex_key)?)?, Prefix::Mainnet));
+    }
+    decode(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_one() -> SecretKey {
+        SecretKey::from_slice(&[[0u8; 31].as_slice(), &[1]].concat()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(encode(&key_one(), Prefix::Mainnet).unwrap(), "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn");
+        assert_eq!(encode(&key_one(), Prefix::Testnet).unwrap(), "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN87JcbXMTcA");
+
+        let private_key = SecretKey::from_slice(&hex::decode("24cd8d2875e8e17e4f88fbc554834a89b7b3e0c6f9d84fae36883604359ba440").unwrap()).unwrap();
+        for prefix in [Prefix::Mainnet, Prefix::Testnet] {
+            let wif = encode(&private_key, prefix).unwrap();
+            assert_eq!(decode(&wif).unwrap(), (private_key, prefix));
+            assert_eq!(parse_private_key(&format!(" {}\n", wif)).unwrap(), (private_key, prefix));
+        }
+        // Simnet and devnet keys would come back as testnet keys
+        assert!(encode(&private_key, Prefix::Simnet).is_err());
+        assert!(encode(&private_key, Prefix::Devnet).is_err());
+        let hex_key = "0x24cd8d2875e8e17e4f88fbc554834a89b7b3e0c6f9d84fae36883604359ba440";
+        assert_eq!(parse_private_key(hex_key).unwrap(), (private_key, Prefix::Mainnet));
+    }
+
+    #[test]
+    fn rejects_bad_keys() {
+        // One character changed breaks the checksum
+        assert!(decode("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWo").is_err());
+        // Uncompressed-key WIF has no 0x01 suffix
+        assert!(decode("5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf").is_err());
+        // Not base58, and a well-formed string with an unknown version byte
+        assert!(decode("0OIl").is_err());
+        let mut bytes = vec![0x81];
+        bytes.extend_from_slice(&key_one().secret_bytes());
+        bytes.push(COMPRESSED);
+        let checksum = double_sha256(&bytes);
+        bytes.extend_from_slice(&checksum[..4]);
+        assert!(decode(&bs58::encode(bytes).into_string()).is_err());
+        // Zero is not a valid private key
+        assert!(parse_private_key(&"00".repeat(32)).is_err());
+        assert!(parse_private_key(&"24".repeat(31)).is_err());
+    }
+}
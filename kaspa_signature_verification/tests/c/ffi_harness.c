@@ -0,0 +1,68 @@
+/* Exercises the C ABI: build against include/ and link libkaspa_message_signer. */
+#include <stdio.h>
+#include <string.h>
+
+#include "kaspa_message_signer.h"
+
+#define ADDRESS "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh"
+#define XPUB "xpub6CWzHoo4nAhEpu2Pwyo5j6Em2Rz9y77gFPGPhZAHWyTGTPBRDCU95ABmQceLqsKyboi3BKwhnjyVgGx3FHAqPNCfSVsse8QKz1bSc69Gsxf"
+#define SIGNATURE "7fcc8bfcf6a256aba35e7b6bbb31ca17cbbff944bf7680a2c2b1044df6e7bc6a4b0f290f135f3640c6462bbfdf014acf5a091e235382410d2589e0f006ffe340"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            const char *error = kaspa_last_error();                   \
+            fprintf(stderr, "%s:%d: check failed: %s (last error: %s)\n", \
+                    __FILE__, __LINE__, #cond, error ? error : "none"); \
+            failures++;                                               \
+        }                                                             \
+    } while (0)
+
+static void test_verify(void) {
+    CHECK(kaspa_verify_signature("login 42", SIGNATURE, ADDRESS) == 1);
+    CHECK(kaspa_verify_signature("login 43", SIGNATURE, ADDRESS) == 0);
+    CHECK(kaspa_verify_signature("login 42", "zz", ADDRESS) == KASPA_ERR_INVALID_INPUT);
+    CHECK(kaspa_last_error() != NULL);
+    CHECK(kaspa_verify_signature(NULL, SIGNATURE, ADDRESS) == KASPA_ERR_NULL_POINTER);
+    kaspa_string_free(NULL);
+}
+
+static void test_address_parse(void) {
+    KaspaAddressInfo info;
+    CHECK(kaspa_address_parse(ADDRESS, &info) == KASPA_OK);
+    CHECK(kaspa_last_error() == NULL);
+    CHECK(info.network == 0 && info.version == 0 && info.payload_len == 32);
+    CHECK(info.payload[0] == 0x1b && info.payload[31] == 0x58);
+    CHECK(kaspa_address_parse("kaspa:notanaddress", &info) == KASPA_ERR_INVALID_INPUT);
+    CHECK(kaspa_address_parse(ADDRESS, NULL) == KASPA_ERR_NULL_POINTER);
+}
+
+static void test_xpub(void) {
+    char *address = kaspa_xpub_derive_address(XPUB, 0, 0);
+    CHECK(address != NULL && strcmp(address, ADDRESS) == 0);
+    kaspa_string_free(address);
+    CHECK(kaspa_xpub_derive_address("xpub-garbage", 0, 0) == NULL);
+
+    uint32_t index = 99;
+    CHECK(kaspa_xpub_find_address(XPUB, ADDRESS, 20, &index) == 1);
+    CHECK(index == 0);
+    address = kaspa_xpub_derive_address(XPUB, 1, 0);
+    CHECK(kaspa_xpub_find_address(XPUB, address, 20, &index) == 0);
+    kaspa_string_free(address);
+    CHECK(kaspa_xpub_find_address(XPUB, ADDRESS, 0, &index) == KASPA_ERR_WINDOW);
+    CHECK(kaspa_xpub_find_address(XPUB, ADDRESS, KASPA_MAX_SEARCH_WINDOW + 1, &index) == KASPA_ERR_WINDOW);
+}
+
+int main(void) {
+    test_verify();
+    test_address_parse();
+    test_xpub();
+    if (failures > 0) {
+        fprintf(stderr, "%d check(s) failed\n", failures);
+        return 1;
+    }
+    printf("all FFI checks passed\n");
+    return 0;
+}
@@ -0,0 +1,15 @@
+// Helpers for reading the JSON fixtures in tests/vectors, shared by the
+// integration tests and the binary's unit tests.
+use serde_json::Value;
+
+pub fn fixture(json: &str) -> Value {
+    serde_json::from_str(json).expect("fixture is valid JSON")
+}
+
+pub fn field<'a>(value: &'a Value, name: &str) -> &'a str {
+    value[name].as_str().unwrap_or_else(|| panic!("fixture entry has no string '{}'", name))
+}
+
+pub fn entries<'a>(value: &'a Value, name: &str) -> &'a Vec<Value> {
+    value[name].as_array().unwrap_or_else(|| panic!("fixture has no array '{}'", name))
+}
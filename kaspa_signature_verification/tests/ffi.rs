@@ -0,0 +1,37 @@
+// Builds tests/c/ffi_harness.c against the generated header and the cdylib, then runs it.
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[test]
+fn c_harness_passes() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // Building this test builds the cdylib into deps/; a separate `cargo build` also copies it next to the binary
+    let bin_dir = Path::new(env!("CARGO_BIN_EXE_kaspa_message_signer")).parent().unwrap();
+    let lib_dirs = [bin_dir.join("deps"), bin_dir.to_path_buf()];
+    let harness: PathBuf = std::env::temp_dir().join(format!("kaspa_ffi_harness_{}", std::process::id()));
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let status = Command::new(&compiler)
+        .arg(manifest_dir.join("tests/c/ffi_harness.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .args(lib_dirs.iter().flat_map(|dir| ["-L".as_ref(), dir.as_os_str()]))
+        .arg("-lkaspa_message_signer")
+        .arg("-o")
+        .arg(&harness)
+        .status()
+        .unwrap_or_else(|e| panic!("cannot run C compiler '{}': {}", compiler, e));
+    assert!(status.success(), "C harness failed to compile");
+
+    let library_path = if cfg!(target_os = "macos") { "DYLD_LIBRARY_PATH" } else { "LD_LIBRARY_PATH" };
+    let output = Command::new(&harness).env(library_path, std::env::join_paths(&lib_dirs).unwrap()).output().unwrap();
+    let _ = std::fs::remove_file(&harness);
+    assert!(
+        output.status.success(),
+        "C harness failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
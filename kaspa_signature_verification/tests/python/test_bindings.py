@@ -0,0 +1,118 @@
+# Tests for the Python bindings. Build them first with `maturin develop`, then run `pytest`.
+import json
+import pathlib
+import threading
+
+import pytest
+
+import kaspa_message_signer as kms
+
+# Same vectors as tests/serve.rs and tests/c/ffi_harness.c
+ADDRESS = "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh"
+XPUB = "xpub6CWzHoo4nAhEpu2Pwyo5j6Em2Rz9y77gFPGPhZAHWyTGTPBRDCU95ABmQceLqsKyboi3BKwhnjyVgGx3FHAqPNCfSVsse8QKz1bSc69Gsxf"
+# ECDSA signature of "login 42" by ADDRESS (m/44'/111111'/0'/0/0 of "abandon ... about")
+SIGNATURE = "7fcc8bfcf6a256aba35e7b6bbb31ca17cbbff944bf7680a2c2b1044df6e7bc6a4b0f290f135f3640c6462bbfdf014acf5a091e235382410d2589e0f006ffe340"
+
+# JSON fixtures shared with tests/vectors.rs
+VECTORS = pathlib.Path(__file__).parent.parent / "vectors"
+
+
+def load_vectors(name):
+    return json.loads((VECTORS / name).read_text(encoding="utf-8"))
+
+
+def test_verify_signature():
+    assert kms.verify_signature("login 42", SIGNATURE, ADDRESS) is True
+    assert kms.verify_signature("login 43", SIGNATURE, ADDRESS) is False
+
+
+def test_verify_signature_rejects_malformed_input():
+    with pytest.raises(ValueError):
+        kms.verify_signature("login 42", "zz", ADDRESS)
+    with pytest.raises(ValueError):
+        kms.verify_signature("login 42", SIGNATURE, "kaspa:notanaddress")
+
+
+VERIFIERS = {"ecdsa": kms.verify_signature, "schnorr": kms.verify_schnorr_signature}
+
+
+def test_message_signing_vectors():
+    vectors = load_vectors("message_signing.json")
+    for algorithm, verify in VERIFIERS.items():
+        for vector in vectors[algorithm]:
+            assert verify(vector["message"], vector["signature"], vector["address"]) is True, vector["message"]
+    for invalid in vectors["invalid"]:
+        verify = VERIFIERS[invalid["algorithm"]]
+        try:
+            result = verify(invalid["message"], invalid["signature"], invalid["address"])
+        except ValueError:
+            continue
+        assert result is False, invalid["case"]
+
+
+def test_verify_schnorr_signature():
+    vector = load_vectors("message_signing.json")["schnorr"][0]
+    assert kms.verify_schnorr_signature(vector["message"], vector["signature"], vector["address"]) is True
+    assert kms.verify_schnorr_signature(vector["message"] + "!", vector["signature"], vector["address"]) is False
+    # An ECDSA signature is not a Schnorr signature, and ECDSA addresses have no x-only key
+    assert kms.verify_schnorr_signature("login 42", SIGNATURE, ADDRESS) is False
+    with pytest.raises(ValueError):
+        kms.verify_schnorr_signature("login 42", "zz", ADDRESS)
+
+
+def test_address_vectors():
+    for vector in load_vectors("kaspa_addresses.json")["vectors"]:
+        for key in (vector["xpub"], vector["kpub"]):
+            xpub = kms.load_xpub(key)
+            for entry in vector["addresses"]:
+                chain, index = (int(step) for step in entry["path"].split("/")[-2:])
+                assert xpub.derive_address(index, chain=chain) == entry["address"]
+
+
+def test_invalid_xpub_vectors():
+    for invalid in load_vectors("bip32.json")["invalid"]:
+        with pytest.raises(ValueError):
+            kms.load_xpub(invalid["key"])
+
+
+def test_load_xpub_and_derive():
+    xpub = kms.load_xpub(XPUB)
+    assert xpub.derive_address(0) == ADDRESS
+    assert kms.derive_address(XPUB, 0) == ADDRESS
+    assert kms.ExtendedPublicKey(XPUB).derive_address(0, chain=1) != ADDRESS
+
+
+def test_load_xpub_rejects_garbage():
+    with pytest.raises(ValueError):
+        kms.load_xpub("xpub-garbage")
+
+
+def test_find_address():
+    xpub = kms.load_xpub(XPUB)
+    assert xpub.find_address(ADDRESS, window=20) == 0
+    assert xpub.find_address(xpub.derive_address(7), window=20, threads=2) == 7
+    assert xpub.find_address(xpub.derive_address(0, chain=1), window=20) is None
+    for window in (0, 10_000_001, 2**32 - 1):
+        with pytest.raises(ValueError):
+            xpub.find_address(ADDRESS, window=window)
+
+
+def test_find_address_releases_the_gil():
+    xpub = kms.load_xpub(XPUB)
+    target = xpub.derive_address(5000)
+    ticks = []
+    done = threading.Event()
+
+    def ticker():
+        while not done.is_set():
+            ticks.append(1)
+            done.wait(0.001)
+
+    thread = threading.Thread(target=ticker)
+    thread.start()
+    try:
+        assert xpub.find_address(target, window=6000, threads=1) == 5000
+    finally:
+        done.set()
+        thread.join()
+    assert len(ticks) > 1
@@ -0,0 +1,222 @@
+// Integration tests for `serve`, run against the binary listening on localhost.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+
+const ADDRESS: &str = "kaspa:qqd6e65yefepe9wk0m9vuxdufxd80sphy67gwwd0vdaumzdt4tc9s3qt0lqeh";
+const XPUB: &str = "xpub6CWzHoo4nAhEpu2Pwyo5j6Em2Rz9y77gFPGPhZAHWyTGTPBRDCU95ABmQceLqsKyboi3BKwhnjyVgGx3FHAqPNCfSVsse8QKz1bSc69Gsxf";
+// ECDSA signature of "login 42" by ADDRESS (m/44'/111111'/0'/0/0 of "abandon ... about")
+const SIGNATURE: &str = "7fcc8bfcf6a256aba35e7b6bbb31ca17cbbff944bf7680a2c2b1044df6e7bc6a4b0f290f135f3640c6462bbfdf014acf5a091e235382410d2589e0f006ffe340";
+
+struct TestServer {
+    child: Child,
+    address: String,
+}
+
+impl TestServer {
+    fn start(args: &[&str]) -> TestServer {
+        let child = Command::new(env!("CARGO_BIN_EXE_kaspa_message_signer"))
+            .args(["serve", "--bind", "127.0.0.1:0"])
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("start server");
+        // Wrapped first so the process is killed even if it never starts listening
+        let mut server = TestServer { child, address: String::new() };
+        let mut stdout = BufReader::new(server.child.stdout.take().unwrap());
+        let mut line = String::new();
+        loop {
+            line.clear();
+            assert!(stdout.read_line(&mut line).unwrap() > 0, "server exited before listening");
+            if let Some(address) = line.trim().strip_prefix("Listening on http://") {
+                server.address = address.to_string();
+                return server;
+            }
+        }
+    }
+
+    fn request(&self, method: &str, path: &str, body: &str) -> (u16, serde_json::Value) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            self.address,
+            body.len(),
+            body
+        )
+        .unwrap();
+        read_response(stream)
+    }
+
+    fn post(&self, path: &str, body: serde_json::Value) -> (u16, serde_json::Value) {
+        self.request("POST", path, &body.to_string())
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn read_response(mut stream: TcpStream) -> (u16, serde_json::Value) {
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response.split(' ').nth(1).unwrap().parse().unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn verify_accepts_valid_and_rejects_tampered_signatures() {
+    let server = TestServer::start(&[]);
+    let (status, reply) = server.post(
+        "/verify",
+        serde_json::json!({ "address": ADDRESS, "message": "login 42", "signature": SIGNATURE }),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(reply["valid"], true);
+
+    let (status, reply) = server.post(
+        "/verify",
+        serde_json::json!({ "address": ADDRESS, "message": "login 43", "signature": SIGNATURE }),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(reply["valid"], false);
+}
+
+#[test]
+fn verify_checks_schnorr_personal_message_signatures() {
+    let server = TestServer::start(&[]);
+    let (status, reply) = server.post(
+        "/verify",
+        serde_json::json!({
+            "address": "kaspa:qr0lr4ml9fn3chekrqmjdkergxl93l4wrk3dankcgvjq776s9wn9jkdskewva",
+            "message": "Hello Kaspa!",
+            "signature": "eb9e8a3c547eb91b6a7592644f328f0648bdd21aba3cd44787d429d4d790aa8b962745691f3b472ed8d65f3b770ecb4f777bd17b1d309100919b53e0e206b4c6",
+            "algorithm": "schnorr",
+        }),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(reply["valid"], true);
+}
+
+#[test]
+fn recover_lists_the_signer_address() {
+    let server = TestServer::start(&[]);
+    let (status, reply) = server.post("/recover", serde_json::json!({ "message": "login 42", "signature": SIGNATURE }));
+    assert_eq!(status, 200);
+    assert!(reply["addresses"].as_array().unwrap().iter().any(|address| address == ADDRESS));
+}
+
+#[test]
+fn match_xpub_finds_the_index_within_the_window_limit() {
+    let server = TestServer::start(&["--max-window", "100"]);
+    let (status, reply) = server.post("/match-xpub", serde_json::json!({ "xpub": XPUB, "address": ADDRESS, "window": 10 }));
+    assert_eq!(status, 200);
+    assert_eq!(reply["found"], true);
+    assert_eq!(reply["path"], "m/0/0");
+
+    let (status, _) = server.post("/match-xpub", serde_json::json!({ "xpub": XPUB, "address": ADDRESS, "window": 101 }));
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn match_xpub_uses_the_descriptor_network_and_address_type() {
+    let server = TestServer::start(&[]);
+    let testnet = format!("kaspatest:pk({}/<0;1>/*)", XPUB);
+    let testnet_address = "kaspatest:qp6r0d88yj4fazlj057wc35245jfgs87n9jn6nahfg223996dfukvf8wm4lf9";
+    let (status, reply) = server.post("/match-xpub", serde_json::json!({ "xpub": testnet, "address": testnet_address, "window": 10 }));
+    assert_eq!(status, 200);
+    assert_eq!(reply["path"], "m/0/1");
+
+    let ecdsa = format!("kaspa:pkecdsa({}/<0;1>/*)", XPUB);
+    let ecdsa_address = "kaspa:qyp3ht82sn98y8y46elv4nseh3ye5a7qxuntepee4a3hhnvf4w40qkq62g5j9h7";
+    let (status, reply) = server.post("/match-xpub", serde_json::json!({ "xpub": ecdsa, "address": ecdsa_address, "window": 10 }));
+    assert_eq!(status, 200);
+    assert_eq!(reply["path"], "m/0/0");
+
+    // A bare xpub still means mainnet Schnorr addresses
+    let (status, reply) = server.post("/match-xpub", serde_json::json!({ "xpub": XPUB, "address": testnet_address, "window": 10 }));
+    assert_eq!(status, 200);
+    assert_eq!(reply["found"], false);
+}
+
+#[test]
+fn json_rpc_calls_methods_and_reports_errors() {
+    let server = TestServer::start(&[]);
+    let (status, reply) = server.post(
+        "/rpc",
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "verify",
+            "params": { "address": ADDRESS, "message": "login 42", "signature": SIGNATURE },
+        }),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(reply["id"], 1);
+    assert_eq!(reply["result"]["valid"], true);
+
+    let (_, reply) = server.post("/rpc", serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "missing" }));
+    assert_eq!(reply["error"]["code"], -32601);
+}
+
+#[test]
+fn signing_is_disabled_by_default() {
+    let server = TestServer::start(&[]);
+    let (status, reply) = server.post("/sign", serde_json::json!({ "message": "login 42" }));
+    assert_eq!(status, 403);
+    assert!(reply["error"].is_string());
+
+    let (_, reply) = server.request("GET", "/health", "");
+    assert_eq!(reply["signing"], false);
+}
+
+#[test]
+fn oversized_bodies_are_refused() {
+    let server = TestServer::start(&["--max-body", "256"]);
+    let message = "x".repeat(300);
+    let (status, _) = server.post(
+        "/verify",
+        serde_json::json!({ "address": ADDRESS, "message": message, "signature": SIGNATURE }),
+    );
+    assert_eq!(status, 413);
+}
+
+#[test]
+fn requests_over_the_concurrency_limit_get_503() {
+    let server = TestServer::start(&["--max-concurrent", "1"]);
+
+    // Hold the only slot with a request whose body hasn't fully arrived. Bodies up to
+    // 1 KiB are read before the request is handed over, so this one is larger.
+    let mut held = TcpStream::connect(&server.address).unwrap();
+    let message = "x".repeat(2000);
+    let body = serde_json::json!({ "address": ADDRESS, "message": message, "signature": SIGNATURE }).to_string();
+    write!(
+        held,
+        "POST /verify HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        server.address,
+        body.len(),
+        &body[..10]
+    )
+    .unwrap();
+    held.flush().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    let (status, _) = server.post("/verify", serde_json::json!({}));
+    assert_eq!(status, 503);
+
+    // Once the held request finishes, the slot is free again
+    held.write_all(&body.as_bytes()[10..]).unwrap();
+    let (status, reply) = read_response(held);
+    assert_eq!(status, 200);
+    assert_eq!(reply["valid"], false);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    let (status, _) = server.request("GET", "/health", "");
+    assert_eq!(status, 200);
+}
@@ -0,0 +1,173 @@
+// Test vectors from the JSON fixtures in tests/vectors, shared with the bindings' tests.
+// The fixtures were cross-checked against an independent implementation of BIP32,
+// the Kaspa address encoding, RFC 6979 ECDSA and BIP340.
+use std::str::FromStr;
+
+use bip39::{Language, Mnemonic};
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_bip32::{DerivationPath, ExtendedPrivateKey, Prefix as KeyPrefix, SecretKey as KaspaSecretKey};
+use kaspa_message_signer::{
+    derive_xpub_address, double_sha256, load_xpub_from_string, recover_signature_addresses, sign_message,
+    verify_schnorr_signature, verify_signature,
+};
+use secp256k1::SecretKey;
+
+mod common;
+use common::{entries, field, fixture};
+
+#[test]
+fn bip39_vectors() {
+    let vectors = fixture(include_str!("vectors/bip39.json"));
+    let passphrase = field(&vectors, "passphrase");
+    for vector in entries(&vectors, "vectors") {
+        let phrase = field(vector, "mnemonic");
+        let mnemonic = Mnemonic::from_entropy(&hex::decode(field(vector, "entropy")).unwrap()).unwrap();
+        assert_eq!(mnemonic.to_string(), phrase);
+        assert_eq!(Mnemonic::parse_in(Language::English, phrase).unwrap(), mnemonic);
+        assert_eq!(hex::encode(mnemonic.to_seed(passphrase)), field(vector, "seed"), "seed of '{}'", phrase);
+    }
+    for invalid in entries(&vectors, "invalid") {
+        assert!(
+            Mnemonic::parse_in(Language::English, field(invalid, "mnemonic")).is_err(),
+            "{} accepted",
+            field(invalid, "reason")
+        );
+    }
+}
+
+#[test]
+fn bip32_vectors() {
+    let vectors = fixture(include_str!("vectors/bip32.json"));
+    for vector in entries(&vectors, "vectors") {
+        let master = ExtendedPrivateKey::<KaspaSecretKey>::new(hex::decode(field(vector, "seed")).unwrap()).unwrap();
+        for chain in entries(vector, "chains") {
+            let path = field(chain, "path");
+            let key = master.clone().derive_path(&DerivationPath::from_str(path).unwrap()).unwrap();
+            assert_eq!(*key.to_string(KeyPrefix::XPRV), field(chain, "xprv"), "xprv at {}", path);
+            assert_eq!(key.public_key().to_string(Some(KeyPrefix::XPUB)), field(chain, "xpub"), "xpub at {}", path);
+
+            // The xpub parser used everywhere else reads the same key back
+            let xpub = load_xpub_from_string(field(chain, "xpub")).unwrap();
+            assert_eq!(xpub.to_string(Some(KeyPrefix::XPUB)), field(chain, "xpub"));
+        }
+    }
+    for invalid in entries(&vectors, "invalid") {
+        assert!(load_xpub_from_string(field(invalid, "key")).is_err(), "{} accepted", field(invalid, "reason"));
+    }
+}
+
+#[test]
+fn kaspa_address_vectors() {
+    let vectors = fixture(include_str!("vectors/kaspa_addresses.json"));
+    for vector in entries(&vectors, "vectors") {
+        let mnemonic = Mnemonic::parse_in(Language::English, field(vector, "mnemonic")).unwrap();
+        let master = ExtendedPrivateKey::<KaspaSecretKey>::new(mnemonic.to_seed(field(vector, "passphrase"))).unwrap();
+        assert_eq!(hex::encode(master.public_key().fingerprint()), field(vector, "master_fingerprint"));
+
+        let account_path = DerivationPath::from_str(field(vector, "account_path")).unwrap();
+        let account_xpub = master.clone().derive_path(&account_path).unwrap().public_key();
+        assert_eq!(account_xpub.to_string(Some(KeyPrefix::XPUB)), field(vector, "xpub"));
+
+        for entry in entries(vector, "addresses") {
+            let path = field(entry, "path");
+            let expected = field(entry, "address");
+
+            // From the private key, as the signer derives it
+            let key = master.clone().derive_path(&DerivationPath::from_str(path).unwrap()).unwrap();
+            let public_key = key.public_key().public_key().serialize();
+            assert_eq!(Address::new(Prefix::Mainnet, Version::PubKey, &public_key[1..33]).to_string(), expected);
+
+            // From the account xpub, as watch-only tools derive it
+            let steps: Vec<u32> = path.rsplit('/').take(2).map(|step| step.parse().unwrap()).collect();
+            assert_eq!(derive_xpub_address(&account_xpub, steps[1], steps[0]).unwrap().to_string(), expected, "{}", path);
+        }
+    }
+
+    let testnet = &vectors["testnet"];
+    let mnemonic = Mnemonic::parse_in(Language::English, field(testnet, "mnemonic")).unwrap();
+    let key = ExtendedPrivateKey::<KaspaSecretKey>::new(mnemonic.to_seed(""))
+        .unwrap()
+        .derive_path(&DerivationPath::from_str(field(testnet, "path")).unwrap())
+        .unwrap();
+    let public_key = key.public_key().public_key().serialize();
+    assert_eq!(Address::new(Prefix::Testnet, Version::PubKey, &public_key[1..33]).to_string(), field(testnet, "address"));
+
+    for invalid in entries(&vectors, "invalid") {
+        assert!(Address::try_from(field(invalid, "address")).is_err(), "{} accepted", field(invalid, "reason"));
+    }
+}
+
+#[test]
+fn kpub_round_trips() {
+    let vectors = fixture(include_str!("vectors/kaspa_addresses.json"));
+    for vector in entries(&vectors, "vectors") {
+        let (xpub, kpub) = (field(vector, "xpub"), field(vector, "kpub"));
+        assert_eq!(load_xpub_from_string(xpub).unwrap().to_string(Some(KeyPrefix::KPUB)), kpub);
+        assert_eq!(load_xpub_from_string(kpub).unwrap().to_string(Some(KeyPrefix::XPUB)), xpub);
+        assert_eq!(
+            derive_xpub_address(&load_xpub_from_string(kpub).unwrap(), 0, 0).unwrap().to_string(),
+            field(&entries(vector, "addresses")[0], "address")
+        );
+    }
+}
+
+#[test]
+fn ecdsa_message_signing_vectors() {
+    let vectors = fixture(include_str!("vectors/message_signing.json"));
+    for vector in entries(&vectors, "ecdsa") {
+        let (message, address, signature) = (field(vector, "message"), field(vector, "address"), field(vector, "signature"));
+        let private_key = SecretKey::from_slice(&hex::decode(field(vector, "private_key")).unwrap()).unwrap();
+        assert_eq!(sign_message(message, &private_key).unwrap(), signature, "signature of {:?}", message);
+        assert!(verify_signature(message, signature, address).unwrap());
+
+        let signers = recover_signature_addresses(&double_sha256(message.as_bytes()), signature).unwrap();
+        assert!(signers.iter().any(|signer| signer.to_string() == address));
+    }
+}
+
+#[test]
+fn invalid_ecdsa_signatures_are_rejected() {
+    let vectors = fixture(include_str!("vectors/message_signing.json"));
+    for invalid in entries(&vectors, "invalid").iter().filter(|invalid| field(invalid, "algorithm") == "ecdsa") {
+        let result = verify_signature(field(invalid, "message"), field(invalid, "signature"), field(invalid, "address"));
+        assert!(!matches!(result, Ok(true)), "{} verified", field(invalid, "case"));
+    }
+}
+
+#[test]
+fn schnorr_message_signing_vectors() {
+    let vectors = fixture(include_str!("vectors/message_signing.json"));
+    for vector in entries(&vectors, "schnorr") {
+        let (message, address, signature) = (field(vector, "message"), field(vector, "address"), field(vector, "signature"));
+        assert!(verify_schnorr_signature(message, signature, address).unwrap(), "signature of {:?}", message);
+        assert!(!verify_schnorr_signature(&format!("{}!", message), signature, address).unwrap());
+    }
+}
+
+#[test]
+fn invalid_schnorr_signatures_are_rejected() {
+    let vectors = fixture(include_str!("vectors/message_signing.json"));
+    for invalid in entries(&vectors, "invalid").iter().filter(|invalid| field(invalid, "algorithm") == "schnorr") {
+        let result = verify_schnorr_signature(field(invalid, "message"), field(invalid, "signature"), field(invalid, "address"));
+        assert!(!matches!(result, Ok(true)), "{} verified", field(invalid, "case"));
+    }
+}
+
+#[test]
+fn message_signatures_are_network_agnostic() {
+    let vectors = fixture(include_str!("vectors/message_signing.json"));
+    for algorithm in ["ecdsa", "schnorr"] {
+        for vector in entries(&vectors, algorithm) {
+            let (message, signature) = (field(vector, "message"), field(vector, "signature"));
+            let address = Address::try_from(field(vector, "address")).unwrap();
+            for prefix in [Prefix::Testnet, Prefix::Simnet, Prefix::Devnet] {
+                let other = Address::new(prefix, address.version, &address.payload).to_string();
+                let verified = match algorithm {
+                    "ecdsa" => verify_signature(message, signature, &other),
+                    _ => verify_schnorr_signature(message, signature, &other),
+                };
+                assert!(verified.unwrap(), "{} signature of {:?} on {}", algorithm, message, other);
+            }
+        }
+    }
+}